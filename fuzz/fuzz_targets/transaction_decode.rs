@@ -0,0 +1,31 @@
+// Copyright (C) 2020 Second State.
+// This file is part of Pallet-SSVM.
+
+// Pallet-SSVM is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+
+// Pallet-SSVM is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+//! Decode arbitrary bytes as raw Ethereum transactions, which must fail
+//! without panicking on malformed input. Run with
+//! `cargo fuzz run transaction_decode` from the `fuzz` directory.
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use pallet_ssvm::Transaction;
+
+fuzz_target!(|data: &[u8]| {
+    if let Ok(transaction) = Transaction::decode(data) {
+        let _ = transaction.signing_hash();
+        let _ = transaction.recover_sender();
+    }
+});
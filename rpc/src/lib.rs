@@ -0,0 +1,501 @@
+// Copyright (C) 2020 Second State.
+// This file is part of Pallet-SSVM.
+
+// Pallet-SSVM is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+
+// Pallet-SSVM is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+//! Ethereum JSON-RPC interface of the SSVM module, served by the node on top
+//! of the `SsvmApi` runtime API.
+
+mod pubsub;
+mod types;
+
+pub use crate::pubsub::{EthPubSub, EthPubSubApi};
+pub use crate::types::{
+    Block, BlockNumber, BlockTag, FeeHistory, Header, Log, LogFilter, PubSubResult, Receipt,
+    SubscriptionKind, ValueOrArray,
+};
+pub use pallet_ssvm::CallRequest;
+
+use jsonrpc_core::{Error, ErrorCode, Result};
+use jsonrpc_derive::rpc;
+use pallet_ssvm::runtime_api::SsvmApi;
+use pallet_ssvm::{CallInfo, EthBlockHeader, ExecutionOutcome};
+use sp_api::ProvideRuntimeApi;
+use sp_blockchain::HeaderBackend;
+use sp_core::{Bytes, H160, H256, U256};
+use sp_runtime::{
+    generic::BlockId,
+    traits::{Block as BlockT, SaturatedConversion, UniqueSaturatedInto, Zero},
+    Permill,
+};
+use std::{marker::PhantomData, sync::Arc};
+
+/// Error code of a failed runtime API call.
+const RUNTIME_ERROR: i64 = 1;
+/// Error code of a reverted execution, as returned by Ethereum clients.
+const EXECUTION_ERROR: i64 = 3;
+/// Gas limit of calls that do not set one.
+const DEFAULT_GAS_LIMIT: u32 = 10_000_000;
+/// Number of recent blocks sampled by `eth_gasPrice`.
+const GAS_PRICE_BLOCKS: u32 = 20;
+/// EVMC status code of a successful execution.
+const STATUS_SUCCESS: i32 = 0;
+/// Maximum number of blocks searched by `eth_getLogs`.
+const MAX_LOG_BLOCKS: u64 = 1_000;
+
+/// Core `eth_*` namespace.
+#[rpc]
+pub trait EthApi {
+    /// Chain id used for replay protection.
+    #[rpc(name = "eth_chainId")]
+    fn chain_id(&self) -> Result<U256>;
+
+    /// Number of the best block.
+    #[rpc(name = "eth_blockNumber")]
+    fn block_number(&self) -> Result<U256>;
+
+    /// Suggested gas price.
+    #[rpc(name = "eth_gasPrice")]
+    fn gas_price(&self) -> Result<U256>;
+
+    /// Balance of an account.
+    #[rpc(name = "eth_getBalance")]
+    fn balance(&self, address: H160, number: Option<BlockNumber>) -> Result<U256>;
+
+    /// Nonce of an account.
+    #[rpc(name = "eth_getTransactionCount")]
+    fn transaction_count(&self, address: H160, number: Option<BlockNumber>) -> Result<U256>;
+
+    /// Code of an account.
+    #[rpc(name = "eth_getCode")]
+    fn code_at(&self, address: H160, number: Option<BlockNumber>) -> Result<Bytes>;
+
+    /// Value of a contract storage slot.
+    #[rpc(name = "eth_getStorageAt")]
+    fn storage_at(&self, address: H160, index: U256, number: Option<BlockNumber>) -> Result<H256>;
+
+    /// Execute a call without changing state and return its output.
+    #[rpc(name = "eth_call")]
+    fn call(&self, request: CallRequest, number: Option<BlockNumber>) -> Result<Bytes>;
+
+    /// Estimate the gas used by a call or a contract creation.
+    #[rpc(name = "eth_estimateGas")]
+    fn estimate_gas(&self, request: CallRequest, number: Option<BlockNumber>) -> Result<U256>;
+
+    /// Block by number. Transactions are always returned as hashes, as
+    /// transaction bodies are not kept.
+    #[rpc(name = "eth_getBlockByNumber")]
+    fn block_by_number(&self, number: BlockNumber, full: bool) -> Result<Option<Block>>;
+
+    /// Block by hash of its Ethereum header. Transactions are always returned
+    /// as hashes, as transaction bodies are not kept.
+    #[rpc(name = "eth_getBlockByHash")]
+    fn block_by_hash(&self, hash: H256, full: bool) -> Result<Option<Block>>;
+
+    /// Receipt of a transaction, while its block is in the retention period.
+    #[rpc(name = "eth_getTransactionReceipt")]
+    fn transaction_receipt(&self, hash: H256) -> Result<Option<Receipt>>;
+
+    /// Logs matching a filter, of blocks in the retention period. A range
+    /// spans at most `MAX_LOG_BLOCKS` blocks.
+    #[rpc(name = "eth_getLogs")]
+    fn logs(&self, filter: LogFilter) -> Result<Vec<Log>>;
+
+    /// Fee history of up to `block_count` blocks ending at `newest`.
+    #[rpc(name = "eth_feeHistory")]
+    fn fee_history(
+        &self,
+        block_count: U256,
+        newest: BlockNumber,
+        percentiles: Option<Vec<f64>>,
+    ) -> Result<FeeHistory>;
+}
+
+/// Implementation of `EthApi` backed by a client.
+pub struct Eth<C, B> {
+    client: Arc<C>,
+    _marker: PhantomData<B>,
+}
+
+impl<C, B> Eth<C, B> {
+    /// Create a new `Eth` instance with the given reference to the client.
+    pub fn new(client: Arc<C>) -> Self {
+        Self {
+            client,
+            _marker: Default::default(),
+        }
+    }
+}
+
+impl<C, Block> Eth<C, Block>
+where
+    Block: BlockT,
+    C: Send + Sync + 'static,
+    C: ProvideRuntimeApi<Block>,
+    C: HeaderBackend<Block>,
+    C::Api: SsvmApi<Block>,
+{
+    fn best_number(&self) -> u64 {
+        self.client.info().best_number.unique_saturated_into()
+    }
+
+    fn number(&self, number: BlockNumber) -> u64 {
+        match number {
+            BlockNumber::Num(n) => n.low_u64(),
+            BlockNumber::Tag(BlockTag::Earliest) => 0,
+            BlockNumber::Tag(_) => self.best_number(),
+        }
+    }
+
+    fn block_id(&self, number: Option<BlockNumber>) -> BlockId<Block> {
+        match number {
+            Some(BlockNumber::Num(n)) => BlockId::Number(n.low_u64().saturated_into()),
+            Some(BlockNumber::Tag(BlockTag::Earliest)) => BlockId::Number(Zero::zero()),
+            _ => BlockId::Hash(self.client.info().best_hash),
+        }
+    }
+
+    fn block(&self, header: Option<EthBlockHeader>) -> Result<Option<Block>> {
+        let header = match header {
+            Some(header) => header,
+            None => return Ok(None),
+        };
+        let at = self.block_id(None);
+        let transactions = self
+            .client
+            .runtime_api()
+            .block_transaction_hashes(&at, header.number)
+            .map_err(runtime_error)?;
+        Ok(Some(Block {
+            hash: header.hash(),
+            parent_hash: header.parent_hash,
+            number: header.number.into(),
+            state_root: header.state_root,
+            transactions_root: header.transactions_root,
+            receipts_root: header.receipts_root,
+            logs_bloom: header.logs_bloom.0.to_vec().into(),
+            gas_used: header.gas_used,
+            gas_limit: header.gas_limit,
+            timestamp: header.timestamp.into(),
+            transactions,
+        }))
+    }
+
+    fn execute(&self, request: CallRequest, at: &BlockId<Block>) -> Result<CallInfo> {
+        let api = self.client.runtime_api();
+        let from = request.from.unwrap_or_default();
+        let value = request.value.unwrap_or_default();
+        let gas_limit = request
+            .gas
+            .map(|gas| gas.min(U256::from(u32::max_value())).low_u32())
+            .unwrap_or(DEFAULT_GAS_LIMIT);
+        let gas_price = match request.gas_price {
+            Some(gas_price) => gas_price,
+            None => api.min_gas_price(at).map_err(runtime_error)?,
+        };
+        let result = match request.to {
+            Some(to) => api.call(at, from, to, request.data, value, gas_limit, gas_price),
+            None => api.create(at, from, request.data, value, gas_limit, gas_price),
+        };
+        let info = result.map_err(runtime_error)?.map_err(runtime_error)?;
+        if info.status_code != STATUS_SUCCESS {
+            return Err(execution_error(info));
+        }
+        Ok(info)
+    }
+}
+
+impl<C, Block> EthApi for Eth<C, Block>
+where
+    Block: BlockT,
+    C: Send + Sync + 'static,
+    C: ProvideRuntimeApi<Block>,
+    C: HeaderBackend<Block>,
+    C::Api: SsvmApi<Block>,
+{
+    fn chain_id(&self) -> Result<U256> {
+        let at = self.block_id(None);
+        let chain_id = self
+            .client
+            .runtime_api()
+            .chain_id(&at)
+            .map_err(runtime_error)?;
+        Ok(chain_id.into())
+    }
+
+    fn block_number(&self) -> Result<U256> {
+        Ok(self.best_number().into())
+    }
+
+    fn gas_price(&self) -> Result<U256> {
+        let api = self.client.runtime_api();
+        let at = self.block_id(None);
+        let min_gas_price = api.min_gas_price(&at).map_err(runtime_error)?;
+        let mut prices: Vec<U256> = api
+            .gas_stats(&at, GAS_PRICE_BLOCKS)
+            .map_err(runtime_error)?
+            .into_iter()
+            .filter(|stats| stats.tx_count > 0)
+            .map(|stats| stats.median_gas_price)
+            .collect();
+        prices.sort();
+        let median = prices.get(prices.len() / 2).cloned().unwrap_or_default();
+        Ok(median.max(min_gas_price))
+    }
+
+    fn balance(&self, address: H160, number: Option<BlockNumber>) -> Result<U256> {
+        let at = self.block_id(number);
+        let account = self
+            .client
+            .runtime_api()
+            .account_basic(&at, address)
+            .map_err(runtime_error)?;
+        Ok(account.balance)
+    }
+
+    fn transaction_count(&self, address: H160, number: Option<BlockNumber>) -> Result<U256> {
+        let at = self.block_id(number);
+        let account = self
+            .client
+            .runtime_api()
+            .account_basic(&at, address)
+            .map_err(runtime_error)?;
+        Ok(account.nonce)
+    }
+
+    fn code_at(&self, address: H160, number: Option<BlockNumber>) -> Result<Bytes> {
+        let at = self.block_id(number);
+        let code = self
+            .client
+            .runtime_api()
+            .account_code_at(&at, address)
+            .map_err(runtime_error)?;
+        Ok(code.into())
+    }
+
+    fn storage_at(&self, address: H160, index: U256, number: Option<BlockNumber>) -> Result<H256> {
+        let at = self.block_id(number);
+        let mut key = H256::zero();
+        index.to_big_endian(key.as_bytes_mut());
+        self.client
+            .runtime_api()
+            .storage_at(&at, address, key)
+            .map_err(runtime_error)
+    }
+
+    fn call(&self, request: CallRequest, number: Option<BlockNumber>) -> Result<Bytes> {
+        let at = self.block_id(number);
+        let info = self.execute(request, &at)?;
+        Ok(info.output.into())
+    }
+
+    fn estimate_gas(&self, request: CallRequest, number: Option<BlockNumber>) -> Result<U256> {
+        let at = self.block_id(number);
+        let api = self.client.runtime_api();
+        let gas_price = match request.gas_price {
+            Some(gas_price) => gas_price,
+            None => api.min_gas_price(&at).map_err(runtime_error)?,
+        };
+        api.estimate_gas(
+            &at,
+            request.from.unwrap_or_default(),
+            request.to,
+            request.data,
+            request.value.unwrap_or_default(),
+            gas_price,
+        )
+        .map_err(runtime_error)?
+        .map_err(runtime_error)
+    }
+
+    fn block_by_number(&self, number: BlockNumber, _full: bool) -> Result<Option<Block>> {
+        let number = self.number(number);
+        let at = self.block_id(None);
+        let header = self
+            .client
+            .runtime_api()
+            .eth_block_by_number(&at, number)
+            .map_err(runtime_error)?;
+        self.block(header)
+    }
+
+    fn block_by_hash(&self, hash: H256, _full: bool) -> Result<Option<Block>> {
+        let at = self.block_id(None);
+        let header = self
+            .client
+            .runtime_api()
+            .eth_block_by_hash(&at, hash)
+            .map_err(runtime_error)?;
+        self.block(header)
+    }
+
+    fn transaction_receipt(&self, hash: H256) -> Result<Option<Receipt>> {
+        let api = self.client.runtime_api();
+        let at = self.block_id(None);
+        let status = match api.transaction_status(&at, hash).map_err(runtime_error)? {
+            Some(status) => status,
+            None => return Ok(None),
+        };
+        let block_hash = api
+            .eth_block_by_number(&at, status.block_number)
+            .map_err(runtime_error)?
+            .map(|header| header.hash())
+            .unwrap_or_default();
+        let logs = api
+            .block_logs(&at, status.block_number)
+            .map_err(runtime_error)?
+            .into_iter()
+            .enumerate()
+            .filter(|(_, (index, _))| *index == status.extrinsic_index)
+            .map(|(log_index, (index, log))| Log {
+                address: log.address,
+                topics: log.topics,
+                data: log.data.into(),
+                block_hash,
+                block_number: status.block_number.into(),
+                transaction_index: index.into(),
+                log_index: U256::from(log_index),
+                removed: false,
+            })
+            .collect();
+        let success = status.outcome == ExecutionOutcome::Succeeded;
+        Ok(Some(Receipt {
+            transaction_hash: hash,
+            transaction_index: status.extrinsic_index.into(),
+            block_hash,
+            block_number: status.block_number.into(),
+            from: status.from,
+            to: status.to,
+            contract_address: status.contract_address,
+            gas_used: status.gas_used,
+            status: if success { U256::one() } else { U256::zero() },
+            outcome: status.outcome,
+            logs,
+        }))
+    }
+
+    fn logs(&self, filter: LogFilter) -> Result<Vec<Log>> {
+        let api = self.client.runtime_api();
+        let at = self.block_id(None);
+        let (from, to) = match filter.block_hash {
+            Some(hash) => match api.eth_block_by_hash(&at, hash).map_err(runtime_error)? {
+                Some(header) => (header.number, header.number),
+                None => return Ok(Vec::new()),
+            },
+            None => {
+                let number = |number: &Option<BlockNumber>| match number {
+                    Some(number) => self.number(number.clone()),
+                    None => self.best_number(),
+                };
+                (number(&filter.from_block), number(&filter.to_block))
+            }
+        };
+        if to >= from && to - from >= MAX_LOG_BLOCKS {
+            return Err(invalid_params("block range is too large"));
+        }
+
+        let mut logs = Vec::new();
+        for number in from..=to {
+            let block_hash = api
+                .eth_block_by_number(&at, number)
+                .map_err(runtime_error)?
+                .map(|header| header.hash())
+                .unwrap_or_default();
+            let block_logs = api.block_logs(&at, number).map_err(runtime_error)?;
+            logs.extend(
+                block_logs
+                    .into_iter()
+                    .enumerate()
+                    .filter(|(_, (_, log))| filter.matches(&log.address, &log.topics))
+                    .map(|(log_index, (index, log))| Log {
+                        address: log.address,
+                        topics: log.topics,
+                        data: log.data.into(),
+                        block_hash,
+                        block_number: number.into(),
+                        transaction_index: index.into(),
+                        log_index: U256::from(log_index),
+                        removed: false,
+                    }),
+            );
+        }
+        Ok(logs)
+    }
+
+    fn fee_history(
+        &self,
+        block_count: U256,
+        newest: BlockNumber,
+        percentiles: Option<Vec<f64>>,
+    ) -> Result<FeeHistory> {
+        let newest = self.number(newest);
+        let requested = percentiles.is_some();
+        let percentiles = percentiles
+            .unwrap_or_default()
+            .into_iter()
+            .map(|p| {
+                if p < 0.0 || p > 100.0 {
+                    return Err(invalid_params("percentiles must be between 0 and 100"));
+                }
+                Ok(Permill::from_parts((p * 10_000.0) as u32))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        let block_count = block_count.min(U256::from(u32::max_value())).low_u32();
+
+        let at = self.block_id(None);
+        let history = self
+            .client
+            .runtime_api()
+            .fee_history(&at, block_count, newest, percentiles)
+            .map_err(runtime_error)?;
+        Ok(FeeHistory {
+            oldest_block: history.oldest_block.into(),
+            base_fee_per_gas: history.base_fee_per_gas,
+            gas_used_ratio: history
+                .gas_used_ratio
+                .into_iter()
+                .map(|ratio| ratio.deconstruct() as f64 / 1_000_000.0)
+                .collect(),
+            reward: if requested {
+                Some(history.reward)
+            } else {
+                None
+            },
+        })
+    }
+}
+
+fn runtime_error(err: impl std::fmt::Debug) -> Error {
+    Error {
+        code: ErrorCode::ServerError(RUNTIME_ERROR),
+        message: "Runtime error".into(),
+        data: Some(format!("{:?}", err).into()),
+    }
+}
+
+fn execution_error(info: CallInfo) -> Error {
+    Error {
+        code: ErrorCode::ServerError(EXECUTION_ERROR),
+        message: format!("execution failed with status {}", info.status_code),
+        data: Some(format!("0x{}", hex::encode(info.output)).into()),
+    }
+}
+
+fn invalid_params(message: &str) -> Error {
+    Error {
+        code: ErrorCode::InvalidParams,
+        message: message.into(),
+        data: None,
+    }
+}
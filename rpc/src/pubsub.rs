@@ -0,0 +1,176 @@
+// Copyright (C) 2020 Second State.
+// This file is part of Pallet-SSVM.
+
+// Pallet-SSVM is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+
+// Pallet-SSVM is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+//! `eth_subscribe` notifications of new best blocks and their logs.
+
+use crate::types::{Header, Log, LogFilter, PubSubResult, SubscriptionKind};
+use futures::{future, StreamExt as _, TryStreamExt as _};
+use jsonrpc_core::{
+    futures::{Future as _, Sink as _},
+    Error, Result,
+};
+use jsonrpc_derive::rpc;
+use jsonrpc_pubsub::{typed::Subscriber, SubscriptionId};
+use log::warn;
+use pallet_ssvm::runtime_api::SsvmApi;
+use sc_client_api::BlockchainEvents;
+use sc_rpc_api::Subscriptions;
+use sp_api::ProvideRuntimeApi;
+use sp_core::{H256, U256};
+use sp_runtime::{
+    generic::BlockId,
+    traits::{Block as BlockT, Header as HeaderT, UniqueSaturatedInto},
+};
+use std::{marker::PhantomData, sync::Arc};
+
+/// `eth_subscribe` namespace.
+#[rpc]
+pub trait EthPubSubApi {
+    /// RPC metadata.
+    type Metadata;
+
+    /// Subscribe to headers of new best blocks or to their logs.
+    #[pubsub(subscription = "eth_subscription", subscribe, name = "eth_subscribe")]
+    fn subscribe(
+        &self,
+        metadata: Self::Metadata,
+        subscriber: Subscriber<PubSubResult>,
+        kind: SubscriptionKind,
+        filter: Option<LogFilter>,
+    );
+
+    /// Cancel a subscription.
+    #[pubsub(
+        subscription = "eth_subscription",
+        unsubscribe,
+        name = "eth_unsubscribe"
+    )]
+    fn unsubscribe(&self, metadata: Option<Self::Metadata>, id: SubscriptionId) -> Result<bool>;
+}
+
+/// Implementation of `EthPubSubApi` backed by a client.
+pub struct EthPubSub<C, B> {
+    client: Arc<C>,
+    subscriptions: Subscriptions,
+    _marker: PhantomData<B>,
+}
+
+impl<C, B> EthPubSub<C, B> {
+    /// Create a new `EthPubSub` instance with the given reference to the
+    /// client, spawning subscriptions with `subscriptions`.
+    pub fn new(client: Arc<C>, subscriptions: Subscriptions) -> Self {
+        Self {
+            client,
+            subscriptions,
+            _marker: Default::default(),
+        }
+    }
+}
+
+impl<C, Block> EthPubSubApi for EthPubSub<C, Block>
+where
+    Block: BlockT<Hash = H256>,
+    C: Send + Sync + 'static,
+    C: ProvideRuntimeApi<Block>,
+    C: BlockchainEvents<Block>,
+    C::Api: SsvmApi<Block>,
+{
+    type Metadata = sc_rpc_api::Metadata;
+
+    fn subscribe(
+        &self,
+        _metadata: Self::Metadata,
+        subscriber: Subscriber<PubSubResult>,
+        kind: SubscriptionKind,
+        filter: Option<LogFilter>,
+    ) {
+        let client = self.client.clone();
+        let filter = filter.unwrap_or_default();
+        let notifications = self
+            .client
+            .import_notification_stream()
+            .filter(|notification| future::ready(notification.is_new_best))
+            .map(move |notification| {
+                let header = &notification.header;
+                let number: u64 = (*header.number()).unique_saturated_into();
+                let results = match kind {
+                    SubscriptionKind::NewHeads => vec![PubSubResult::Header(Header {
+                        hash: notification.hash,
+                        parent_hash: *header.parent_hash(),
+                        number: number.into(),
+                    })],
+                    SubscriptionKind::Logs => {
+                        block_logs(&*client, notification.hash, number, &filter)
+                            .into_iter()
+                            .map(PubSubResult::Log)
+                            .collect()
+                    }
+                };
+                futures::stream::iter(results)
+            })
+            .flatten()
+            .map(|result| Ok::<_, ()>(Ok::<_, Error>(result)))
+            .compat();
+
+        self.subscriptions.add(subscriber, |sink| {
+            sink.sink_map_err(|e| warn!("Error sending notifications: {:?}", e))
+                .send_all(notifications)
+                .map(|_| ())
+        });
+    }
+
+    fn unsubscribe(&self, _metadata: Option<Self::Metadata>, id: SubscriptionId) -> Result<bool> {
+        Ok(self.subscriptions.cancel(id))
+    }
+}
+
+/// Logs of block `number` with hash `hash` matching `filter`.
+pub(crate) fn block_logs<C, Block>(
+    client: &C,
+    hash: H256,
+    number: u64,
+    filter: &LogFilter,
+) -> Vec<Log>
+where
+    Block: BlockT<Hash = H256>,
+    C: ProvideRuntimeApi<Block>,
+    C::Api: SsvmApi<Block>,
+{
+    let logs = match client
+        .runtime_api()
+        .block_logs(&BlockId::Hash(hash), number)
+    {
+        Ok(logs) => logs,
+        Err(e) => {
+            warn!("Unable to read logs of block {}: {:?}", number, e);
+            return Vec::new();
+        }
+    };
+    logs.into_iter()
+        .enumerate()
+        .filter(|(_, (_, log))| filter.matches(&log.address, &log.topics))
+        .map(|(log_index, (transaction_index, log))| Log {
+            address: log.address,
+            topics: log.topics,
+            data: log.data.into(),
+            block_hash: hash,
+            block_number: number.into(),
+            transaction_index: transaction_index.into(),
+            log_index: U256::from(log_index),
+            removed: false,
+        })
+        .collect()
+}
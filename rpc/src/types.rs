@@ -0,0 +1,226 @@
+// Copyright (C) 2020 Second State.
+// This file is part of Pallet-SSVM.
+
+// Pallet-SSVM is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+
+// Pallet-SSVM is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+//! Types of the Ethereum JSON-RPC interface.
+
+use pallet_ssvm::ExecutionOutcome;
+use serde::{Deserialize, Serialize};
+use sp_core::{Bytes, H160, H256, U256};
+
+/// Block parameter of `eth_*` methods, either a block number or a tag.
+#[derive(Clone, Debug, Eq, PartialEq, Deserialize)]
+#[serde(untagged)]
+pub enum BlockNumber {
+    /// Block by number.
+    Num(U256),
+    /// Block by tag.
+    Tag(BlockTag),
+}
+
+/// Named block of `eth_*` methods.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BlockTag {
+    /// The best block.
+    Latest,
+    /// The genesis block.
+    Earliest,
+    /// The best block, since pending transactions are not executed ahead.
+    Pending,
+}
+
+/// Result of `eth_feeHistory`.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FeeHistory {
+    /// Number of the oldest block in the range.
+    pub oldest_block: U256,
+    /// Base fee of each block, plus the base fee of the block after the
+    /// newest one.
+    pub base_fee_per_gas: Vec<U256>,
+    /// Ratio of gas used to the block gas limit of each block.
+    pub gas_used_ratio: Vec<f64>,
+    /// Tips at the requested percentiles of each block.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reward: Option<Vec<Vec<U256>>>,
+}
+
+/// A single value or an array of values, as accepted by log filters.
+#[derive(Clone, Debug, Eq, PartialEq, Deserialize)]
+#[serde(untagged)]
+pub enum ValueOrArray<T> {
+    /// Single value.
+    Value(T),
+    /// Array of values.
+    Array(Vec<T>),
+}
+
+impl<T: PartialEq> ValueOrArray<T> {
+    /// Whether `value` is one of the values.
+    pub fn contains(&self, value: &T) -> bool {
+        match self {
+            ValueOrArray::Value(v) => v == value,
+            ValueOrArray::Array(values) => values.contains(value),
+        }
+    }
+}
+
+/// Kind of an `eth_subscribe` subscription.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum SubscriptionKind {
+    /// Headers of new best blocks.
+    NewHeads,
+    /// Logs of new best blocks matching a filter.
+    Logs,
+}
+
+/// Filter of `eth_getLogs` and log subscriptions. Subscriptions ignore the
+/// block range.
+#[derive(Clone, Debug, Default, Eq, PartialEq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LogFilter {
+    /// First block of the range, the best block if not set.
+    pub from_block: Option<BlockNumber>,
+    /// Last block of the range, the best block if not set.
+    pub to_block: Option<BlockNumber>,
+    /// Hash of the single block of the range, instead of `from_block` and
+    /// `to_block`.
+    pub block_hash: Option<H256>,
+    /// Addresses a log must be emitted by, any address if not set.
+    pub address: Option<ValueOrArray<H160>>,
+    /// Topics a log must have at each position, any topic if not set.
+    pub topics: Option<Vec<Option<ValueOrArray<H256>>>>,
+}
+
+impl LogFilter {
+    /// Whether a log emitted by `address` with `topics` matches the filter.
+    pub fn matches(&self, address: &H160, topics: &[H256]) -> bool {
+        if let Some(ref filter) = self.address {
+            if !filter.contains(address) {
+                return false;
+            }
+        }
+        self.topics
+            .iter()
+            .flatten()
+            .enumerate()
+            .all(|(i, filter)| match filter {
+                Some(filter) => topics.get(i).map_or(false, |topic| filter.contains(topic)),
+                None => true,
+            })
+    }
+}
+
+/// Block header of `newHeads` notifications.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Header {
+    /// Block hash.
+    pub hash: H256,
+    /// Parent block hash.
+    pub parent_hash: H256,
+    /// Block number.
+    pub number: U256,
+}
+
+/// Log of `logs` notifications and `eth_getLogs`.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Log {
+    /// Source address of the log.
+    pub address: H160,
+    /// Topics of the log.
+    pub topics: Vec<H256>,
+    /// Data of the log.
+    pub data: Bytes,
+    /// Hash of the block of the log.
+    pub block_hash: H256,
+    /// Number of the block of the log.
+    pub block_number: U256,
+    /// Index of the extrinsic that emitted the log.
+    pub transaction_index: U256,
+    /// Index of the log in the block.
+    pub log_index: U256,
+    /// Whether the log was removed by a reorganization.
+    pub removed: bool,
+}
+
+/// Notification of an `eth_subscribe` subscription.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize)]
+#[serde(untagged)]
+pub enum PubSubResult {
+    /// Header of a new best block.
+    Header(Header),
+    /// Log of a new best block.
+    Log(Log),
+}
+
+/// Block of `eth_getBlockByNumber` and `eth_getBlockByHash`.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Block {
+    /// Hash of the Ethereum header.
+    pub hash: H256,
+    /// Hash of the Ethereum header of the parent block.
+    pub parent_hash: H256,
+    /// Block number.
+    pub number: U256,
+    /// Root hash over the state of touched accounts.
+    pub state_root: H256,
+    /// Root hash over the hashes of the block's transactions.
+    pub transactions_root: H256,
+    /// Root hash over the logs emitted in the block.
+    pub receipts_root: H256,
+    /// Bloom of all logs emitted in the block.
+    pub logs_bloom: Bytes,
+    /// Total gas used by executed transactions.
+    pub gas_used: U256,
+    /// Block gas limit.
+    pub gas_limit: U256,
+    /// Block timestamp.
+    pub timestamp: U256,
+    /// Hashes of the block's transactions.
+    pub transactions: Vec<H256>,
+}
+
+/// Receipt of `eth_getTransactionReceipt`.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Receipt {
+    /// Transaction hash.
+    pub transaction_hash: H256,
+    /// Index of the extrinsic in the block.
+    pub transaction_index: U256,
+    /// Hash of the Ethereum header of the including block.
+    pub block_hash: H256,
+    /// Number of the including block.
+    pub block_number: U256,
+    /// Sender of the transaction.
+    pub from: H160,
+    /// Recipient of a call, `None` for a contract creation.
+    pub to: Option<H160>,
+    /// Address of the created contract, if creation succeeded.
+    pub contract_address: Option<H160>,
+    /// Gas charged for the transaction, after refunds.
+    pub gas_used: U256,
+    /// 1 if the execution succeeded, 0 otherwise.
+    pub status: U256,
+    /// Why the execution failed, if it did.
+    pub outcome: ExecutionOutcome,
+    /// Logs emitted by the transaction.
+    pub logs: Vec<Log>,
+}
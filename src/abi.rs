@@ -0,0 +1,235 @@
+// Copyright (C) 2020 Second State.
+// This file is part of Pallet-SSVM.
+
+// Pallet-SSVM is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+
+// Pallet-SSVM is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+//! Solidity ABI encoding of SCALE encoded arguments, letting Substrate tools
+//! call contracts without encoding their ABI, and decoding of return data
+//! into typed values.
+
+use codec::{Decode, Encode};
+#[cfg(feature = "std")]
+use serde::{Deserialize, Serialize};
+use sp_core::{H160, U256};
+use sp_std::vec::Vec;
+
+#[derive(Clone, Eq, PartialEq, Encode, Decode)]
+#[cfg_attr(feature = "std", derive(Debug, Serialize, Deserialize))]
+/// Argument of a contract method. Used by `call_method`.
+pub enum AbiValue {
+    /// `uint<M>`, any width.
+    Uint(U256),
+    /// `int<M>`, any width up to 128 bits.
+    Int(i128),
+    Address(H160),
+    Bool(bool),
+    /// `bytes<M>`, at most 32 bytes.
+    FixedBytes(Vec<u8>),
+    Bytes(Vec<u8>),
+    /// `string`, as UTF-8 bytes.
+    String(Vec<u8>),
+    /// `uint<M>[]`.
+    UintArray(Vec<U256>),
+    /// `address[]`.
+    AddressArray(Vec<H160>),
+}
+
+#[derive(Clone, Copy, Eq, PartialEq, Encode, Decode)]
+#[cfg_attr(feature = "std", derive(Debug, Serialize, Deserialize))]
+/// Type of an `AbiValue`, describing how to decode it.
+pub enum AbiType {
+    Uint,
+    Int,
+    Address,
+    Bool,
+    /// `bytes<M>` of the given width, at most 32.
+    FixedBytes(u8),
+    Bytes,
+    String,
+    UintArray,
+    AddressArray,
+}
+
+impl AbiValue {
+    fn is_dynamic(&self) -> bool {
+        match self {
+            AbiValue::Bytes(_)
+            | AbiValue::String(_)
+            | AbiValue::UintArray(_)
+            | AbiValue::AddressArray(_) => true,
+            _ => false,
+        }
+    }
+
+    /// Head word of static values, `None` if invalid.
+    fn word(&self) -> Option<[u8; 32]> {
+        let mut word = [0u8; 32];
+        match self {
+            AbiValue::Uint(value) => value.to_big_endian(&mut word),
+            AbiValue::Int(value) => {
+                if *value < 0 {
+                    word = [0xff; 32];
+                }
+                word[16..].copy_from_slice(&value.to_be_bytes());
+            }
+            AbiValue::Address(address) => word[12..].copy_from_slice(address.as_bytes()),
+            AbiValue::Bool(value) => word[31] = *value as u8,
+            AbiValue::FixedBytes(bytes) if bytes.len() <= 32 => {
+                word[..bytes.len()].copy_from_slice(bytes)
+            }
+            _ => return None,
+        }
+        Some(word)
+    }
+
+    /// Tail of dynamic values.
+    fn tail(&self) -> Vec<u8> {
+        let mut tail = Vec::new();
+        match self {
+            AbiValue::Bytes(bytes) | AbiValue::String(bytes) => {
+                tail.extend_from_slice(&uint_word(U256::from(bytes.len())));
+                tail.extend_from_slice(bytes);
+                tail.resize((tail.len() + 31) / 32 * 32, 0);
+            }
+            AbiValue::UintArray(values) => {
+                tail.extend_from_slice(&uint_word(U256::from(values.len())));
+                for value in values {
+                    tail.extend_from_slice(&uint_word(*value));
+                }
+            }
+            AbiValue::AddressArray(addresses) => {
+                tail.extend_from_slice(&uint_word(U256::from(addresses.len())));
+                for address in addresses {
+                    tail.extend_from_slice(&uint_word(U256::from(address.as_bytes())));
+                }
+            }
+            _ => {}
+        }
+        tail
+    }
+}
+
+fn uint_word(value: U256) -> [u8; 32] {
+    let mut word = [0u8; 32];
+    value.to_big_endian(&mut word);
+    word
+}
+
+/// ABI encode `values` as a tuple, `None` if a value is invalid.
+pub fn encode(values: &[AbiValue]) -> Option<Vec<u8>> {
+    let head_len = 32 * values.len();
+    let mut head = Vec::with_capacity(head_len);
+    let mut tail = Vec::new();
+    for value in values {
+        if value.is_dynamic() {
+            head.extend_from_slice(&uint_word(U256::from(head_len + tail.len())));
+            tail.extend(value.tail());
+        } else {
+            head.extend_from_slice(&value.word()?);
+        }
+    }
+    head.extend(tail);
+    Some(head)
+}
+
+/// Call data of the method with `selector` and `args`, `None` if an
+/// argument is invalid.
+pub fn encode_call(selector: [u8; 4], args: &[AbiValue]) -> Option<Vec<u8>> {
+    let mut input = selector.to_vec();
+    input.extend(encode(args)?);
+    Some(input)
+}
+
+/// Word at byte `offset` of `data`.
+fn word_at(data: &[u8], offset: usize) -> Option<&[u8]> {
+    data.get(offset..offset.checked_add(32)?)
+}
+
+/// Word at byte `offset` of `data` as an offset or length.
+fn usize_at(data: &[u8], offset: usize) -> Option<usize> {
+    let value = U256::from_big_endian(word_at(data, offset)?);
+    if value > U256::from(data.len()) {
+        return None;
+    }
+    Some(value.as_usize())
+}
+
+/// Decode the value of type `ty` at `index` of the ABI encoded tuple `data`.
+fn decode_value(ty: AbiType, data: &[u8], index: usize) -> Option<AbiValue> {
+    let head = index.checked_mul(32)?;
+    let word = word_at(data, head)?;
+    let value = match ty {
+        AbiType::Uint => AbiValue::Uint(U256::from_big_endian(word)),
+        AbiType::Int => {
+            let mut low = [0u8; 16];
+            low.copy_from_slice(&word[16..]);
+            let value = i128::from_be_bytes(low);
+            let fill = if value < 0 { 0xff } else { 0 };
+            if word[..16].iter().any(|byte| *byte != fill) {
+                return None;
+            }
+            AbiValue::Int(value)
+        }
+        AbiType::Address => AbiValue::Address(H160::from_slice(&word[12..])),
+        AbiType::Bool => {
+            if word[..31].iter().any(|byte| *byte != 0) || word[31] > 1 {
+                return None;
+            }
+            AbiValue::Bool(word[31] == 1)
+        }
+        AbiType::FixedBytes(len) if len <= 32 => {
+            AbiValue::FixedBytes(word[..len as usize].to_vec())
+        }
+        AbiType::FixedBytes(_) => return None,
+        AbiType::Bytes | AbiType::String => {
+            let offset = usize_at(data, head)?;
+            let len = usize_at(data, offset)?;
+            let start = offset.checked_add(32)?;
+            let bytes = data.get(start..start.checked_add(len)?)?.to_vec();
+            if ty == AbiType::Bytes {
+                AbiValue::Bytes(bytes)
+            } else {
+                AbiValue::String(bytes)
+            }
+        }
+        AbiType::UintArray | AbiType::AddressArray => {
+            let offset = usize_at(data, head)?;
+            let len = usize_at(data, offset)?;
+            let words = (1..=len)
+                .map(|i| word_at(data, offset.checked_add(i.checked_mul(32)?)?))
+                .collect::<Option<Vec<_>>>()?;
+            if ty == AbiType::UintArray {
+                AbiValue::UintArray(words.into_iter().map(U256::from_big_endian).collect())
+            } else {
+                AbiValue::AddressArray(
+                    words
+                        .into_iter()
+                        .map(|word| H160::from_slice(&word[12..]))
+                        .collect(),
+                )
+            }
+        }
+    };
+    Some(value)
+}
+
+/// Decode ABI encoded `data`, e.g. return data, as a tuple of `types`,
+/// `None` if it does not match.
+pub fn decode(types: &[AbiType], data: &[u8]) -> Option<Vec<AbiValue>> {
+    types
+        .iter()
+        .enumerate()
+        .map(|(index, ty)| decode_value(*ty, data, index))
+        .collect()
+}
@@ -14,23 +14,33 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
-use crate::{AccountCodes, Accounts, Event, Module, Trait};
+use crate::{
+    is_precompile, AbiValue, Module, Trait, STATUS_OUT_OF_GAS, STATUS_REVERT, STATUS_SUCCESS,
+};
 use codec::{Decode, Encode};
-use frame_support::storage::StorageMap;
+#[cfg(feature = "std")]
+use frame_support::traits::Get;
 #[cfg(feature = "std")]
 use serde::{Deserialize, Serialize};
-use sha3::{Digest, Keccak256};
 use sp_core::{H160, H256, U256};
+use sp_io::hashing::keccak_256;
+use sp_runtime::Permill;
 use sp_std::marker::PhantomData;
 use sp_std::vec::Vec;
 #[cfg(feature = "std")]
 use ssvm::host::HostInterface;
 #[cfg(feature = "std")]
-use ssvm::types::{Address, Bytes, Bytes32, CallKind, StatusCode, StorageStatus, ADDRESS_LENGTH};
+use ssvm::types::{
+    Address, Bytes, Bytes32, CallKind, Revision, StatusCode, StorageStatus, ADDRESS_LENGTH,
+};
+#[cfg(feature = "std")]
+use std::collections::HashMap;
 
 #[derive(Clone, Eq, PartialEq, Encode, Decode, Default)]
 #[cfg_attr(feature = "std", derive(Debug, Serialize, Deserialize))]
 /// Ethereum account nonce, balance and code. Used by storage.
+/// `Module::nonce_of` and `Module::balance_of` read the fields at fixed
+/// offsets of the encoding, so their order and size must not change.
 pub struct Account {
     /// Account nonce.
     pub nonce: U256,
@@ -50,20 +60,722 @@ pub struct Log {
     pub data: Vec<u8>,
 }
 
+#[derive(Clone, Copy, Eq, PartialEq, Encode, Decode)]
+#[cfg_attr(feature = "std", derive(Debug, Serialize, Deserialize))]
+/// Standard event a log is decoded as, in addition to the raw `Log` event.
+pub enum LogDecoder {
+    /// ERC-20 `Transfer(address indexed from, address indexed to, uint256 value)`.
+    Erc20Transfer,
+    /// ERC-20 `Approval(address indexed owner, address indexed spender, uint256 value)`.
+    Erc20Approval,
+}
+
+#[derive(Clone, Eq, PartialEq, Encode, Decode, Default)]
+#[cfg_attr(feature = "std", derive(Debug, Serialize, Deserialize))]
+/// Account with its code and storage. Used by state import and export.
+pub struct AccountDump {
+    /// Account nonce.
+    pub nonce: U256,
+    /// Account balance.
+    pub balance: U256,
+    /// Account code.
+    pub code: Vec<u8>,
+    /// Storage slots, keyed as stored in `AccountStorages`.
+    pub storage: Vec<(H256, H256)>,
+}
+
+#[derive(Clone, Eq, PartialEq, Encode, Decode, Default)]
+#[cfg_attr(feature = "std", derive(Debug, Serialize, Deserialize))]
+/// Page of the storage of a contract. Used by `storage_range_at`.
+pub struct StorageRange {
+    /// Storage slots, keyed as stored in `AccountStorages`.
+    pub storage: Vec<(H256, H256)>,
+    /// Key to continue from, `None` if the range is complete.
+    pub next_key: Option<H256>,
+}
+
+#[derive(Clone, Eq, PartialEq, Encode, Decode, Default)]
+#[cfg_attr(feature = "std", derive(Debug, Serialize, Deserialize))]
+/// Page of a state dump. Used by state export.
+pub struct StateDump {
+    /// Dumped accounts.
+    pub accounts: Vec<(H160, AccountDump)>,
+    /// Address to continue the dump from, `None` if the dump is complete.
+    pub next: Option<H160>,
+}
+
+#[derive(Clone, Eq, PartialEq, Encode, Decode, Default)]
+#[cfg_attr(feature = "std", derive(Debug, Serialize, Deserialize))]
+/// Result of an SSVM execution. Used by runtime API.
+pub struct CallInfo {
+    /// Output data of the execution.
+    #[cfg_attr(feature = "std", serde(with = "crate::types::hex_bytes"))]
+    pub output: Vec<u8>,
+    /// Gas used by the execution, before refunds.
+    pub gas_used: U256,
+    /// Gas refunded for cleared storage and destroyed contracts.
+    pub gas_refunded: U256,
+    /// Gas charged for the execution, `gas_used` minus `gas_refunded`.
+    pub effective_gas_used: U256,
+    /// EVMC status code of the execution.
+    pub status_code: i32,
+}
+
+#[derive(Clone, Eq, PartialEq, Encode, Decode, Default)]
+#[cfg_attr(feature = "std", derive(Debug, Serialize, Deserialize))]
+/// Result of an SSVM execution with its output decoded. Used by runtime API.
+pub struct ExecutionOutput {
+    /// Raw result of the execution.
+    pub info: CallInfo,
+    /// Output decoded against the requested types, `None` if the execution
+    /// failed or the output does not match them.
+    pub decoded: Option<Vec<AbiValue>>,
+}
+
+#[cfg(feature = "xcm")]
+#[derive(Clone, Eq, PartialEq, Encode, Decode)]
+#[cfg_attr(feature = "std", derive(Debug))]
+/// SCALE encoded input of the XCM precompile.
+pub enum XcmMessage {
+    /// Transfer the reserve asset to a beneficiary on another parachain.
+    ReserveTransfer {
+        /// Destination parachain id.
+        dest: u32,
+        /// Beneficiary account on the destination.
+        beneficiary: H256,
+        /// Amount to transfer.
+        amount: U256,
+    },
+    /// Execute an encoded call on another parachain.
+    Transact {
+        /// Destination parachain id.
+        dest: u32,
+        /// Encoded call on the destination.
+        call: Vec<u8>,
+        /// Maximum weight of the call.
+        weight: u32,
+    },
+}
+
+/// Ethereum 2048-bit logs bloom.
+#[derive(Clone, Encode, Decode)]
+pub struct Bloom(pub [u8; 256]);
+
+impl PartialEq for Bloom {
+    fn eq(&self, other: &Self) -> bool {
+        self.0[..] == other.0[..]
+    }
+}
+
+impl Eq for Bloom {}
+
+impl Default for Bloom {
+    fn default() -> Self {
+        Self([0u8; 256])
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::fmt::Debug for Bloom {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "Bloom({})", hex::encode(&self.0[..]))
+    }
+}
+
+impl Bloom {
+    /// Set the three bits selected by the keccak hash of `input`.
+    pub fn accrue(&mut self, input: &[u8]) {
+        let hash = keccak_256(input);
+        for i in 0..3 {
+            let bit = (((hash[2 * i] as usize) << 8) | hash[2 * i + 1] as usize) & 2047;
+            self.0[255 - bit / 8] |= 1 << (bit % 8);
+        }
+    }
+
+    /// Accrue the address and all topics of a log.
+    pub fn accrue_log(&mut self, log: &Log) {
+        self.accrue(log.address.as_bytes());
+        for topic in log.topics.iter() {
+            self.accrue(topic.as_bytes());
+        }
+    }
+}
+
+#[derive(Clone, Copy, Eq, PartialEq, Ord, PartialOrd, Encode, Decode)]
+#[cfg_attr(feature = "std", derive(Debug, Serialize, Deserialize))]
+/// Hard fork whose rules contracts execute under.
+pub enum SsvmRevision {
+    Byzantium,
+    Istanbul,
+    Berlin,
+    /// London gas refund rules. The engine executes with the Berlin
+    /// instruction set, as it has no London revision.
+    London,
+}
+
+impl Default for SsvmRevision {
+    fn default() -> Self {
+        SsvmRevision::Byzantium
+    }
+}
+
+impl SsvmRevision {
+    /// EVMC revision the engine executes with.
+    #[cfg(feature = "std")]
+    pub fn evmc_revision(&self) -> Revision {
+        match self {
+            SsvmRevision::Byzantium => Revision::EVMC_BYZANTIUM,
+            SsvmRevision::Istanbul => Revision::EVMC_ISTANBUL,
+            SsvmRevision::Berlin | SsvmRevision::London => Revision::EVMC_BERLIN,
+        }
+    }
+
+    /// Gas refunded for clearing a storage slot.
+    pub fn sstore_clear_refund(&self) -> i64 {
+        match self {
+            SsvmRevision::London => 4_800,
+            _ => 15_000,
+        }
+    }
+
+    /// Gas refunded for destroying a contract.
+    pub fn selfdestruct_refund(&self) -> i64 {
+        match self {
+            SsvmRevision::London => 0,
+            _ => 24_000,
+        }
+    }
+
+    /// Refunds are capped at the gas used divided by this quotient.
+    pub fn max_refund_quotient(&self) -> u64 {
+        match self {
+            SsvmRevision::London => 5,
+            _ => 2,
+        }
+    }
+}
+
+#[derive(Clone, Eq, PartialEq, Encode, Decode)]
+#[cfg_attr(feature = "std", derive(Debug, Serialize, Deserialize))]
+/// Fuel charged by host functions, on top of the gas metered by the VM. Fuel
+/// is granted at `Trait::FuelPerGas` per unit of gas.
+pub struct GasCostSchedule {
+    /// Reading a storage slot not yet accessed in the transaction.
+    pub get_storage_cold: u64,
+    /// Reading a storage slot already accessed in the transaction.
+    pub get_storage_warm: u64,
+    /// Setting a storage slot from zero.
+    pub set_storage_added: u64,
+    /// Changing a storage slot for the first time in the transaction.
+    pub set_storage_modified: u64,
+    /// Changing a storage slot already changed in the transaction.
+    pub set_storage_modified_again: u64,
+    /// Clearing a storage slot.
+    pub set_storage_deleted: u64,
+    /// Setting a storage slot to its current value.
+    pub set_storage_unchanged: u64,
+    /// Reading the balance of an account.
+    pub get_balance: u64,
+    /// Reading the code size of an account.
+    pub get_code_size: u64,
+    /// Reading the code hash of an account.
+    pub get_code_hash: u64,
+    /// Copying the code of an account.
+    pub copy_code: u64,
+    /// Destroying a contract.
+    pub selfdestruct: u64,
+    /// Reading a block hash.
+    pub get_block_hash: u64,
+    /// Emitting a log.
+    pub emit_log: u64,
+    /// Emitting a log, per topic.
+    pub emit_log_per_topic: u64,
+    /// Emitting a log, per byte of data.
+    pub emit_log_per_byte: u64,
+    /// Making a nested call or creation.
+    pub call: u64,
+}
+
+/// One unit of fuel per host function call, plus one per topic and byte of
+/// data of logs.
+impl Default for GasCostSchedule {
+    fn default() -> Self {
+        Self {
+            get_storage_cold: 1,
+            get_storage_warm: 1,
+            set_storage_added: 1,
+            set_storage_modified: 1,
+            set_storage_modified_again: 1,
+            set_storage_deleted: 1,
+            set_storage_unchanged: 1,
+            get_balance: 1,
+            get_code_size: 1,
+            get_code_hash: 1,
+            copy_code: 1,
+            selfdestruct: 1,
+            get_block_hash: 1,
+            emit_log: 1,
+            emit_log_per_topic: 1,
+            emit_log_per_byte: 1,
+            call: 1,
+        }
+    }
+}
+
+impl GasCostSchedule {
+    /// Check that storage, log, call and self-destruct costs are not zero,
+    /// that cold storage reads cost at least as much as warm ones, and that
+    /// adding a storage slot costs at least as much as modifying it, which
+    /// costs at least as much as modifying it again.
+    pub fn is_valid(&self) -> bool {
+        let critical = [
+            self.get_storage_cold,
+            self.get_storage_warm,
+            self.set_storage_added,
+            self.set_storage_modified,
+            self.set_storage_modified_again,
+            self.set_storage_deleted,
+            self.set_storage_unchanged,
+            self.emit_log,
+            self.selfdestruct,
+            self.call,
+        ];
+        critical.iter().all(|cost| *cost > 0)
+            && self.get_storage_cold >= self.get_storage_warm
+            && self.set_storage_added >= self.set_storage_modified
+            && self.set_storage_modified >= self.set_storage_modified_again
+    }
+
+    /// Fuel charged for setting a storage slot with the resulting `status`.
+    #[cfg(feature = "std")]
+    pub fn set_storage(&self, status: StorageStatus) -> u64 {
+        match status {
+            StorageStatus::EVMC_STORAGE_ADDED => self.set_storage_added,
+            StorageStatus::EVMC_STORAGE_MODIFIED => self.set_storage_modified,
+            StorageStatus::EVMC_STORAGE_MODIFIED_AGAIN => self.set_storage_modified_again,
+            StorageStatus::EVMC_STORAGE_DELETED => self.set_storage_deleted,
+            StorageStatus::EVMC_STORAGE_UNCHANGED => self.set_storage_unchanged,
+        }
+    }
+
+    /// Fuel charged for emitting a log with `topics` topics and `data_len`
+    /// bytes of data.
+    pub fn emit_log(&self, topics: usize, data_len: usize) -> u64 {
+        self.emit_log
+            .saturating_add(self.emit_log_per_topic.saturating_mul(topics as u64))
+            .saturating_add(self.emit_log_per_byte.saturating_mul(data_len as u64))
+    }
+}
+
+#[derive(Clone, Eq, PartialEq, Encode, Decode, Default)]
+#[cfg_attr(feature = "std", derive(Debug))]
+/// Summary of SSVM activity in a block. Used for `deposit_event`.
+pub struct BlockSummary {
+    /// Number of executed transactions.
+    pub tx_count: u32,
+    /// Total gas used by executed transactions.
+    pub gas_used: U256,
+    /// Bloom of all logs emitted in the block.
+    pub logs_bloom: Bloom,
+    /// Root hash over the state of touched accounts.
+    pub touched_root: H256,
+}
+
+/// Maximum number of topics of a log, as LOG4.
+pub const MAX_LOG_TOPICS: usize = 4;
+
+/// Bytes a storage slot takes in state, its key and value.
+pub const STORAGE_SLOT_BYTES: u64 = 64;
+
+#[derive(Clone, Copy, Eq, PartialEq, Encode, Decode, Default)]
+#[cfg_attr(feature = "std", derive(Debug))]
+/// Contract state bytes added and removed by a transaction, counting code and
+/// storage slots. Used for `deposit_event`.
+pub struct StorageGrowth {
+    /// Bytes of code deployed and storage slots set from zero.
+    pub bytes_added: u64,
+    /// Bytes of code of destroyed contracts and storage slots cleared.
+    pub bytes_removed: u64,
+}
+
+impl StorageGrowth {
+    /// Net bytes added, negative if more bytes were removed.
+    pub fn net(&self) -> i128 {
+        self.bytes_added as i128 - self.bytes_removed as i128
+    }
+
+    /// Check whether the transaction neither added nor removed bytes.
+    pub fn is_empty(&self) -> bool {
+        self.bytes_added == 0 && self.bytes_removed == 0
+    }
+}
+
+#[derive(Clone, Eq, PartialEq, Encode, Decode, Default)]
+#[cfg_attr(feature = "std", derive(Debug, Serialize, Deserialize))]
+/// Aggregate metrics of the SSVM transactions of a block.
+pub struct ExecutionMetrics {
+    /// Block number.
+    pub block_number: u64,
+    /// Number of executed transactions.
+    pub executions: u32,
+    /// Number of failed transactions by status code, sorted by status code.
+    pub failures: Vec<(i32, u32)>,
+    /// Gas used by executed transactions.
+    pub gas_used: U256,
+}
+
+impl ExecutionMetrics {
+    /// Count a transaction that ended with `status_code` after using
+    /// `gas_used`.
+    pub fn record(&mut self, status_code: i32, gas_used: u64) {
+        self.executions = self.executions.saturating_add(1);
+        self.gas_used = self.gas_used.saturating_add(gas_used.into());
+        if status_code == STATUS_SUCCESS {
+            return;
+        }
+        match self
+            .failures
+            .binary_search_by_key(&status_code, |(code, _)| *code)
+        {
+            Ok(index) => self.failures[index].1 = self.failures[index].1.saturating_add(1),
+            Err(index) => self.failures.insert(index, (status_code, 1)),
+        }
+    }
+
+    /// Number of failed transactions.
+    pub fn failure_count(&self) -> u32 {
+        self.failures
+            .iter()
+            .fold(0u32, |total, (_, count)| total.saturating_add(*count))
+    }
+
+    /// Average gas used by a transaction.
+    pub fn average_gas(&self) -> U256 {
+        self.gas_used
+            .checked_div(self.executions.into())
+            .unwrap_or_default()
+    }
+}
+
+#[derive(Clone, Copy, Eq, PartialEq, Encode, Decode, Default)]
+#[cfg_attr(feature = "std", derive(Debug, Serialize, Deserialize))]
+/// Transactions and gas of a sender in a rate limit window.
+pub struct RateLimitUsage {
+    /// Index of the window, the block number divided by the window length.
+    pub window: u64,
+    /// Number of transactions.
+    pub transactions: u32,
+    /// Gas used by the transactions.
+    pub gas_used: u64,
+}
+
+#[derive(Clone, Eq, PartialEq, Encode, Decode, Default)]
+#[cfg_attr(feature = "std", derive(Debug, Serialize, Deserialize))]
+/// Contract call made at the start of every block, e.g. by an oracle.
+pub struct BlockHook {
+    /// Account that registered the hook and holds its deposit.
+    pub owner: H160,
+    /// Gas limit of each call.
+    pub gas: u32,
+    /// Deposit held from the owner, returned when the hook is removed.
+    pub deposit: U256,
+    /// Whether the hook was approved and runs.
+    pub approved: bool,
+}
+
+#[derive(Clone, Eq, PartialEq, Encode, Decode, Default)]
+#[cfg_attr(feature = "std", derive(Debug, Serialize, Deserialize))]
+/// Recurring contract call paid from a prepayment, run by the module as a
+/// keeper.
+pub struct Job {
+    /// Account that registered the job and is refunded its prepayment.
+    pub owner: H160,
+    /// Contract called.
+    pub target: H160,
+    /// Input of each call.
+    pub input: Vec<u8>,
+    /// Gas limit of each call.
+    pub gas: u32,
+    /// Gas price of each call.
+    pub gas_price: U256,
+    /// Number of blocks between calls.
+    pub interval: u32,
+    /// Prepayment left to pay the fees of calls.
+    pub prepaid: U256,
+}
+
+impl Job {
+    /// Fee of a call using all its gas, which the prepayment must cover for
+    /// the call to run.
+    pub fn max_fee(&self) -> U256 {
+        U256::from(self.gas).saturating_mul(self.gas_price)
+    }
+}
+
+#[derive(Clone, Eq, PartialEq, Encode, Decode, Default)]
+#[cfg_attr(feature = "std", derive(Debug, Serialize, Deserialize))]
+/// Threshold approval required for transfers above
+/// `Trait::MultisigValueThreshold` from an account.
+pub struct MultisigGuard {
+    /// Number of signatories that must approve a call.
+    pub threshold: u32,
+    /// Signatories, sorted and without duplicates.
+    pub signatories: Vec<H160>,
+}
+
+#[derive(Clone, Eq, PartialEq, Encode, Decode, Default)]
+#[cfg_attr(feature = "std", derive(Debug, Serialize, Deserialize))]
+/// Gas statistics of a block. Used by the gas price oracle.
+pub struct BlockGasStats {
+    /// Block number.
+    pub block_number: u64,
+    /// Number of executed transactions.
+    pub tx_count: u32,
+    /// Total gas used by executed transactions.
+    pub gas_used: U256,
+    /// Minimum gas price of executed transactions.
+    pub min_gas_price: U256,
+    /// Median gas price of executed transactions.
+    pub median_gas_price: U256,
+    /// Maximum gas price of executed transactions.
+    pub max_gas_price: U256,
+    /// Gas price and gas used of each executed transaction, ordered by gas
+    /// price.
+    pub transactions: Vec<(U256, u64)>,
+}
+
+impl BlockGasStats {
+    /// Compute statistics from the gas price and gas used of a block's
+    /// transactions.
+    pub fn new(block_number: u64, gas_used: U256, mut transactions: Vec<(U256, u64)>) -> Self {
+        transactions.sort();
+        Self {
+            block_number,
+            tx_count: transactions.len() as u32,
+            gas_used,
+            min_gas_price: transactions.first().map(|tx| tx.0).unwrap_or_default(),
+            median_gas_price: transactions
+                .get(transactions.len() / 2)
+                .map(|tx| tx.0)
+                .unwrap_or_default(),
+            max_gas_price: transactions.last().map(|tx| tx.0).unwrap_or_default(),
+            transactions,
+        }
+    }
+
+    /// Tip above `base_fee` paid at each of the given percentiles of gas used
+    /// in the block, following `eth_feeHistory`.
+    pub fn rewards(&self, base_fee: U256, percentiles: &[Permill]) -> Vec<U256> {
+        let total: u64 = self.transactions.iter().map(|tx| tx.1).sum();
+        percentiles
+            .iter()
+            .map(|percentile| {
+                let threshold = *percentile * total;
+                let mut cumulative = 0u64;
+                for (gas_price, gas_used) in self.transactions.iter() {
+                    cumulative = cumulative.saturating_add(*gas_used);
+                    if cumulative >= threshold {
+                        return gas_price.saturating_sub(base_fee);
+                    }
+                }
+                U256::zero()
+            })
+            .collect()
+    }
+}
+
+#[derive(Clone, Eq, PartialEq, Encode, Decode, Default)]
+#[cfg_attr(feature = "std", derive(Debug, Serialize, Deserialize))]
+/// Fee history of a range of blocks, in the shape of `eth_feeHistory`.
+pub struct FeeHistory {
+    /// Number of the oldest block in the range.
+    pub oldest_block: u64,
+    /// Base fee of each block, plus the base fee of the block after the
+    /// newest one.
+    pub base_fee_per_gas: Vec<U256>,
+    /// Ratio of gas used to the block gas limit of each block.
+    pub gas_used_ratio: Vec<Permill>,
+    /// Tips at the requested percentiles of each block.
+    pub reward: Vec<Vec<U256>>,
+}
+
+#[derive(Clone, Eq, PartialEq, Encode, Decode, Default)]
+#[cfg_attr(feature = "std", derive(Debug))]
+/// Minimal Ethereum block header of a Substrate block. Its hash only depends
+/// on chain state, so all nodes agree on it.
+pub struct EthBlockHeader {
+    /// Hash of the Ethereum header of the parent block.
+    pub parent_hash: H256,
+    /// Root hash over the state of touched accounts, standing in for the
+    /// state root.
+    pub state_root: H256,
+    /// Root hash over the hashes of the block's transactions.
+    pub transactions_root: H256,
+    /// Root hash over the logs emitted in the block.
+    pub receipts_root: H256,
+    /// Bloom of all logs emitted in the block.
+    pub logs_bloom: Bloom,
+    /// Block number.
+    pub number: u64,
+    /// Total gas used by executed transactions.
+    pub gas_used: U256,
+    /// Block gas limit, the maximum block weight converted to gas.
+    pub gas_limit: U256,
+    /// Block timestamp, as seen by the `TIMESTAMP` opcode.
+    pub timestamp: u64,
+}
+
+impl EthBlockHeader {
+    /// Keccak hash of the RLP encoded header.
+    pub fn hash(&self) -> H256 {
+        let mut stream = rlp::RlpStream::new_list(9);
+        stream.append(&self.parent_hash);
+        stream.append(&self.state_root);
+        stream.append(&self.transactions_root);
+        stream.append(&self.receipts_root);
+        stream.append(&self.logs_bloom.0.to_vec());
+        stream.append(&self.number);
+        stream.append(&self.gas_used);
+        stream.append(&self.gas_limit);
+        stream.append(&self.timestamp);
+        H256::from(keccak_256(&stream.out()))
+    }
+}
+
+#[derive(Clone, Copy, Eq, PartialEq, Encode, Decode)]
+#[cfg_attr(feature = "std", derive(Debug, Serialize, Deserialize))]
+/// Outcome of an SSVM transaction.
+pub enum ExecutionOutcome {
+    /// Execution succeeded.
+    Succeeded,
+    /// Execution was reverted by the contract.
+    Reverted,
+    /// Execution ran out of gas.
+    OutOfGas,
+    /// Execution trapped in the VM, e.g. on an invalid instruction, with the
+    /// EVMC status code.
+    Trapped(i32),
+    /// Execution failed on an internal VM error, with the EVMC status code.
+    Fatal(i32),
+}
+
+impl ExecutionOutcome {
+    /// Outcome of an execution that ended with EVMC `status_code`.
+    pub fn from_status_code(status_code: i32) -> Self {
+        match status_code {
+            STATUS_SUCCESS => ExecutionOutcome::Succeeded,
+            STATUS_REVERT => ExecutionOutcome::Reverted,
+            STATUS_OUT_OF_GAS => ExecutionOutcome::OutOfGas,
+            code if code < 0 => ExecutionOutcome::Fatal(code),
+            code => ExecutionOutcome::Trapped(code),
+        }
+    }
+}
+
+#[derive(Clone, Eq, PartialEq, Encode, Decode)]
+#[cfg_attr(feature = "std", derive(Debug, Serialize, Deserialize))]
+/// Status of an SSVM transaction included in a block. Used by runtime API.
+pub struct TransactionStatus {
+    /// Number of the including block.
+    pub block_number: u64,
+    /// Index of the extrinsic in the block.
+    pub extrinsic_index: u32,
+    /// Sender of the transaction.
+    pub from: H160,
+    /// Recipient of a call, `None` for a contract creation.
+    pub to: Option<H160>,
+    /// Address of the created contract, if creation succeeded.
+    pub contract_address: Option<H160>,
+    /// Outcome of the execution.
+    pub outcome: ExecutionOutcome,
+    /// Gas charged for the transaction, after refunds.
+    pub gas_used: U256,
+}
+
+/// Hash identifying the SSVM transaction of `sender` with `nonce`.
+pub fn transaction_hash(sender: &H160, nonce: U256) -> H256 {
+    let mut stream = rlp::RlpStream::new_list(2);
+    stream.append(sender);
+    stream.append(&nonce);
+    H256::from(keccak_256(&stream.out()))
+}
+
 pub fn create_address(caller: H160, nonce: U256) -> H160 {
     let mut stream = rlp::RlpStream::new_list(2);
     stream.append(&caller);
     stream.append(&nonce);
-    H256::from_slice(Keccak256::digest(&stream.out()).as_slice()).into()
+    H256::from(keccak_256(&stream.out())).into()
+}
+
+/// Mapping of contract storage keys into keys of `AccountStorages`.
+pub trait StorageKeyHasher {
+    /// Map a contract storage key.
+    fn hash_key(key: &H256) -> H256;
+}
+
+/// Use contract storage keys as is.
+pub struct RawStorageKey;
+
+impl StorageKeyHasher for RawStorageKey {
+    fn hash_key(key: &H256) -> H256 {
+        *key
+    }
+}
+
+/// Hash contract storage keys with Keccak256, the layout of the secure trie
+/// of Ethereum clients.
+pub struct KeccakStorageKey;
+
+impl StorageKeyHasher for KeccakStorageKey {
+    fn hash_key(key: &H256) -> H256 {
+        H256::from(keccak_256(key.as_bytes()))
+    }
+}
+
+/// Hash contract storage keys with Blake2-256.
+pub struct Blake2StorageKey;
+
+impl StorageKeyHasher for Blake2StorageKey {
+    fn hash_key(key: &H256) -> H256 {
+        H256::from(sp_io::hashing::blake2_256(key.as_bytes()))
+    }
+}
+
+/// Keccak256 hash of contract code.
+pub fn code_hash(code: &[u8]) -> H256 {
+    H256::from(keccak_256(code))
+}
+
+#[derive(Clone, Eq, PartialEq, Encode, Decode, Default)]
+#[cfg_attr(feature = "std", derive(Debug, Serialize, Deserialize))]
+/// Block fields seen by contracts. Kept per block, so that queries against
+/// the state of a past block execute in the context of that block.
+pub struct BlockContext {
+    /// Block number.
+    pub number: u64,
+    /// Block timestamp.
+    pub timestamp: u64,
+    /// Block author, zero if it is unknown.
+    pub coinbase: H160,
+    /// Block gas limit, the maximum block weight converted to gas.
+    pub gas_limit: u64,
+    /// Block difficulty, a random value per block.
+    pub difficulty: U256,
+    /// Base fee per gas, the minimum gas price.
+    pub base_fee: U256,
 }
 
+/// Transaction and block fields seen by contracts. Block fields are kept
+/// unsigned and narrowed to the signed EVMC types only when handed to the VM.
+#[derive(Clone, Eq, PartialEq, Encode, Decode)]
+#[cfg_attr(feature = "std", derive(Debug))]
 pub struct TxContext {
     tx_gas_price: U256,
     tx_origin: H160,
     block_coinbase: H160,
-    block_number: i64,
-    block_timestamp: i64,
-    block_gas_limit: i64,
+    block_number: u64,
+    block_timestamp: u64,
+    block_gas_limit: u64,
     block_difficulty: U256,
     chain_id: U256,
 }
@@ -73,9 +785,9 @@ impl TxContext {
         tx_gas_price: U256,
         tx_origin: H160,
         block_coinbase: H160,
-        block_number: i64,
-        block_timestamp: i64,
-        block_gas_limit: i64,
+        block_number: u64,
+        block_timestamp: u64,
+        block_gas_limit: u64,
         block_difficulty: U256,
         chain_id: U256,
     ) -> Self {
@@ -90,19 +802,234 @@ impl TxContext {
             chain_id,
         }
     }
+
+    /// Block number, timestamp and gas limit as the signed EVMC types, `None`
+    /// if any of them does not fit.
+    pub fn evmc_block_fields(&self) -> Option<(i64, i64, i64)> {
+        let narrow = |value: u64| {
+            if value > i64::max_value() as u64 {
+                None
+            } else {
+                Some(value as i64)
+            }
+        };
+        Some((
+            narrow(self.block_number)?,
+            narrow(self.block_timestamp)?,
+            narrow(self.block_gas_limit)?,
+        ))
+    }
+}
+
+/// A message executed by SSVM, either a transaction or a nested call.
+#[cfg(feature = "std")]
+pub struct Message {
+    /// Kind of the call.
+    pub kind: CallKind,
+    /// Immediate caller, seen by contracts as `msg.sender`.
+    pub sender: H160,
+    /// Account whose storage and balance the code operates on.
+    pub target: H160,
+    /// Account whose code is executed. Contract creations execute `input`.
+    pub code_address: H160,
+    /// Value seen by contracts as `msg.value`.
+    pub value: U256,
+    /// Call data, or init code of contract creations.
+    pub input: Vec<u8>,
+    /// Gas limit of the message.
+    pub gas: u32,
+    /// Call depth, 0 for transactions.
+    pub depth: i32,
+    /// Whether the message must not change state.
+    pub is_static: bool,
+}
+
+#[cfg(feature = "std")]
+impl Message {
+    /// Top level message of a transaction from `sender` to `target`.
+    pub fn new(
+        kind: CallKind,
+        sender: H160,
+        target: H160,
+        value: U256,
+        input: Vec<u8>,
+        gas: u32,
+    ) -> Self {
+        Self {
+            kind,
+            sender,
+            target,
+            code_address: target,
+            value,
+            input,
+            gas,
+            depth: 0,
+            is_static: false,
+        }
+    }
+}
+
+/// Per-transaction storage overlay. Values read from storage are cached, and
+/// writes are buffered until the transaction is committed. Writes of a call
+/// frame are undone with `revert_to` if the frame fails.
+#[cfg(feature = "std")]
+#[derive(Default)]
+pub struct StorageOverlay {
+    /// Values of accessed slots at the start of the transaction.
+    original: HashMap<(H160, H256), H256>,
+    /// Values written during the transaction.
+    dirty: HashMap<(H160, H256), H256>,
+    /// Written slots with their previously written value, in write order.
+    journal: Vec<((H160, H256), Option<H256>)>,
+}
+
+/// State changed outside of the storage overlay during an execution, by
+/// precompiles, with what is needed to undo it.
+#[cfg(feature = "std")]
+pub enum JournalEntry {
+    /// Nonce and balance of an account before they changed.
+    Account(H160, Account),
+    /// ERC-20 allowance of a precompiled token before it changed. token,
+    /// (owner, spender), allowance
+    Allowance(H160, (H160, H160), U256),
+    /// Multisig approvals of a call before they were consumed. account, call
+    /// hash, approvals
+    MultisigApprovals(H160, H256, Vec<H160>),
+    /// Transfer of an asset, undone by the reverse transfer. asset, from, to,
+    /// amount
+    AssetTransfer(u128, H160, H160, U256),
+    /// Code stored by a nested creation, undone by removing it.
+    Code(H160),
+    /// Bond reserved for code stored by a nested creation, undone by
+    /// releasing it.
+    Bond(H160),
+}
+
+/// Point of an execution to roll back to when a call frame fails.
+#[cfg(feature = "std")]
+#[derive(Clone, Copy, Default)]
+pub struct Checkpoint {
+    /// Length of the storage overlay journal.
+    pub storage: usize,
+    /// Length of the journal of precompile state changes.
+    pub journal: usize,
+    /// Number of buffered logs.
+    pub logs: usize,
+    /// Number of buffered self-destructs.
+    pub selfdestructs: usize,
+    /// Gas refund accrued.
+    pub refund: i64,
+}
+
+#[cfg(feature = "std")]
+impl StorageOverlay {
+    /// Get the current value of a storage slot.
+    pub fn get<T: Trait>(&mut self, address: H160, key: H256) -> H256 {
+        if let Some(value) = self.dirty.get(&(address, key)) {
+            return *value;
+        }
+        self.original::<T>(address, key)
+    }
+
+    /// Get the value of a storage slot at the start of the transaction.
+    fn original<T: Trait>(&mut self, address: H160, key: H256) -> H256 {
+        *self
+            .original
+            .entry((address, key))
+            .or_insert_with(|| Module::<T>::read_storage(&address, &key))
+    }
+
+    /// Check whether a storage slot was already accessed.
+    pub fn is_warm(&self, address: H160, key: H256) -> bool {
+        self.original.contains_key(&(address, key))
+    }
+
+    /// Set the value of a storage slot, returning the EVMC storage status.
+    pub fn set<T: Trait>(&mut self, address: H160, key: H256, value: H256) -> StorageStatus {
+        let original = self.original::<T>(address, key);
+        let current = self.get::<T>(address, key);
+        let previous = self.dirty.insert((address, key), value);
+        self.journal.push(((address, key), previous));
+
+        if current == value {
+            StorageStatus::EVMC_STORAGE_UNCHANGED
+        } else if original != current {
+            StorageStatus::EVMC_STORAGE_MODIFIED_AGAIN
+        } else if original == H256::zero() {
+            StorageStatus::EVMC_STORAGE_ADDED
+        } else if value == H256::zero() {
+            StorageStatus::EVMC_STORAGE_DELETED
+        } else {
+            StorageStatus::EVMC_STORAGE_MODIFIED
+        }
+    }
+
+    /// Write buffered values into storage, and return the storage slots
+    /// added and removed as state growth. Each slot is written once with its
+    /// final value, and slots restored to their original value are not
+    /// written at all.
+    pub fn commit<T: Trait>(&self) -> StorageGrowth {
+        let mut growth = StorageGrowth::default();
+        for ((address, key), value) in self.dirty.iter() {
+            let original = self.original[&(*address, *key)];
+            if original == *value {
+                continue;
+            }
+            if original == H256::zero() {
+                growth.bytes_added += STORAGE_SLOT_BYTES;
+            } else if *value == H256::zero() {
+                growth.bytes_removed += STORAGE_SLOT_BYTES;
+            }
+            Module::<T>::write_storage(address, key, value);
+        }
+        growth
+    }
+
+    /// Number of writes so far, to `revert_to` when a call frame fails.
+    pub fn checkpoint(&self) -> usize {
+        self.journal.len()
+    }
+
+    /// Undo the writes made since `checkpoint`. Slots stay warm.
+    pub fn revert_to(&mut self, checkpoint: usize) {
+        while self.journal.len() > checkpoint {
+            match self.journal.pop() {
+                Some((slot, Some(previous))) => {
+                    self.dirty.insert(slot, previous);
+                }
+                Some((slot, None)) => {
+                    self.dirty.remove(&slot);
+                }
+                None => break,
+            }
+        }
+    }
+
+    /// Clear all cached and buffered values.
+    pub fn clear(&mut self) {
+        self.original.clear();
+        self.dirty.clear();
+        self.journal.clear();
+    }
 }
 
 #[cfg(feature = "std")]
 pub struct HostContext<T> {
     tx_context: TxContext,
+    /// Account whose storage and balance the executing code operates on.
+    address: H160,
+    /// Do not emit events or touch state outside of the storage cache.
+    read_only: bool,
     _marker: PhantomData<T>,
 }
 
 #[cfg(feature = "std")]
 impl<T> HostContext<T> {
-    pub fn new(tx_context: TxContext) -> Self {
+    pub fn new(tx_context: TxContext, address: H160, read_only: bool) -> Self {
         Self {
             tx_context,
+            address,
+            read_only,
             _marker: PhantomData,
         }
     }
@@ -114,51 +1041,102 @@ impl<T: Trait> HostInterface for HostContext<T> {
         true
     }
     fn get_storage(&mut self, address: &Address, key: &Bytes32) -> Bytes32 {
-        let ret =
-            Module::<T>::get_storage(H160::from(address.to_owned()), H256::from(key.to_owned()));
-        ret.to_fixed_bytes()
+        let address = H160::from(address.to_owned());
+        let key = H256::from(key.to_owned());
+        let warm = Module::<T>::is_storage_warm(address, key);
+        if !Module::<T>::charge_fuel(|schedule| {
+            if warm {
+                schedule.get_storage_warm
+            } else {
+                schedule.get_storage_cold
+            }
+        }) {
+            return [0u8; 32];
+        }
+        Module::<T>::get_storage(address, key).to_fixed_bytes()
     }
     fn set_storage(&mut self, address: &Address, key: &Bytes32, value: &Bytes32) -> StorageStatus {
-        Module::<T>::set_storage(
+        if Module::<T>::fuel_exhausted() {
+            return StorageStatus::EVMC_STORAGE_UNCHANGED;
+        }
+        let status = Module::<T>::set_storage(
             H160::from(address.to_owned()),
             H256::from(key.to_owned()),
             H256::from(value.to_owned()),
         );
-        StorageStatus::EVMC_STORAGE_MODIFIED
+        // The cost depends on the resulting status. If it can not be paid,
+        // the execution fails and the write is discarded with the overlay.
+        if !Module::<T>::charge_fuel(|schedule| schedule.set_storage(status)) {
+            return StorageStatus::EVMC_STORAGE_UNCHANGED;
+        }
+        if status == StorageStatus::EVMC_STORAGE_DELETED {
+            Module::<T>::add_refund(Module::<T>::revision().sstore_clear_refund());
+        }
+        status
     }
     fn get_balance(&mut self, address: &Address) -> Bytes32 {
-        let balance = Accounts::get(H160::from(address.to_owned())).balance;
-        balance.into()
+        if !Module::<T>::charge_fuel(|schedule| schedule.get_balance) {
+            return [0u8; 32];
+        }
+        Module::<T>::balance_of(&H160::from(address.to_owned())).into()
     }
     fn get_code_size(&mut self, address: &Address) -> usize {
-        AccountCodes::decode_len(H160::from(address)).unwrap_or(0)
+        if !Module::<T>::charge_fuel(|schedule| schedule.get_code_size) {
+            return 0;
+        }
+        Module::<T>::code_size(&H160::from(address))
     }
     fn get_code_hash(&mut self, address: &Address) -> Bytes32 {
-        H256::from_slice(Keccak256::digest(&AccountCodes::get(H160::from(address))).as_slice())
-            .into()
+        if !Module::<T>::charge_fuel(|schedule| schedule.get_code_hash) {
+            return [0u8; 32];
+        }
+        Module::<T>::cached_code_hash(&H160::from(address)).into()
     }
     fn copy_code(
         &mut self,
-        _addr: &Address,
-        _offset: &usize,
-        _buffer_data: &*mut u8,
-        _buffer_size: &usize,
+        addr: &Address,
+        offset: &usize,
+        buffer_data: &*mut u8,
+        buffer_size: &usize,
     ) -> usize {
-        0
+        if !Module::<T>::charge_fuel(|schedule| schedule.copy_code) || buffer_data.is_null() {
+            return 0;
+        }
+        // The VM hands over a buffer of `buffer_size` bytes that it owns for
+        // the duration of the call.
+        let buffer = unsafe { std::slice::from_raw_parts_mut(*buffer_data, *buffer_size) };
+        Module::<T>::copy_code(&H160::from(addr), *offset, buffer)
+    }
+    fn selfdestruct(&mut self, addr: &Address, beneficiary: &Address) {
+        if !Module::<T>::charge_fuel(|schedule| schedule.selfdestruct) {
+            return;
+        }
+        // Self-destructs are buffered until the execution is committed, so
+        // dry runs see the same refunds as transactions.
+        if Module::<T>::schedule_selfdestruct(H160::from(addr), H160::from(beneficiary)) {
+            Module::<T>::add_refund(Module::<T>::revision().selfdestruct_refund());
+        }
     }
-    fn selfdestruct(&mut self, _addr: &Address, _beneficiary: &Address) {}
     fn get_tx_context(&mut self) -> (Bytes32, Address, Address, i64, i64, i64, Bytes32) {
+        // Contexts are checked to fit when created by `Module::tx_context`.
+        let (block_number, block_timestamp, block_gas_limit) = self
+            .tx_context
+            .evmc_block_fields()
+            .unwrap_or((i64::max_value(), i64::max_value(), i64::max_value()));
         (
             self.tx_context.tx_gas_price.into(),
             self.tx_context.tx_origin.to_fixed_bytes(),
             self.tx_context.block_coinbase.to_fixed_bytes(),
-            self.tx_context.block_number,
-            self.tx_context.block_timestamp,
-            self.tx_context.block_gas_limit,
+            block_number,
+            block_timestamp,
+            block_gas_limit,
             self.tx_context.block_difficulty.into(),
         )
     }
     fn get_block_hash(&mut self, block_number: i64) -> Bytes32 {
+        if !Module::<T>::charge_fuel(|schedule| schedule.get_block_hash) {
+            return [0u8; 32];
+        }
         let number = U256::from(block_number);
         if number > U256::from(u32::max_value()) {
             H256::default().into()
@@ -168,14 +1146,30 @@ impl<T: Trait> HostInterface for HostContext<T> {
         }
     }
     fn emit_log(&mut self, address: &Address, topics: &Vec<Bytes32>, data: &Bytes) {
-        Module::<T>::deposit_event(Event::Log(Log {
+        if !Module::<T>::charge_fuel(|schedule| schedule.emit_log(topics.len(), data.len())) {
+            return;
+        }
+        // Oversized logs fail the execution, so contracts can not inflate
+        // events and the log index. As LOG0 to LOG4, a log has at most 4
+        // topics whatever `T::MaxTopics` allows.
+        if topics.len() > (T::MaxTopics::get() as usize).min(MAX_LOG_TOPICS)
+            || data.len() > T::MaxLogDataSize::get() as usize
+        {
+            Module::<T>::fail_execution();
+            return;
+        }
+        if self.read_only {
+            return;
+        }
+        let log = Log {
             address: H160::from(address.to_owned()),
             topics: topics
                 .iter()
                 .map(|b32| H256::from(b32))
                 .collect::<Vec<H256>>(),
             data: data.to_vec(),
-        }));
+        };
+        Module::<T>::deposit_log(log);
     }
     fn call(
         &mut self,
@@ -188,17 +1182,99 @@ impl<T: Trait> HostInterface for HostContext<T> {
         _depth: i32,
         _is_static: bool,
     ) -> (Vec<u8>, i64, Address, StatusCode) {
-        let (output, gas_left, status_code) = Module::<T>::execute_ssvm(
-            _sender.into(),
-            _destination.into(),
-            _value.into(),
-            _input.to_vec(),
-            _gas as u32,
-            self.tx_context.tx_gas_price.into(),
-            Accounts::get(H160::from(_sender)).nonce,
-            _kind,
-        )
-        .unwrap();
+        if !Module::<T>::charge_fuel(|schedule| schedule.call) {
+            return (
+                Vec::new(),
+                0,
+                [0u8; ADDRESS_LENGTH],
+                StatusCode::EVMC_OUT_OF_GAS,
+            );
+        }
+        // Delegate calls and call codes run the destination's code in the
+        // storage and balance context of the calling contract. The origin and
+        // gas price stay those of the transaction. A delegate call keeps the
+        // sender of the calling contract, so precompiles, which act as the
+        // sender, must not be reached this way: the calling contract would
+        // act as its own caller.
+        if let CallKind::EVMC_CREATE | CallKind::EVMC_CREATE2 = _kind {
+            let message = Message {
+                kind: _kind,
+                sender: H160::from(_sender),
+                target: H160::zero(),
+                code_address: H160::zero(),
+                value: _value.into(),
+                input: _input.to_vec(),
+                gas: _gas.max(0).min(u32::max_value() as i64) as u32,
+                depth: _depth,
+                is_static: _is_static,
+            };
+            let (output, gas_left, created_address, status_code) =
+                Module::<T>::execute_nested_create(
+                    self.tx_context.clone(),
+                    message,
+                    self.read_only,
+                );
+            return (
+                output,
+                gas_left,
+                created_address.to_fixed_bytes(),
+                status_code,
+            );
+        }
+        let destination = H160::from(_destination);
+        let target = match _kind {
+            CallKind::EVMC_DELEGATECALL | CallKind::EVMC_CALLCODE => {
+                if is_precompile(&destination) {
+                    return (
+                        Vec::new(),
+                        0,
+                        [0u8; ADDRESS_LENGTH],
+                        StatusCode::EVMC_FAILURE,
+                    );
+                }
+                self.address
+            }
+            _ => destination,
+        };
+        let message = Message {
+            kind: _kind,
+            sender: H160::from(_sender),
+            target,
+            code_address: destination,
+            value: _value.into(),
+            input: _input.to_vec(),
+            gas: _gas.max(0).min(u32::max_value() as i64) as u32,
+            depth: _depth,
+            is_static: _is_static,
+        };
+        // A failed or reverted frame leaves no trace: its storage writes,
+        // value transfer, logs, self-destructs, refunds and precompile state
+        // changes are rolled back, so callers may recover from it.
+        let checkpoint = Module::<T>::checkpoint();
+        // A delegate call passes on the value of the calling frame without
+        // moving it again.
+        if _kind != CallKind::EVMC_DELEGATECALL {
+            if Module::<T>::balance_of(&message.sender) < message.value {
+                Module::<T>::revert_to_checkpoint(checkpoint);
+                return (
+                    Vec::new(),
+                    message.gas as i64,
+                    [0u8; ADDRESS_LENGTH],
+                    StatusCode::EVMC_FAILURE,
+                );
+            }
+            if !self.read_only {
+                Module::<T>::journal_transfer(&message.sender, &message.target, message.value);
+            }
+        }
+        let (output, gas_left, status_code) =
+            match Module::<T>::execute_ssvm(self.tx_context.clone(), message, self.read_only) {
+                Ok(result) => result,
+                Err(_) => (Vec::new(), 0, StatusCode::EVMC_FAILURE),
+            };
+        if status_code != StatusCode::EVMC_SUCCESS {
+            Module::<T>::revert_to_checkpoint(checkpoint);
+        }
         return (output, gas_left, [0u8; ADDRESS_LENGTH], status_code);
     }
 }
@@ -0,0 +1,136 @@
+// Copyright (C) 2020 Second State.
+// This file is part of Pallet-SSVM.
+
+// Pallet-SSVM is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+
+// Pallet-SSVM is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+//! Conformance of SSVM executions across execution strategies.
+//!
+//! An execution through `executor::ssvm_runtime_interface` must give the same
+//! status, gas, output, logs and state changes whether the runtime runs
+//! natively or in Wasm. `run` executes a `Fixture` under both strategies in
+//! identical fresh externalities and reports the `diff` of their traces.
+//!
+//! The native strategy calls `Module::execute_vm` directly. The Wasm strategy
+//! calls `ssvm_runtime_interface::execute`, as a Wasm runtime does; the
+//! module tests run it through the host function registered by
+//! `executor::register`, and node tests through the Wasm build of their
+//! runtime.
+
+use crate::{hex_bytes, ExecutionResult, Log, VmContext};
+use codec::{Decode, Encode};
+use serde::{Deserialize, Serialize};
+
+/// A VM execution to run under each execution strategy.
+#[derive(Clone, Eq, PartialEq, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Fixture {
+    pub name: String,
+    #[serde(with = "hex_bytes")]
+    pub code: Vec<u8>,
+    #[serde(with = "hex_bytes")]
+    pub input: Vec<u8>,
+    /// SCALE encoded `VmContext`.
+    #[serde(with = "hex_bytes")]
+    pub context: Vec<u8>,
+}
+
+impl Fixture {
+    /// Fixture executing `code` with `input` in `context`.
+    pub fn new(name: &str, code: Vec<u8>, input: Vec<u8>, context: &VmContext) -> Self {
+        Fixture {
+            name: name.into(),
+            code,
+            input,
+            context: context.encode(),
+        }
+    }
+}
+
+/// Observable result of running a `Fixture`.
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub struct ExecutionTrace {
+    pub status_code: i32,
+    pub gas_left: i64,
+    pub output: Vec<u8>,
+    pub logs: Vec<Log>,
+    /// Storage root after the execution, covering all state changes.
+    pub state_root: Vec<u8>,
+}
+
+/// Difference between the traces of two execution strategies, native first.
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub enum Mismatch {
+    StatusCode(i32, i32),
+    GasLeft(i64, i64),
+    Output(Vec<u8>, Vec<u8>),
+    Logs(Vec<Log>, Vec<Log>),
+    StateRoot(Vec<u8>, Vec<u8>),
+}
+
+/// Run `fixture` with `execute`, the `ssvm_runtime_interface::execute` of an
+/// execution strategy, in the current externalities.
+pub fn trace(
+    fixture: &Fixture,
+    execute: impl FnOnce(&[u8], &[u8], &[u8]) -> Vec<u8>,
+) -> ExecutionTrace {
+    let result = execute(&fixture.code, &fixture.input, &fixture.context);
+    let result =
+        ExecutionResult::decode(&mut &result[..]).unwrap_or_else(|_| ExecutionResult::failure());
+    ExecutionTrace {
+        status_code: result.status_code,
+        gas_left: result.gas_left,
+        output: result.output,
+        logs: result.logs,
+        state_root: sp_io::storage::root(),
+    }
+}
+
+/// Differences between the `native` and `wasm` traces of a fixture, empty if
+/// the strategies conform.
+pub fn diff(native: &ExecutionTrace, wasm: &ExecutionTrace) -> Vec<Mismatch> {
+    let mut mismatches = Vec::new();
+    if native.status_code != wasm.status_code {
+        mismatches.push(Mismatch::StatusCode(native.status_code, wasm.status_code));
+    }
+    if native.gas_left != wasm.gas_left {
+        mismatches.push(Mismatch::GasLeft(native.gas_left, wasm.gas_left));
+    }
+    if native.output != wasm.output {
+        mismatches.push(Mismatch::Output(native.output.clone(), wasm.output.clone()));
+    }
+    if native.logs != wasm.logs {
+        mismatches.push(Mismatch::Logs(native.logs.clone(), wasm.logs.clone()));
+    }
+    if native.state_root != wasm.state_root {
+        mismatches.push(Mismatch::StateRoot(
+            native.state_root.clone(),
+            wasm.state_root.clone(),
+        ));
+    }
+    mismatches
+}
+
+/// Run `fixture` with the `native` and `wasm` strategies, each in fresh
+/// externalities built by `new_ext`, and return the differences between
+/// their traces.
+pub fn run(
+    fixture: &Fixture,
+    new_ext: impl Fn() -> sp_io::TestExternalities,
+    native: impl FnOnce(&[u8], &[u8], &[u8]) -> Vec<u8>,
+    wasm: impl FnOnce(&[u8], &[u8], &[u8]) -> Vec<u8>,
+) -> Vec<Mismatch> {
+    let native = new_ext().execute_with(|| trace(fixture, native));
+    let wasm = new_ext().execute_with(|| trace(fixture, wasm));
+    diff(&native, &wasm)
+}
@@ -0,0 +1,205 @@
+// Copyright (C) 2020 Second State.
+// This file is part of Pallet-SSVM.
+
+// Pallet-SSVM is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+
+// Pallet-SSVM is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+//! Delegation of SSVM execution to the node.
+//!
+//! SSVM only runs natively. Wasm runtimes built with the `executor` feature
+//! hand the transactions they dispatch to the node through the
+//! `ssvm_executor` runtime interface, and the node executes them with the
+//! native build of the same runtime, against the same state.
+//!
+//! The lower level `ssvm_runtime_interface` runs a single VM execution of
+//! given code, for runtimes handling transactions themselves.
+//!
+//! The node adds `ssvm_executor::HostFunctions` and
+//! `ssvm_runtime_interface::HostFunctions` to its executor and calls
+//! `register` with its runtime.
+
+use crate::{Log, TxContext};
+#[cfg(feature = "std")]
+use crate::{Module, Trait};
+use codec::{Decode, Encode};
+use sp_core::{H160, U256};
+use sp_runtime::{DispatchError, DispatchResult};
+use sp_runtime_interface::runtime_interface;
+use sp_std::vec::Vec;
+#[cfg(feature = "std")]
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// Transaction executed by the node on behalf of a Wasm runtime. The node
+/// fails requests whose `spec_version` differs from the one of its native
+/// runtime, which would execute them under different rules.
+#[derive(Clone, Eq, PartialEq, Encode, Decode)]
+#[cfg_attr(feature = "std", derive(Debug))]
+pub enum ExecutionRequest {
+    /// A call, with its fee paid by `fee_payer`, or by the payer chosen by
+    /// `Trait::Paymaster` if `None`.
+    Call {
+        spec_version: u32,
+        source: H160,
+        target: H160,
+        input: Vec<u8>,
+        value: U256,
+        gas_limit: u32,
+        gas_price: U256,
+        fee_payer: Option<H160>,
+    },
+    /// A contract creation, with the bond of the deployed code reserved from
+    /// the encoded account id `bond_payer`, or no bond if `None`.
+    Create {
+        spec_version: u32,
+        source: H160,
+        code: Vec<u8>,
+        value: U256,
+        gas_limit: u32,
+        gas_price: U256,
+        bond_payer: Option<Vec<u8>>,
+    },
+}
+
+impl ExecutionRequest {
+    /// Spec version of the runtime that made the request.
+    pub fn spec_version(&self) -> u32 {
+        match self {
+            ExecutionRequest::Call { spec_version, .. } => *spec_version,
+            ExecutionRequest::Create { spec_version, .. } => *spec_version,
+        }
+    }
+}
+
+/// Version of `ssvm_runtime_interface`, i.e. of the `VmContext` and
+/// `ExecutionResult` encodings. Hosts execute contexts of their version or
+/// older, and fail newer ones.
+pub const INTERFACE_VERSION: u32 = 1;
+
+/// Context of a VM execution through `ssvm_runtime_interface`.
+#[derive(Clone, Eq, PartialEq, Encode, Decode)]
+#[cfg_attr(feature = "std", derive(Debug))]
+pub struct VmContext {
+    /// `INTERFACE_VERSION` the context was encoded for.
+    pub version: u32,
+    pub tx_context: TxContext,
+    /// EVMC call kind: 0 call, 1 delegate call, 2 call code, 3 create and
+    /// 4 create2.
+    pub kind: u8,
+    pub sender: H160,
+    pub target: H160,
+    pub value: U256,
+    pub gas: u32,
+    /// Call depth, 0 for transactions.
+    pub depth: i32,
+    pub is_static: bool,
+    /// Whether state changes are discarded, as in dry runs.
+    pub read_only: bool,
+}
+
+/// Result of a VM execution through `ssvm_runtime_interface`.
+#[derive(Clone, Eq, PartialEq, Encode, Decode)]
+#[cfg_attr(feature = "std", derive(Debug))]
+pub struct ExecutionResult {
+    /// EVMC status code.
+    pub status_code: i32,
+    pub gas_left: i64,
+    pub output: Vec<u8>,
+    /// Logs of successful executions at depth 0.
+    pub logs: Vec<Log>,
+}
+
+impl ExecutionResult {
+    /// Result of executions the host could not run, with `EVMC_FAILURE`.
+    pub fn failure() -> Self {
+        Self {
+            status_code: 1,
+            gas_left: 0,
+            output: Vec::new(),
+            logs: Vec::new(),
+        }
+    }
+}
+
+/// Native function executing encoded requests, 0 until registered.
+#[cfg(feature = "std")]
+static EXECUTOR: AtomicUsize = AtomicUsize::new(0);
+
+/// Native function running VM executions, 0 until registered.
+#[cfg(feature = "std")]
+static VM_EXECUTOR: AtomicUsize = AtomicUsize::new(0);
+
+/// Execute the requests of Wasm runtimes with the native runtime `T`.
+#[cfg(feature = "std")]
+pub fn register<T: Trait>() {
+    let execute: fn(&[u8]) -> Vec<u8> = Module::<T>::execute_request;
+    EXECUTOR.store(execute as usize, Ordering::SeqCst);
+    let execute_vm: fn(&[u8], &[u8], &[u8]) -> Vec<u8> = Module::<T>::execute_vm;
+    VM_EXECUTOR.store(execute_vm as usize, Ordering::SeqCst);
+}
+
+/// Host functions executing SSVM transactions natively.
+#[runtime_interface]
+pub trait SsvmExecutor {
+    /// Execute an encoded `ExecutionRequest` and return the encoded
+    /// `DispatchResult`.
+    fn execute(request: &[u8]) -> Vec<u8> {
+        let executor = EXECUTOR.load(Ordering::SeqCst);
+        if executor == 0 {
+            let result: DispatchResult = Err(DispatchError::Other("No SSVM executor registered"));
+            return result.encode();
+        }
+        // Only `register` stores into `EXECUTOR`, and it stores a function of
+        // this type.
+        let execute: fn(&[u8]) -> Vec<u8> = unsafe { std::mem::transmute(executor) };
+        execute(request)
+    }
+}
+
+/// Host functions running SSVM natively.
+#[runtime_interface]
+pub trait SsvmRuntimeInterface {
+    /// `INTERFACE_VERSION` of the host.
+    fn version() -> u32 {
+        INTERFACE_VERSION
+    }
+
+    /// Execute `code` with `input` in an encoded `VmContext`, and return the
+    /// encoded `ExecutionResult`.
+    fn execute(code: &[u8], input: &[u8], context: &[u8]) -> Vec<u8> {
+        let executor = VM_EXECUTOR.load(Ordering::SeqCst);
+        if executor == 0 {
+            return ExecutionResult::failure().encode();
+        }
+        // Only `register` stores into `VM_EXECUTOR`, and it stores a function
+        // of this type.
+        let execute: fn(&[u8], &[u8], &[u8]) -> Vec<u8> = unsafe { std::mem::transmute(executor) };
+        execute(code, input, context)
+    }
+}
+
+/// Execute `code` with `input` in `context` through the node. Fails if the
+/// host does not support the version of `context`.
+pub fn execute(code: &[u8], input: &[u8], context: &VmContext) -> ExecutionResult {
+    if ssvm_runtime_interface::version() < context.version {
+        return ExecutionResult::failure();
+    }
+    let result = ssvm_runtime_interface::execute(code, input, &context.encode());
+    ExecutionResult::decode(&mut &result[..]).unwrap_or_else(|_| ExecutionResult::failure())
+}
+
+/// Execute a request through the node.
+pub fn delegate(request: ExecutionRequest) -> DispatchResult {
+    let result = ssvm_executor::execute(&request.encode());
+    DispatchResult::decode(&mut &result[..])
+        .unwrap_or_else(|_| Err(DispatchError::Other("Invalid SSVM executor result")))
+}
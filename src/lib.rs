@@ -19,35 +19,89 @@
 // Ensure we're `no_std` when compiling for Wasm.
 #![cfg_attr(not(feature = "std"), no_std)]
 
+pub mod abi;
 mod backend;
+#[cfg(feature = "std")]
+pub mod conformance;
+pub mod executor;
+#[cfg(feature = "std")]
+pub mod metrics;
+#[cfg(test)]
+mod mock;
+#[cfg(feature = "std")]
+mod precompiles;
+pub mod runtime_api;
+#[cfg(test)]
+mod tests;
+mod transaction;
+mod types;
+mod validation;
+pub mod vm;
 
+pub use crate::abi::{AbiType, AbiValue};
 #[cfg(feature = "std")]
-use crate::backend::HostContext;
-pub use crate::backend::{create_address, Account, Log, TxContext};
-use frame_support::traits::{Currency, ExistenceRequirement, WithdrawReason};
-use frame_support::weights::SimpleDispatchInfo;
-use frame_support::weights::{DispatchClass, FunctionOf, Weight};
-use frame_support::{decl_error, decl_event, decl_module, decl_storage};
-use frame_system::{self as system, ensure_signed};
+pub use crate::backend::Message;
+#[cfg(feature = "xcm")]
+pub use crate::backend::XcmMessage;
+pub use crate::backend::{
+    code_hash, create_address, transaction_hash, Account, AccountDump, Blake2StorageKey,
+    BlockContext, BlockGasStats, BlockHook, BlockSummary, Bloom, CallInfo, EthBlockHeader,
+    ExecutionMetrics, ExecutionOutcome, ExecutionOutput, FeeHistory, GasCostSchedule, Job,
+    KeccakStorageKey, Log, LogDecoder, MultisigGuard, RateLimitUsage, RawStorageKey, SsvmRevision,
+    StateDump, StorageGrowth, StorageKeyHasher, StorageRange, TransactionStatus, TxContext,
+};
+#[cfg(feature = "std")]
+use crate::backend::{Checkpoint, HostContext, JournalEntry, StorageOverlay};
+pub use crate::executor::{ExecutionRequest, ExecutionResult, VmContext};
+use crate::transaction::recover_signer;
+pub use crate::transaction::{
+    AccessListItem, CheckedTransaction, Eip1559Transaction, Eip2930Transaction, LegacyTransaction,
+    Transaction, TransactionAction, TransactionError,
+};
 #[cfg(feature = "std")]
-use lazy_static::lazy_static;
-use sha2::Sha256;
-use sha3::{Digest, Keccak256};
+pub use crate::types::{hex_bytes, hex_bytes_option, hex_quantity};
+pub use crate::types::{AccountInfo, CallRequest, StateOverride, TransactionRequest};
+pub use crate::validation::{
+    check_replay_protection, transaction_priority, validate_transaction, CheckSsvmTransaction,
+};
+pub use crate::vm::{CodeFormat, EvmcVm, Ssvm};
+use codec::{Decode, Encode};
+use frame_support::storage::{unhashed, StoragePrefixedMap};
+use frame_support::traits::{
+    Currency, EnsureOrigin, ExistenceRequirement, FindAuthor, Get, Randomness, ReservableCurrency,
+};
+use frame_support::weights::SimpleDispatchInfo;
+use frame_support::weights::{DispatchClass, FunctionOf, GetDispatchInfo, Weight};
+use frame_support::{
+    decl_error, decl_event, decl_module, decl_storage, ensure, Blake2_128Concat, Parameter,
+    StorageHasher,
+};
+use frame_system::{self as system, ensure_root, ensure_signed};
+use sha2::{Digest, Sha256};
 use sp_core::{Hasher, H160, H256, U256};
+use sp_io::hashing::keccak_256;
 use sp_runtime::ModuleId;
 use sp_runtime::{
-    traits::{AccountIdConversion, SaturatedConversion, UniqueSaturatedInto},
-    DispatchResult,
+    traits::{
+        AccountIdConversion, AtLeast32Bit, BadOrigin, Convert, Dispatchable, SaturatedConversion,
+        UniqueSaturatedInto,
+    },
+    DispatchError, DispatchResult, Percent, Permill,
 };
 use sp_std::convert::TryInto;
 use sp_std::{if_std, marker::PhantomData, vec::Vec};
 #[cfg(feature = "std")]
-use ssvm::types::{CallKind, Revision, StatusCode, StorageStatus};
+use ssvm::types::{CallKind, StatusCode, StorageStatus};
+#[cfg(feature = "std")]
+use std::cell::{Cell, RefCell};
 #[cfg(feature = "std")]
 use std::collections::HashMap;
-const MODULE_ID: ModuleId = ModuleId(*b"ssvmmoid");
 #[cfg(feature = "std")]
-use std::sync::Mutex;
+use std::rc::Rc;
+const MODULE_ID: ModuleId = ModuleId(*b"ssvmmoid");
+
+/// Weight of clearing a storage slot of a removed contract.
+const DELETION_WEIGHT_PER_SLOT: Weight = 10_000;
 
 /// Type alias for currency balance.
 pub type BalanceOf<T> =
@@ -91,21 +145,773 @@ impl<H: Hasher, A: AsRef<[u8]>> ConvertAccountId<A> for HashTruncateConvertAccou
     }
 }
 
+/// Trait for checking which origins may act as which `H160` source address
+/// in `call` and `create`.
+pub trait EnsureAddressOrigin<OuterOrigin> {
+    /// Ok if `origin` may act as `address`.
+    fn ensure_address_origin(address: &H160, origin: OuterOrigin) -> Result<(), BadOrigin>;
+}
+
+/// Signed origins may act as the address their account id converts to by
+/// `T::ConvertAccountId`.
+pub struct EnsureAddressMapped<T>(PhantomData<T>);
+
+impl<T: Trait> EnsureAddressOrigin<T::Origin> for EnsureAddressMapped<T> {
+    fn ensure_address_origin(address: &H160, origin: T::Origin) -> Result<(), BadOrigin> {
+        let who = ensure_signed(origin)?;
+        if T::ConvertAccountId::convert_account_id(&who) == *address {
+            Ok(())
+        } else {
+            Err(BadOrigin)
+        }
+    }
+}
+
+/// Signed origins may act as the address of the first 20 bytes of their
+/// account id.
+pub struct EnsureAddressTruncated<T>(PhantomData<T>);
+
+impl<T: frame_system::Trait> EnsureAddressOrigin<T::Origin> for EnsureAddressTruncated<T>
+where
+    T::AccountId: AsRef<[u8]>,
+{
+    fn ensure_address_origin(address: &H160, origin: T::Origin) -> Result<(), BadOrigin> {
+        let who = ensure_signed(origin)?;
+        if who.as_ref().get(0..20) == Some(address.as_bytes()) {
+            Ok(())
+        } else {
+            Err(BadOrigin)
+        }
+    }
+}
+
+/// Root may act as any address.
+pub struct EnsureAddressRoot<T>(PhantomData<T>);
+
+impl<T: frame_system::Trait> EnsureAddressOrigin<T::Origin> for EnsureAddressRoot<T> {
+    fn ensure_address_origin(_address: &H160, origin: T::Origin) -> Result<(), BadOrigin> {
+        ensure_root(origin)
+    }
+}
+
+/// Trait for converting `H160` addresses of EVM module into account ids,
+/// used when a contract acts on the rest of Substrate modules.
+pub trait ConvertAddress<A> {
+    /// Given an Ethereum address, return the corresponding Substrate address.
+    fn convert_address(address: &H160) -> A;
+}
+
+/// Hash the prefixed address, decoding the hash as the account id.
+pub struct HashedConvertAddress<H>(PhantomData<H>);
+
+impl<H: Hasher> Default for HashedConvertAddress<H> {
+    fn default() -> Self {
+        Self(PhantomData)
+    }
+}
+
+impl<H: Hasher, A: Decode + Default> ConvertAddress<A> for HashedConvertAddress<H> {
+    fn convert_address(address: &H160) -> A {
+        let mut data = [0u8; 24];
+        data[0..4].copy_from_slice(b"ssvm");
+        data[4..24].copy_from_slice(address.as_bytes());
+        let hash = H::hash(&data);
+        A::decode(&mut hash.as_ref()).unwrap_or_default()
+    }
+}
+
+/// Trait for filtering runtime calls dispatched by contracts.
+pub trait CallFilter<Call> {
+    /// Return true if contracts may dispatch `call`.
+    fn filter(call: &Call) -> bool;
+}
+
+/// Deny all runtime calls.
+impl<Call> CallFilter<Call> for () {
+    fn filter(_call: &Call) -> bool {
+        false
+    }
+}
+
+/// Fungible assets of other modules, exposed to contracts as ERC-20 tokens.
+pub trait Assets<A> {
+    /// Total supply of `asset`, `None` if the asset does not exist.
+    fn total_supply(asset: u128) -> Option<U256>;
+    /// Balance of `who` in `asset`.
+    fn balance(asset: u128, who: &A) -> U256;
+    /// Transfer `amount` of `asset` from `from` to `to`.
+    fn transfer(asset: u128, from: &A, to: &A, amount: U256) -> DispatchResult;
+}
+
+/// No assets.
+impl<A> Assets<A> for () {
+    fn total_supply(_asset: u128) -> Option<U256> {
+        None
+    }
+
+    fn balance(_asset: u128, _who: &A) -> U256 {
+        U256::zero()
+    }
+
+    fn transfer(_asset: u128, _from: &A, _to: &A, _amount: U256) -> DispatchResult {
+        Err(DispatchError::Other("No assets"))
+    }
+}
+
+/// Handler of gas fees withdrawn from the EVM balance of transaction senders.
+pub trait OnFeePayment {
+    /// Handle `fee` paid by `source`.
+    fn on_fee_payment(source: &H160, fee: U256);
+}
+
+/// Burn all fees.
+impl OnFeePayment for () {
+    fn on_fee_payment(_source: &H160, _fee: U256) {}
+}
+
+/// Pay fees to the EVM balance of a fixed address, e.g. a treasury.
+pub struct PayTo<A>(PhantomData<A>);
+
+impl<A: Get<H160>> OnFeePayment for PayTo<A> {
+    fn on_fee_payment(_source: &H160, fee: U256) {
+        Accounts::mutate(A::get(), |account| {
+            account.balance = account.balance.saturating_add(fee)
+        });
+    }
+}
+
+/// Pay fees to the EVM balance of the block author, burning them if the
+/// author is unknown.
+pub struct PayAuthor<T, F>(PhantomData<(T, F)>);
+
+impl<T: frame_system::Trait, F: FindAuthor<H160>> OnFeePayment for PayAuthor<T, F> {
+    fn on_fee_payment(_source: &H160, fee: U256) {
+        let digest = frame_system::Module::<T>::digest();
+        let pre_runtime_digests = digest.logs.iter().filter_map(|d| d.as_pre_runtime());
+        if let Some(author) = F::find_author(pre_runtime_digests) {
+            Accounts::mutate(author, |account| {
+                account.balance = account.balance.saturating_add(fee)
+            });
+        }
+    }
+}
+
+/// Pay fees to the currency balance of a fixed account, e.g. a treasury
+/// outside the EVM, from the module account backing EVM balances.
+pub struct PayToAccount<T, A>(PhantomData<(T, A)>);
+
+impl<T: Trait, A: Get<T::AccountId>> OnFeePayment for PayToAccount<T, A> {
+    fn on_fee_payment(_source: &H160, fee: U256) {
+        if let Some(fee) = T::FeeConverter::into_native(fee) {
+            // The fee stays in the module account, burnt from the EVM, if the
+            // transfer fails.
+            let _ = T::Currency::transfer(
+                &Module::<T>::account_id(),
+                &A::get(),
+                fee,
+                ExistenceRequirement::KeepAlive,
+            );
+        }
+    }
+}
+
+/// Hand `P` percent of fees to `First` and the rest to `Second`, e.g.
+/// `SplitFee<TreasuryShare, PayTo<Treasury>, PayAuthor<Runtime, FindAuthor>>`.
+pub struct SplitFee<P, First, Second>(PhantomData<(P, First, Second)>);
+
+impl<P: Get<Percent>, First: OnFeePayment, Second: OnFeePayment> OnFeePayment
+    for SplitFee<P, First, Second>
+{
+    fn on_fee_payment(source: &H160, fee: U256) {
+        let first = fee.saturating_mul(U256::from(P::get().deconstruct())) / U256::from(100);
+        First::on_fee_payment(source, first);
+        Second::on_fee_payment(source, fee - first);
+    }
+}
+
+/// Chooses who pays the fee of a call before it is charged, letting
+/// contracts sponsor transactions like ERC-4337 paymasters.
+pub trait Paymaster {
+    /// Address paying the fee, of at most `max_fee`, of a call of `target` by
+    /// `source` with `input`. `None` lets `source` pay.
+    fn fee_payer(source: &H160, target: &H160, input: &[u8], max_fee: U256) -> Option<H160>;
+}
+
+/// Senders always pay their fees.
+impl Paymaster for () {
+    fn fee_payer(_source: &H160, _target: &H160, _input: &[u8], _max_fee: U256) -> Option<H160> {
+        None
+    }
+}
+
+/// `validatePaymasterFee(address,bytes4,uint256)`
+pub const SELECTOR_VALIDATE_PAYMASTER_FEE: [u8; 4] = [0xa2, 0xa5, 0xc3, 0x47];
+
+/// Let the called contract pay the fee if its policy approves it. The policy
+/// is a static call from `SYSTEM_ADDRESS` with at most `G` gas of
+/// `validatePaymasterFee(source, selector, maxFee)` on the contract, which
+/// must return true.
+pub struct ContractPays<T, G>(PhantomData<(T, G)>);
+
+impl<T: Trait, G: Get<u32>> Paymaster for ContractPays<T, G> {
+    fn fee_payer(source: &H160, target: &H160, input: &[u8], max_fee: U256) -> Option<H160> {
+        if AccountCodes::decode_len(target).unwrap_or(0) == 0 {
+            return None;
+        }
+        let mut call = SELECTOR_VALIDATE_PAYMASTER_FEE.to_vec();
+        call.extend_from_slice(H256::from(*source).as_bytes());
+        let mut selector = [0u8; 32];
+        let len = input.len().min(4);
+        selector[..len].copy_from_slice(&input[..len]);
+        call.extend_from_slice(&selector);
+        let mut fee = [0u8; 32];
+        max_fee.to_big_endian(&mut fee);
+        call.extend_from_slice(&fee);
+
+        let info = Module::<T>::dry_run(
+            SYSTEM_ADDRESS,
+            Some(*target),
+            call,
+            U256::zero(),
+            G::get(),
+            U256::zero(),
+        )
+        .ok()?;
+        if info.status_code == STATUS_SUCCESS
+            && info.output.len() == 32
+            && U256::from_big_endian(&info.output) == U256::one()
+        {
+            Some(*target)
+        } else {
+            None
+        }
+    }
+}
+
+/// Handler called after each SSVM transaction, letting other modules react
+/// to contract activity.
+pub trait OnSsvmExecuted {
+    /// `sender` called or created `target`, ending with `status_code` after
+    /// using `gas_used` and emitting `logs`.
+    fn on_ssvm_executed(
+        sender: &H160,
+        target: &H160,
+        status_code: i32,
+        gas_used: u64,
+        logs: &[Log],
+    );
+}
+
+impl OnSsvmExecuted for () {
+    fn on_ssvm_executed(
+        _sender: &H160,
+        _target: &H160,
+        _status_code: i32,
+        _gas_used: u64,
+        _logs: &[Log],
+    ) {
+    }
+}
+
+/// Cross-chain message sender of parachain runtimes, used by the XCM
+/// precompile.
+#[cfg(feature = "xcm")]
+pub trait XcmSender<A> {
+    /// Transfer `amount` of the reserve asset from `origin` to `beneficiary`
+    /// on parachain `dest`.
+    fn reserve_transfer(origin: &A, dest: u32, beneficiary: H256, amount: U256) -> DispatchResult;
+    /// Execute the encoded `call` with at most `weight` on parachain `dest`.
+    fn transact(origin: &A, dest: u32, call: Vec<u8>, weight: Weight) -> DispatchResult;
+}
+
+/// Additional host functions beyond the standard EEI, e.g. reading the block
+/// author or querying another pallet. Contracts call them at the address
+/// `extended_host_address(function)`.
+pub trait ExtendedHostFunctions {
+    /// Gas charged for calling `function` with `input`, `None` if there is no
+    /// such function.
+    fn gas(function: u32, input: &[u8]) -> Option<u64>;
+    /// Execute `function` on behalf of `caller` with `input` and return its
+    /// output. `read_only` calls must not change state.
+    fn call(
+        function: u32,
+        caller: &H160,
+        input: &[u8],
+        read_only: bool,
+    ) -> Result<Vec<u8>, DispatchError>;
+}
+
+/// No extended host functions.
+impl ExtendedHostFunctions for () {
+    fn gas(_function: u32, _input: &[u8]) -> Option<u64> {
+        None
+    }
+
+    fn call(
+        _function: u32,
+        _caller: &H160,
+        _input: &[u8],
+        _read_only: bool,
+    ) -> Result<Vec<u8>, DispatchError> {
+        Err(DispatchError::Other("No extended host functions"))
+    }
+}
+
+/// Prefix of the addresses of extended host functions.
+pub const EXTENDED_HOST_PREFIX: [u8; 4] = [0xff, 0xff, 0xff, 0xfe];
+
+/// Address of the extended host function `function`, the prefix followed by
+/// zeros and the big endian function id.
+pub fn extended_host_address(function: u32) -> H160 {
+    let mut address = [0u8; 20];
+    address[0..4].copy_from_slice(&EXTENDED_HOST_PREFIX);
+    address[16..20].copy_from_slice(&function.to_be_bytes());
+    H160::from(address)
+}
+
+/// Function id of an extended host function address.
+pub fn extended_host_function(address: &H160) -> Option<u32> {
+    let bytes = address.as_bytes();
+    if bytes[0..4] != EXTENDED_HOST_PREFIX || bytes[4..16].iter().any(|b| *b != 0) {
+        return None;
+    }
+    let mut id = [0u8; 4];
+    id.copy_from_slice(&bytes[16..20]);
+    Some(u32::from_be_bytes(id))
+}
+
+/// Prefix of the ERC-20 precompile addresses of assets.
+pub const ASSET_PRECOMPILE_PREFIX: [u8; 4] = [0xff, 0xff, 0xff, 0xff];
+
+/// Address of the ERC-20 precompile of `asset`, the prefix followed by the
+/// big endian asset id.
+pub fn asset_address(asset: u128) -> H160 {
+    let mut address = [0u8; 20];
+    address[0..4].copy_from_slice(&ASSET_PRECOMPILE_PREFIX);
+    address[4..20].copy_from_slice(&asset.to_be_bytes());
+    H160::from(address)
+}
+
+/// Asset id of an ERC-20 precompile address of an asset.
+pub fn asset_id(address: &H160) -> Option<u128> {
+    let bytes = address.as_bytes();
+    if bytes[0..4] != ASSET_PRECOMPILE_PREFIX {
+        return None;
+    }
+    let mut id = [0u8; 16];
+    id.copy_from_slice(&bytes[4..20]);
+    Some(u128::from_be_bytes(id))
+}
+
+/// Address of the ERC-20 precompile for native EVM balances.
+pub const ERC20_PRECOMPILE: H160 = H160([
+    0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0x08, 0x00,
+]);
+/// Address of the precompile dispatching runtime calls.
+pub const DISPATCH_PRECOMPILE: H160 = H160([
+    0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0x08, 0x01,
+]);
+/// Address of the precompile returning runtime randomness.
+pub const RANDOMNESS_PRECOMPILE: H160 = H160([
+    0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0x08, 0x02,
+]);
+/// Address of the precompile sending cross-chain messages.
+#[cfg(feature = "xcm")]
+pub const XCM_PRECOMPILE: H160 = H160([
+    0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0x08, 0x03,
+]);
+
+/// Check whether `address` is served by a precompile rather than code.
+pub fn is_precompile(address: &H160) -> bool {
+    let bytes = address.as_bytes();
+    let low = bytes[0..18].iter().all(|b| *b == 0);
+    let standard = low && bytes[18] == 0 && [1, 2, 9].contains(&bytes[19]);
+    #[cfg(feature = "xcm")]
+    let native = *address == XCM_PRECOMPILE;
+    #[cfg(not(feature = "xcm"))]
+    let native = false;
+    standard
+        || native
+        || *address == ERC20_PRECOMPILE
+        || *address == DISPATCH_PRECOMPILE
+        || *address == RANDOMNESS_PRECOMPILE
+        || *address == MULTISIG_PRECOMPILE
+        || *address == GAS_LEFT_PRECOMPILE
+        || asset_id(address).is_some()
+        || extended_host_function(address).is_some()
+}
+
+/// Prefix of the reserved range at the top of the address space, holding the
+/// ERC-20 precompiles of assets and the extended host functions.
+pub const RESERVED_HIGH_PREFIX: [u8; 3] = [0xff, 0xff, 0xff];
+
+/// Check whether `address` is reserved for present and future precompiles
+/// and system accounts: the zero address and up to 0xffff, holding the
+/// standard and native precompiles and `SYSTEM_ADDRESS`, and the addresses
+/// starting with `RESERVED_HIGH_PREFIX`.
+pub fn is_reserved(address: &H160) -> bool {
+    let bytes = address.as_bytes();
+    bytes[0..18].iter().all(|b| *b == 0) || bytes[0..3] == RESERVED_HIGH_PREFIX
+}
+
+/// Address of the precompile managing multisig guards.
+pub const MULTISIG_PRECOMPILE: H160 = H160([
+    0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0x08, 0x04,
+]);
+
+/// Address of the precompile returning the gas left, accounting for the gas
+/// charged by host functions.
+pub const GAS_LEFT_PRECOMPILE: H160 = H160([
+    0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0x08, 0x05,
+]);
+
+/// Sender of system calls made by the runtime. No key exists for it.
+pub const SYSTEM_ADDRESS: H160 = H160([
+    0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0x09, 0x00,
+]);
+
+/// EVMC status code of a successful execution.
+pub const STATUS_SUCCESS: i32 = 0;
+/// EVMC status code of a generic execution failure.
+pub const STATUS_FAILURE: i32 = 1;
+/// EVMC status code of a reverted execution.
+pub const STATUS_REVERT: i32 = 2;
+/// EVMC status code of an execution that ran out of gas.
+pub const STATUS_OUT_OF_GAS: i32 = 3;
+
+/// Gas used by an execution with `gas_limit` that left `gas_left`.
+pub fn gas_used(gas_limit: u32, gas_left: i64) -> u64 {
+    (gas_limit as i64).saturating_sub(gas_left).max(0) as u64
+}
+
+/// Trait for converting balances of the currency module into EVM balances
+/// and back.
+pub trait BalanceConverter<B> {
+    /// Convert a currency balance into an EVM balance, `None` on overflow.
+    fn into_evm(balance: B) -> Option<U256>;
+    /// Convert an EVM balance into a currency balance, together with the dust
+    /// that can not be represented in the currency. `None` on overflow.
+    fn from_evm(value: U256) -> Option<(B, U256)>;
+}
+
+/// Scale balances by `10^D`, e.g. `D = 6` bridges a 12 decimals currency to
+/// 18 decimals EVM balances. Dust below `10^D` stays in the EVM account.
+pub struct ScaledBalanceConverter<D>(PhantomData<D>);
+
+impl<B: AtLeast32Bit, D: Get<u32>> BalanceConverter<B> for ScaledBalanceConverter<D> {
+    fn into_evm(balance: B) -> Option<U256> {
+        let balance: u128 = balance.try_into().ok()?;
+        U256::from(balance).checked_mul(U256::exp10(D::get() as usize))
+    }
+
+    fn from_evm(value: U256) -> Option<(B, U256)> {
+        let (balance, dust) = value.div_mod(U256::exp10(D::get() as usize));
+        if balance > U256::from(u128::max_value()) {
+            return None;
+        }
+        let balance: B = balance.low_u128().try_into().ok()?;
+        Some((balance, dust))
+    }
+}
+
+/// Trait for converting gas fees, in EVM balance units, into balances of the
+/// currency module.
+pub trait FeeConverter<B> {
+    /// Convert `fee` into a currency balance, rounded up so that a fee is
+    /// never truncated to zero. `None` on overflow.
+    fn into_native(fee: U256) -> Option<B>;
+}
+
+impl<B: AtLeast32Bit, D: Get<u32>> FeeConverter<B> for ScaledBalanceConverter<D> {
+    fn into_native(fee: U256) -> Option<B> {
+        let (fee, dust) = fee.div_mod(U256::exp10(D::get() as usize));
+        let fee = if dust.is_zero() {
+            fee
+        } else {
+            fee.checked_add(U256::one())?
+        };
+        if fee > U256::from(u128::max_value()) {
+            return None;
+        }
+        fee.low_u128().try_into().ok()
+    }
+}
+
 /// SSVM module trait
 pub trait Trait: frame_system::Trait + pallet_timestamp::Trait {
     /// Convert account ID to H160;
     type ConvertAccountId: ConvertAccountId<Self::AccountId>;
+    /// Origins allowed to act as source addresses of `call` and `create`.
+    type EnsureAddressOrigin: EnsureAddressOrigin<Self::Origin>;
     /// Currency type for deposit and withdraw.
-    type Currency: Currency<Self::AccountId>;
+    type Currency: ReservableCurrency<Self::AccountId>;
+    /// Convert currency balances into EVM balances.
+    type BalanceConverter: BalanceConverter<BalanceOf<Self>>;
+    /// Convert gas fees into currency balances. Fees are charged in whole
+    /// units of the currency, so it should scale balances like
+    /// `BalanceConverter`.
+    type FeeConverter: FeeConverter<BalanceOf<Self>>;
     /// The overarching event type.
     type Event: From<Event> + Into<<Self as frame_system::Trait>::Event>;
+    /// Minimum gas price accepted for a transaction, before escalation under
+    /// load.
+    type MinGasPrice: Get<U256>;
+    /// Share of the block gas limit, averaged over recent blocks, above which
+    /// the minimum gas price escalates. `Permill::one()` disables escalation.
+    type CongestionThreshold: Get<Permill>;
+    /// Rate the minimum gas price grows by at each block while the average
+    /// gas usage is above `CongestionThreshold`, and decays by otherwise.
+    type GasPriceAdjustment: Get<Permill>;
+    /// Fuel granted per unit of gas. Host function calls burn fuel as priced
+    /// by the `GasCostSchedule` of the revision, and execution aborts with out
+    /// of gas once it is exhausted.
+    type FuelPerGas: Get<u64>;
+    /// Convert H160 to account ID.
+    type ConvertAddress: ConvertAddress<Self::AccountId>;
+    /// Runtime call type contracts may dispatch through the dispatch precompile.
+    type DispatchCall: Parameter
+        + Dispatchable<Origin = <Self as frame_system::Trait>::Origin>
+        + GetDispatchInfo;
+    /// Filter of runtime calls contracts may dispatch. It must not allow calls
+    /// into this module, which can not be re-entered during an execution.
+    type DispatchFilter: CallFilter<Self::DispatchCall>;
+    /// Convert weight of dispatched runtime calls into gas.
+    type WeightToGas: Convert<Weight, u64>;
+    /// Fungible assets exposed to contracts as ERC-20 precompiles.
+    type Assets: Assets<Self::AccountId>;
+    /// Source of randomness exposed to contracts by the randomness precompile.
+    type Randomness: Randomness<H256>;
+    /// Host functions exposed to contracts in addition to the standard EEI.
+    type ExtendedHostFunctions: ExtendedHostFunctions;
+    /// Cross-chain message sender used by the XCM precompile.
+    #[cfg(feature = "xcm")]
+    type XcmSender: XcmSender<Self::AccountId>;
+    /// Maximum weight of a call contracts may transact on another chain.
+    #[cfg(feature = "xcm")]
+    type MaxXcmWeight: Get<Weight>;
+    /// Maximum size of contract metadata in bytes.
+    type MaxMetadataSize: Get<u32>;
+    /// Mapping of contract storage keys into the keys they are stored under.
+    type StorageKeyHasher: StorageKeyHasher;
+    /// Number of recent blocks to keep gas statistics of.
+    type GasStatsHistory: Get<u32>;
+    /// Origin allowed to upgrade contract code. The account it resolves to
+    /// must also own the contract. Use `EnsureNever` to disable upgrades.
+    type CodeUpgradeOrigin: EnsureOrigin<Self::Origin, Success = Self::AccountId>;
+    /// Number of blocks Ethereum transaction hashes and logs are kept in the
+    /// index, or 0 to keep them forever.
+    type TransactionIndexRetention: Get<u32>;
+    /// Handler of gas fees paid by transactions. Use `()` to burn them.
+    type OnFeePayment: OnFeePayment;
+    /// Handler called after each SSVM transaction.
+    type OnSsvmExecuted: OnSsvmExecuted;
+    /// Chooses who pays the fee of `call` transactions. Use `()` to let
+    /// senders pay.
+    type Paymaster: Paymaster;
+    /// Engine executing contract code. Use `Ssvm` for Ewasm, or pair it with
+    /// a runtime provided engine to pick one by the format of the code.
+    type Vm: EvmcVm + Default;
+    /// Hard fork whose rules contracts execute under, until a scheduled fork
+    /// activates.
+    type Revision: Get<SsvmRevision>;
+    /// Origin allowed to schedule hard forks.
+    type ForkOrigin: EnsureOrigin<Self::Origin>;
+    /// EIP-155 chain id of Ethereum transactions, also returned to contracts.
+    type ChainId: Get<u64>;
+    /// Accept Ethereum transactions signed without EIP-155 replay protection.
+    type AllowUnprotectedTransactions: Get<bool>;
+    /// Maximum size of contract code in bytes.
+    type MaxCodeSize: Get<u32>;
+    /// Maximum size of the data of a log in bytes.
+    type MaxLogDataSize: Get<u32>;
+    /// Maximum number of topics of a log. Logs never have more than 4 topics.
+    type MaxTopics: Get<u32>;
+    /// Maximum size of the input of a transaction in bytes, call data or
+    /// creation code.
+    type MaxTransactionInputSize: Get<u32>;
+    /// Reject `call` transactions with input to addresses that have no code
+    /// and are no precompile. Such calls succeed without running anything.
+    type RejectCallsWithoutCode: Get<bool>;
+    /// Value above which transfers from an account with a multisig guard
+    /// need the approval of its signatories.
+    type MultisigValueThreshold: Get<U256>;
+    /// Maximum number of signatories of a multisig guard.
+    type MaxSignatories: Get<u32>;
+    /// Length in blocks of the windows in which transactions and gas of each
+    /// sender are rate limited.
+    type RateLimitWindow: Get<u32>;
+    /// Maximum number of SSVM transactions of a sender per window, 0 for no
+    /// limit.
+    type MaxTransactionsPerWindow: Get<u32>;
+    /// Maximum gas of SSVM transactions of a sender per window, 0 for no
+    /// limit. Transactions are checked against their gas limit.
+    type MaxGasPerWindow: Get<u64>;
+    /// Maximum number of storage slots of removed contracts cleared per block,
+    /// within the rest of `MaxInitializeWeight`. Storage is cleared at once
+    /// when the contract is removed if 0.
+    type DeletionBudget: Get<u32>;
+    /// Maximum weight of a `static_call` by another module, bounding its gas
+    /// through `WeightToGas`. Callers account for it in their own weight.
+    type MaxStaticCallWeight: Get<Weight>;
+    /// Origin allowed to approve and remove block hooks.
+    type HookOrigin: EnsureOrigin<Self::Origin>;
+    /// Deposit held from the EVM balance of the owner of a block hook.
+    type HookDeposit: Get<U256>;
+    /// Maximum gas of a call of a block hook.
+    type MaxHookGas: Get<u32>;
+    /// Maximum number of registered block hooks.
+    type MaxBlockHooks: Get<u32>;
+    /// Maximum gas of a call of a keeper job.
+    type MaxJobGas: Get<u32>;
+    /// Maximum number of keeper jobs run per block. Jobs due beyond it are
+    /// postponed to the next block.
+    type MaxJobsPerBlock: Get<u32>;
+    /// Maximum weight of the block hooks, keeper jobs and storage deletions
+    /// run in `on_initialize`. A hook or job runs only if its gas limit fits
+    /// in the rest of it.
+    type MaxInitializeWeight: Get<Weight>;
+    /// Author of blocks, seen by contracts as the coinbase.
+    type FindAuthor: FindAuthor<H160>;
+    /// Number of blocks the block fields seen by contracts are kept for
+    /// historical queries and tracing, or 0 to keep them forever.
+    type BlockContextRetention: Get<u32>;
+    /// System contracts deployed by the runtime itself, such as a deployer
+    /// helper, multicall or a deterministic-deployment proxy. (address, code)
+    type SystemContracts: Get<Vec<(H160, Vec<u8>)>>;
+    /// Version of `SystemContracts`. They are deployed at genesis, and
+    /// redeployed at the end of the first block run by a runtime with a new
+    /// version.
+    type SystemContractsVersion: Get<u32>;
+    /// Origin allowed to overwrite the nonce and balance of any account, to
+    /// recover stuck accounts on test networks or after a faulty migration.
+    /// Use `EnsureNever` to disable it in production.
+    type ForceOrigin: EnsureOrigin<Self::Origin>;
+    /// Bond per byte of code of a created contract, in EVM balance units.
+    /// It is reserved in `Currency`, converted by `FeeConverter`, from the
+    /// signer of the creation, or for contracts created by contracts from the
+    /// payer of the creating contract's bond. It is released when the
+    /// contract is removed, e.g. when it self-destructs. Zero disables bonds.
+    type ContractBondPerByte: Get<U256>;
 }
 
 decl_storage! {
     trait Store for Module<T: Trait> as SSVM {
         Accounts get(fn accounts) config(): map hasher(blake2_128_concat) H160 => Account;
         AccountCodes: map hasher(blake2_128_concat) H160 => Vec<u8>;
+        /// Keccak hash of the code of each account with code, kept in sync
+        /// with `AccountCodes`.
+        AccountCodeHashes: map hasher(blake2_128_concat) H160 => Option<H256>;
         AccountStorages: double_map hasher(blake2_128_concat) H160, hasher(blake2_128_concat) H256 => H256;
+        /// Removed contracts whose storage is still being cleared, oldest first.
+        DeletionQueue get(fn deletion_queue): Vec<H160>;
+        /// Whether the storage of a removed contract is still being cleared.
+        /// Such storage reads as zero.
+        PendingDeletions: map hasher(blake2_128_concat) H160 => bool;
+        /// ERC-20 allowances of precompiled tokens. token, (owner, spender) => amount
+        Allowances: double_map hasher(blake2_128_concat) H160, hasher(blake2_128_concat) (H160, H160) => U256;
+
+        /// Number of SSVM transactions executed in the current block.
+        BlockTxCount: u32;
+        /// Gas used by SSVM transactions in the current block.
+        BlockGasUsed: U256;
+        /// Bloom of logs emitted in the current block.
+        BlockLogsBloom: Bloom;
+        /// Accounts touched in the current block.
+        BlockTouchedAccounts: Vec<H160>;
+        /// Gas price and gas used of SSVM transactions in the current block.
+        BlockGasPrices: Vec<(U256, u64)>;
+        /// Execution metrics of the current block.
+        BlockMetrics: ExecutionMetrics;
+        /// Execution metrics of the last finalized block.
+        LastBlockMetrics get(fn last_block_metrics): ExecutionMetrics;
+        /// Ring buffer of gas statistics of recent blocks, indexed by block
+        /// number modulo `T::GasStatsHistory`.
+        GasStats: map hasher(twox_64_concat) u32 => Option<BlockGasStats>;
+
+        /// Block number and extrinsic index of ingested Ethereum transactions,
+        /// by keccak hash of their RLP payload.
+        TransactionIndex get(fn transaction_by_hash): map hasher(identity) H256 => Option<(u64, u32)>;
+        /// Status of SSVM transactions by hash.
+        TransactionStatuses get(fn transaction_status): map hasher(identity) H256 => Option<TransactionStatus>;
+        /// Hashes of Ethereum transactions ingested and SSVM transactions
+        /// executed in a block, for pruning.
+        BlockTransactionHashes get(fn block_transaction_hashes): map hasher(twox_64_concat) u64 => Vec<H256>;
+        /// Logs emitted in a block, with the index of the extrinsic that
+        /// emitted them.
+        BlockLogs get(fn block_logs): map hasher(twox_64_concat) u64 => Vec<(u32, Log)>;
+
+        /// Ethereum header of each block.
+        EthBlocks get(fn eth_block): map hasher(twox_64_concat) u64 => Option<EthBlockHeader>;
+        /// Block number by hash of its Ethereum header.
+        EthBlockNumbers get(fn eth_block_number): map hasher(identity) H256 => Option<u64>;
+
+        /// Number of accounts imported by `import_state`.
+        ImportedAccounts get(fn imported_accounts): u64;
+
+        /// Owner of contracts, the deployer unless ownership was transferred.
+        ContractOwner get(fn owner_of): map hasher(blake2_128_concat) H160 => Option<H160>;
+
+        /// Bonds of created contracts in EVM balance units, reserved in the
+        /// currency from the account paying them. contract => (creator,
+        /// payer, bond)
+        ContractBonds get(fn contract_bond): map hasher(blake2_128_concat) H160 => Option<(H160, T::AccountId, U256)>;
+        /// Metadata of contracts, e.g. name, ABI hash or source link.
+        ContractMetadata get(fn contract_metadata): map hasher(blake2_128_concat) H160 => Vec<u8>;
+        /// Format of the code of contracts, deciding the engine executing it.
+        CodeFormats get(fn code_format): map hasher(blake2_128_concat) H160 => CodeFormat;
+
+        /// Revisions scheduled to activate at a block number.
+        ForkSchedule get(fn fork_schedule): map hasher(twox_64_concat) u64 => Option<SsvmRevision>;
+        /// Revision activated by the fork schedule, overriding `T::Revision`.
+        ActiveRevision: Option<SsvmRevision>;
+        /// Fuel costs of host functions by revision. Revisions without a
+        /// schedule use the default one.
+        GasSchedules get(fn gas_schedule_of): map hasher(twox_64_concat) SsvmRevision => Option<GasCostSchedule>;
+
+        /// Usage of the current rate limit window by sender.
+        RateLimits get(fn rate_limit): map hasher(blake2_128_concat) H160 => RateLimitUsage;
+
+        /// Multisig guards of accounts.
+        MultisigGuards get(fn multisig_guard): map hasher(blake2_128_concat) H160 => Option<MultisigGuard>;
+        /// Signatories that approved a call of a guarded account, by call hash.
+        MultisigApprovals get(fn multisig_approvals): double_map hasher(blake2_128_concat) H160, hasher(identity) H256 => Vec<H160>;
+
+        /// Decoders of logs into typed events. address, topic0 => decoder
+        LogDecoders get(fn log_decoder): double_map hasher(blake2_128_concat) H160, hasher(blake2_128_concat) H256 => Option<LogDecoder>;
+
+        /// Contract calls made at the start of every block. (contract, selector) => hook
+        BlockHooks get(fn block_hook): map hasher(blake2_128_concat) (H160, [u8; 4]) => Option<BlockHook>;
+        /// Registered block hooks, in the order they run.
+        BlockHookList get(fn block_hook_list): Vec<(H160, [u8; 4])>;
+
+        /// Keeper jobs by id.
+        Jobs get(fn job): map hasher(twox_64_concat) u64 => Option<Job>;
+        /// Id of the next registered keeper job.
+        NextJobId get(fn next_job_id): u64;
+        /// Block fields seen by contracts in each finalized block.
+        BlockContexts get(fn block_context_at): map hasher(twox_64_concat) u64 => Option<BlockContext>;
+
+        /// Keeper jobs due in a block. block number => job ids
+        JobSchedule get(fn job_schedule): map hasher(twox_64_concat) u64 => Vec<u64>;
+
+        /// Code hashes of the deployed system contracts.
+        SystemContracts get(fn system_contract): map hasher(blake2_128_concat) H160 => Option<H256>;
+
+        /// Exponential moving average of the share of the block gas limit used
+        /// by recent blocks.
+        GasUsageAverage get(fn gas_usage_average): Permill;
+
+        /// Escalation of the minimum gas price under load, in millionths above
+        /// `T::MinGasPrice`.
+        MinGasPriceEscalation get(fn min_gas_price_escalation): u64;
+
+        /// Version of the system contracts last deployed from the runtime.
+        SystemContractsVersion get(fn system_contracts_version): u32;
+    }
+    add_extra_genesis {
+        build(|_config: &GenesisConfig| {
+            Module::<T>::deploy_system_contracts();
+        });
     }
 }
 
@@ -113,10 +919,84 @@ decl_event! {
     /// SSVM events
     pub enum Event {
         Nonce(U256),
-        Create(H160),
         Call(H160),
         Output(Vec<u8>),
         Log(Log),
+        /// A contract was created. (deployer, address, code hash, init code hash, gas used)
+        Created(H160, H160, H256, H256, U256),
+        /// A contract creation did not succeed. (deployer, address, status code)
+        CreateFailed(H160, H160, i32),
+        /// Code was stored for an account. (address, code hash)
+        CodeStored(H160, H256),
+        /// Balance was deposited from currency module. (address, value)
+        BalanceDeposit(H160, U256),
+        /// Balance was withdrawn into currency module. (address, value)
+        BalanceWithdraw(H160, U256),
+        /// A contract self-destructed. (address, beneficiary)
+        Selfdestructed(H160, H160),
+        /// The storage of a removed contract was cleared.
+        StorageCleared(H160),
+        /// An execution did not succeed. (target, status code)
+        ExecutionFailed(H160, i32),
+        /// Metadata of a contract was registered. (address)
+        MetadataRegistered(H160),
+        /// Ownership of a contract was transferred. (address, old owner, new owner)
+        OwnershipTransferred(H160, H160, H160),
+        /// Code of a contract was upgraded. (address, old code hash, new code hash)
+        CodeUpdated(H160, H256, H256),
+        /// A chunk of accounts was imported. (accounts in chunk, total imported)
+        StateImported(u32, u64),
+        /// Gas charged to a transaction. (source, gas used after refund, gas refunded)
+        GasUsed(H160, U256, U256),
+        /// Decoded ERC-20 transfer log. (token, from, to, value)
+        Erc20Transfer(H160, H160, H160, U256),
+        /// Decoded ERC-20 approval log. (token, owner, spender, value)
+        Erc20Approval(H160, H160, H160, U256),
+        /// A log decoder was set or removed. (address, topic0)
+        LogDecoderSet(H160, H256),
+        /// A hard fork was scheduled, or unscheduled if `None`. (block number, revision)
+        ForkScheduled(u64, Option<SsvmRevision>),
+        /// Fuel costs of host functions under a revision were set.
+        GasScheduleSet(SsvmRevision),
+        /// A scheduled hard fork activated. (block number, revision)
+        ForkActivated(u64, SsvmRevision),
+        /// Summary of SSVM activity at the end of a block.
+        BlockSummary(BlockSummary),
+        /// Contract state bytes added and removed by a transaction. (source, growth)
+        StorageGrowth(H160, StorageGrowth),
+        /// EVM balance was transferred without running code. (from, to, value)
+        Transfer(H160, H160, U256),
+        /// A multisig guard was set, or removed if the threshold is 0. (address, threshold, signatories)
+        MultisigGuardSet(H160, u32, u32),
+        /// A signatory approved a call of a guarded account. (address, call hash, signatory)
+        MultisigApproval(H160, H256, H160),
+        /// Fee of a call was paid by a sponsor. (fee payer, source, fee)
+        FeeSponsored(H160, H160, U256),
+        /// A block hook was registered. (contract, selector, owner)
+        BlockHookRegistered(H160, [u8; 4], H160),
+        /// A block hook was approved. (contract, selector)
+        BlockHookApproved(H160, [u8; 4]),
+        /// A block hook was removed. (contract, selector)
+        BlockHookRemoved(H160, [u8; 4]),
+        /// A block hook was called. (contract, selector, status code)
+        BlockHookExecuted(H160, [u8; 4], i32),
+        /// A keeper job was registered. (id, owner, target)
+        JobRegistered(u64, H160, H160),
+        /// A keeper job was run. (id, status code, fee)
+        JobExecuted(u64, i32, U256),
+        /// A keeper job was cancelled, or drained of its prepayment. (id, refund)
+        JobRemoved(u64, U256),
+        /// A system contract was deployed or upgraded. (address, code hash)
+        SystemContractDeployed(H160, H256),
+        /// A system contract was removed. (address)
+        SystemContractRemoved(H160),
+        /// The nonce and balance of an account were overwritten.
+        /// (address, old nonce, new nonce, old balance, new balance)
+        AccountForced(H160, U256, U256, U256, U256),
+        /// A bond was reserved for a created contract. (contract, deployer, bond)
+        ContractBonded(H160, H160, U256),
+        /// The bond of a removed contract was released. (contract, deployer, bond)
+        ContractBondRefunded(H160, H160, U256),
         // LogMessage(String),
     }
 }
@@ -141,6 +1021,65 @@ decl_error! {
         ExitReasonFatal,
         /// Nonce is invalid
         InvalidNonce,
+        /// Balance conversion overflowed
+        BalanceConversionOverflow,
+        /// Contract metadata is too large
+        MetadataTooLarge,
+        /// Sender is not the owner of the contract
+        NotContractOwner,
+        /// Contract does not exist
+        ContractNotFound,
+        /// Storage of a contract removed at the address is still being cleared
+        PendingDeletion,
+        /// No engine of the chain executes code in this format
+        UnsupportedCodeFormat,
+        /// Method arguments cannot be ABI encoded
+        InvalidAbiArguments,
+        /// Transaction is signed for another chain
+        InvalidChainId,
+        /// Transaction is not replay protected
+        UnprotectedTransaction,
+        /// Hard fork scheduled at a past block
+        ForkInPast,
+        /// Contract code is too large
+        CodeTooLarge,
+        /// Transaction input is too large
+        InputTooLarge,
+        /// Call with input to an address without code
+        NoCode,
+        /// Plain transfer to a contract or precompile
+        HasCode,
+        /// Transfer not approved by the signatories of the multisig guard
+        NotApproved,
+        /// Multisig threshold is 0 or above the number of signatories, or too many signatories
+        InvalidMultisigGuard,
+        /// Fee payer signature of a sponsored call is invalid
+        InvalidPayerSignature,
+        /// Sender exceeded its transactions or gas in the rate limit window
+        RateLimited,
+        /// Gas schedule has zero critical costs or inconsistent relations
+        InvalidGasSchedule,
+        /// Block number or timestamp does not fit the types seen by contracts
+        TxContextOverflow,
+        /// Block hook is already registered
+        HookExists,
+        /// Block hook is not registered
+        HookNotFound,
+        /// Too many block hooks are registered
+        TooManyHooks,
+        /// Gas of the block hook is above the maximum
+        HookGasTooHigh,
+        /// Keeper job not found, or not owned by the sender
+        JobNotFound,
+        /// Nonce of the sender reached its maximum
+        NonceOverflow,
+        /// Address is reserved for precompiles or system accounts
+        ReservedAddress,
+        /// Keeper job gas is above the maximum, its interval is 0, or its
+        /// prepayment does not cover a call
+        InvalidJob,
+        /// Bond payer can not afford the bond of the deployed code
+        BondReserveFailed,
     }
 }
 
@@ -150,24 +1089,103 @@ decl_module! {
 
         fn deposit_event() = default;
 
+        /// Minimum gas price accepted for a transaction.
+        const MinGasPrice: U256 = T::MinGasPrice::get();
+
+        /// Fuel granted per unit of gas.
+        const FuelPerGas: u64 = T::FuelPerGas::get();
+
+        /// Maximum size of contract metadata in bytes.
+        const MaxMetadataSize: u32 = T::MaxMetadataSize::get();
+
+        /// Maximum size of contract code in bytes.
+        const MaxCodeSize: u32 = T::MaxCodeSize::get();
+
+        /// Maximum size of the data of a log in bytes.
+        const MaxLogDataSize: u32 = T::MaxLogDataSize::get();
+
+        /// Maximum number of topics of a log.
+        const MaxTopics: u32 = T::MaxTopics::get();
+
+        /// Maximum size of the input of a transaction in bytes.
+        const MaxTransactionInputSize: u32 = T::MaxTransactionInputSize::get();
+
+        /// Length in blocks of rate limit windows.
+        const RateLimitWindow: u32 = T::RateLimitWindow::get();
+
+        /// Maximum number of transactions of a sender per rate limit window.
+        const MaxTransactionsPerWindow: u32 = T::MaxTransactionsPerWindow::get();
+
+        /// Maximum gas of a sender per rate limit window.
+        const MaxGasPerWindow: u64 = T::MaxGasPerWindow::get();
+
+        /// Maximum weight of a static call by another module.
+        const MaxStaticCallWeight: Weight = T::MaxStaticCallWeight::get();
+
+        /// Bond held per byte of code of a created contract.
+        const ContractBondPerByte: U256 = T::ContractBondPerByte::get();
+
+        /// Maximum weight of the block hooks, keeper jobs and storage deletions
+        /// run per block.
+        const MaxInitializeWeight: Weight = T::MaxInitializeWeight::get();
+
+        fn on_initialize(n: T::BlockNumber) -> Weight {
+            let block_number: u64 = n.unique_saturated_into();
+            let budget = T::MaxInitializeWeight::get();
+            let mut weight = Self::run_block_hooks(block_number, budget);
+            weight = weight.saturating_add(Self::run_jobs(block_number, budget.saturating_sub(weight)));
+            weight.saturating_add(Self::process_deletion_queue(budget.saturating_sub(weight)))
+        }
+
+        fn on_finalize(n: T::BlockNumber) {
+            let block_number: u64 = n.unique_saturated_into();
+            Self::snapshot_block_context(block_number);
+            if SystemContractsVersion::get() != T::SystemContractsVersion::get() {
+                Self::deploy_system_contracts();
+            }
+            let tx_count = BlockTxCount::take();
+            let gas_used = BlockGasUsed::take();
+            Self::record_gas_stats(block_number, gas_used, BlockGasPrices::take());
+            Self::adjust_min_gas_price(gas_used);
+            let mut metrics = BlockMetrics::take();
+            metrics.block_number = block_number;
+            LastBlockMetrics::put(metrics);
+            let logs_bloom = BlockLogsBloom::take();
+            let touched_root = Self::touched_root(BlockTouchedAccounts::take());
+            Self::store_eth_block(block_number, gas_used, logs_bloom.clone(), touched_root);
+            Self::prune_transaction_index(block_number);
+            Self::activate_fork(block_number + 1);
+            if tx_count > 0 {
+                Module::<T>::deposit_event(Event::BlockSummary(BlockSummary {
+                    tx_count,
+                    gas_used,
+                    logs_bloom,
+                    touched_root,
+                }));
+            }
+        }
+
         /// Deposit balance from currency/balances module into Ewasm.
         #[weight = SimpleDispatchInfo::FixedNormal(10_000)]
         fn deposit_balance(origin, value: BalanceOf<T>) {
             let sender = ensure_signed(origin)?;
+            let bvalue = T::BalanceConverter::into_evm(value)
+                .ok_or(Error::<T>::BalanceConversionOverflow)?;
 
-            let imbalance = T::Currency::withdraw(
-                &sender,
-                value,
-                WithdrawReason::Reserve.into(),
-                ExistenceRequirement::AllowDeath,
-            )?;
-            T::Currency::resolve_creating(&Self::account_id(), imbalance);
+            // Depositing the whole balance may reap the sender, but leaving dust
+            // below the existential deposit, which would be burnt, is refused.
+            let existence = if T::Currency::free_balance(&sender) == value {
+                ExistenceRequirement::AllowDeath
+            } else {
+                ExistenceRequirement::KeepAlive
+            };
+            T::Currency::transfer(&sender, &Self::account_id(), value, existence)?;
 
-            let bvalue = U256::from(UniqueSaturatedInto::<u128>::unique_saturated_into(value));
             let address = T::ConvertAccountId::convert_account_id(&sender);
             Accounts::mutate(&address, |account| {
                 account.balance += bvalue;
             });
+            Module::<T>::deposit_event(Event::BalanceDeposit(address, bvalue));
         }
 
         /// Withdraw balance from Ewasm into currency/balances module.
@@ -175,28 +1193,60 @@ decl_module! {
         fn withdraw_balance(origin, value: BalanceOf<T>) {
             let sender = ensure_signed(origin)?;
             let address = T::ConvertAccountId::convert_account_id(&sender);
-            let bvalue = U256::from(UniqueSaturatedInto::<u128>::unique_saturated_into(value));
+            let bvalue = T::BalanceConverter::into_evm(value)
+                .ok_or(Error::<T>::BalanceConversionOverflow)?;
 
             let mut account = Accounts::get(&address);
             account.balance = account.balance.checked_sub(bvalue)
                 .ok_or(Error::<T>::BalanceLow)?;
+            Self::ensure_multisig_approved(&address, &address, bvalue, &[])?;
 
-            let imbalance = T::Currency::withdraw(
+            // The module account backs all EVM balances and must never be
+            // reaped. The transfer fails rather than burning a withdrawal that
+            // leaves the sender below the existential deposit.
+            T::Currency::transfer(
                 &Self::account_id(),
+                &sender,
                 value,
-                WithdrawReason::Reserve.into(),
-                ExistenceRequirement::AllowDeath
+                ExistenceRequirement::KeepAlive,
             )?;
 
             Accounts::insert(&address, account);
+            Module::<T>::deposit_event(Event::BalanceWithdraw(address, bvalue));
+        }
+
+        /// Transfer EVM balance from the sender's address to `to`, without
+        /// running the VM. Transfers to contracts must go through `call`, so
+        /// that their code runs.
+        #[weight = SimpleDispatchInfo::FixedNormal(10_000)]
+        fn transfer(origin, to: H160, value: U256) {
+            let sender = ensure_signed(origin)?;
+            let from = T::ConvertAccountId::convert_account_id(&sender);
 
-            T::Currency::resolve_creating(&sender, imbalance);
+            ensure!(
+                AccountCodes::decode_len(&to).unwrap_or(0) == 0 && !is_precompile(&to),
+                Error::<T>::HasCode
+            );
+            ensure!(!Self::is_reserved_address(&to), Error::<T>::ReservedAddress);
+            let account = Accounts::get(&from);
+            ensure!(account.balance >= value, Error::<T>::BalanceLow);
+            Self::check_rate_limit(&from, 0)?;
+            Self::ensure_multisig_approved(&from, &to, value, &[])?;
+
+            Self::transfer_value(&from, &to, value);
+            Accounts::mutate(&from, |account| {
+                account.nonce = account.nonce.saturating_add(U256::one());
+            });
+            Self::record_rate_limit(&from, 0);
+            Self::record_transaction_status(from, account.nonce, Some(to), None, STATUS_SUCCESS, 0);
+            Module::<T>::deposit_event(Event::Transfer(from, to, value));
         }
 
         /// Issue an Ewasm call operation. This is similar to a message call transaction in Ethereum.
-        #[weight = FunctionOf(|(_, _, _, gas_limit, gas_price): (&H160, &Vec<u8>, &U256, &u32, &U256)| (*gas_price).saturated_into::<Weight>().saturating_mul(*gas_limit), DispatchClass::Normal, true)]
+        #[weight = FunctionOf(|(_, _, _, _, gas_limit, gas_price): (&H160, &H160, &Vec<u8>, &U256, &u32, &U256)| (*gas_price).saturated_into::<Weight>().saturating_mul(*gas_limit), DispatchClass::Normal, true)]
         fn call(
             origin,
+            source: H160,
             target: H160,
             input: Vec<u8>,
             value: U256,
@@ -204,224 +1254,2864 @@ decl_module! {
             gas_price: U256,
         ) -> DispatchResult {
             if_std!{
-                let sender = ensure_signed(origin)?;
-                let source = T::ConvertAccountId::convert_account_id(&sender);
-                let nonce = Accounts::get(&source).nonce;
-                let (result, gas_left, status_code) = Self::execute_ssvm(
+                T::EnsureAddressOrigin::ensure_address_origin(&source, origin)?;
+                let fee_payer = Self::fee_payer(&source, &target, &input, gas_limit, gas_price);
+                Self::execute_call(source, target, input, value, gas_limit, gas_price, fee_payer)?;
+            }
+            #[cfg(all(not(feature = "std"), feature = "executor"))]
+            {
+                T::EnsureAddressOrigin::ensure_address_origin(&source, origin)?;
+                executor::delegate(ExecutionRequest::Call {
+                    spec_version: Self::spec_version(),
                     source,
                     target,
-                    value,
                     input,
+                    value,
                     gas_limit,
                     gas_price,
-                    nonce,
-                    CallKind::EVMC_CALL,
-                )?;
+                    fee_payer: None,
+                })?;
+            }
+            Ok(())
+        }
 
-                if status_code == StatusCode::EVMC_SUCCESS {
-                    Self::sync_storage();
-                }
-                Self::drop_storage_cache();
+        /// Call the method with `selector` of a contract, with `args` ABI
+        /// encoded on chain. Otherwise the same as `call`.
+        #[weight = FunctionOf(|(_, _, _, _, _, gas_limit, gas_price): (&H160, &H160, &[u8; 4], &Vec<AbiValue>, &U256, &u32, &U256)| (*gas_price).saturated_into::<Weight>().saturating_mul(*gas_limit), DispatchClass::Normal, true)]
+        fn call_method(
+            origin,
+            source: H160,
+            target: H160,
+            selector: [u8; 4],
+            args: Vec<AbiValue>,
+            value: U256,
+            gas_limit: u32,
+            gas_price: U256,
+        ) -> DispatchResult {
+            let input = abi::encode_call(selector, &args).ok_or(Error::<T>::InvalidAbiArguments)?;
+            Self::call(origin, source, target, input, value, gas_limit, gas_price)
+        }
 
-                Accounts::mutate(&source, |account| {
-                    account.nonce += U256::one();
-                });
-                Module::<T>::deposit_event(Event::Call(target));
-                Module::<T>::deposit_event(Event::Output(result.to_owned()));
+        /// Issue an Ewasm call operation whose fee is paid by `fee_payer`
+        /// rather than `source`, which still is the caller seen by contracts.
+        /// `payer_signature` is the 65 bytes secp256k1 signature of the payer
+        /// over `sponsorship_hash`.
+        #[weight = FunctionOf(|(_, _, _, _, gas_limit, gas_price, _, _): (&H160, &H160, &Vec<u8>, &U256, &u32, &U256, &H160, &Vec<u8>)| (*gas_price).saturated_into::<Weight>().saturating_mul(*gas_limit), DispatchClass::Normal, true)]
+        fn sponsored_call(
+            origin,
+            source: H160,
+            target: H160,
+            input: Vec<u8>,
+            value: U256,
+            gas_limit: u32,
+            gas_price: U256,
+            fee_payer: H160,
+            payer_signature: Vec<u8>,
+        ) -> DispatchResult {
+            T::EnsureAddressOrigin::ensure_address_origin(&source, origin)?;
+            Self::check_payer_signature(
+                &source,
+                &target,
+                &input,
+                value,
+                gas_limit,
+                gas_price,
+                &fee_payer,
+                &payer_signature,
+            )?;
+            if_std!{
+                Self::execute_call(source, target, input, value, gas_limit, gas_price, fee_payer)?;
+            }
+            #[cfg(all(not(feature = "std"), feature = "executor"))]
+            {
+                executor::delegate(ExecutionRequest::Call {
+                    spec_version: Self::spec_version(),
+                    source,
+                    target,
+                    input,
+                    value,
+                    gas_limit,
+                    gas_price,
+                    fee_payer: Some(fee_payer),
+                })?;
             }
             Ok(())
         }
 
         /// Create contract with Ewasm
-        #[weight = FunctionOf(|(_, _, gas_limit, gas_price): (&Vec<u8>, &U256, &u32, &U256)| (*gas_price).saturated_into::<Weight>().saturating_mul(*gas_limit), DispatchClass::Normal, true)]
+        #[weight = FunctionOf(|(_, _, _, gas_limit, gas_price): (&H160, &Vec<u8>, &U256, &u32, &U256)| (*gas_price).saturated_into::<Weight>().saturating_mul(*gas_limit), DispatchClass::Normal, true)]
         fn create(
             origin,
+            source: H160,
             code: Vec<u8>,
             value: U256,
             gas_limit: u32,
             gas_price: U256,
         ) -> DispatchResult {
+            // Signers pay the bond of the deployed code from their own account.
+            let bond_payer = ensure_signed(origin.clone()).ok();
             if_std!{
-                let sender = ensure_signed(origin)?;
-                let source = T::ConvertAccountId::convert_account_id(&sender);
-                let nonce = Accounts::get(&source).nonce;
-                let created_address = create_address(source, nonce);
-                let (output, gas_left, status_code) = Self::execute_ssvm(
+                T::EnsureAddressOrigin::ensure_address_origin(&source, origin)?;
+                Self::execute_create(source, code, value, gas_limit, gas_price, bond_payer)?;
+            }
+            #[cfg(all(not(feature = "std"), feature = "executor"))]
+            {
+                T::EnsureAddressOrigin::ensure_address_origin(&source, origin)?;
+                executor::delegate(ExecutionRequest::Create {
+                    spec_version: Self::spec_version(),
                     source,
-                    created_address,
-                    value,
                     code,
+                    value,
                     gas_limit,
                     gas_price,
-                    nonce,
-                    CallKind::EVMC_CREATE,
-                )?;
-
-                if status_code == StatusCode::EVMC_SUCCESS {
-                    Self::sync_storage();
-                }
-                Self::drop_storage_cache();
-
-                Accounts::mutate(&source, |account| {
-                    account.nonce += U256::one();
-                });
-                AccountCodes::insert(created_address, output.to_owned());
-                Module::<T>::deposit_event(Event::Create(created_address));
+                    bond_payer: bond_payer.map(|payer| payer.encode()),
+                })?;
             }
             Ok(())
         }
-    }
-}
 
-#[cfg(feature = "std")]
-lazy_static::lazy_static! {
-    static ref STORAGE_CACHE:Mutex<HashMap<(H160, H256), H256>> = Mutex::new(HashMap::new());
-}
+        /// Register metadata of a contract, such as name, ABI hash or source
+        /// link. Only the owner of the contract may register metadata.
+        #[weight = SimpleDispatchInfo::FixedNormal(10_000)]
+        fn register_contract_metadata(origin, address: H160, metadata: Vec<u8>) {
+            let sender = ensure_signed(origin)?;
+            let source = T::ConvertAccountId::convert_account_id(&sender);
 
-impl<T: Trait> Module<T> {
-    /// The account ID of the SSVM module.
-    pub fn account_id() -> T::AccountId {
-        MODULE_ID.into_account()
+            ensure!(
+                metadata.len() <= T::MaxMetadataSize::get() as usize,
+                Error::<T>::MetadataTooLarge
+            );
+            Self::ensure_contract_owner(&address, &source)?;
+
+            ContractMetadata::insert(&address, metadata);
+            Module::<T>::deposit_event(Event::MetadataRegistered(address));
+        }
+
+        /// Transfer ownership of a contract to `new_owner`.
+        #[weight = SimpleDispatchInfo::FixedNormal(10_000)]
+        fn transfer_contract_ownership(origin, address: H160, new_owner: H160) {
+            let sender = ensure_signed(origin)?;
+            let source = T::ConvertAccountId::convert_account_id(&sender);
+
+            Self::ensure_contract_owner(&address, &source)?;
+
+            ContractOwner::insert(&address, new_owner);
+            Module::<T>::deposit_event(Event::OwnershipTransferred(address, source, new_owner));
+        }
+
+        /// Import a chunk of accounts with their code and storage, e.g. to
+        /// migrate the state of an existing EVM chain. Storage keys are
+        /// inserted as given, already mapped by `T::StorageKeyHasher`.
+        #[weight = FunctionOf(
+            |(accounts,): (&Vec<(H160, AccountDump)>,)| {
+                let slots: usize = accounts.iter().map(|(_, dump)| dump.storage.len() + 1).sum();
+                (slots as Weight).saturating_mul(10_000)
+            },
+            DispatchClass::Operational,
+            true
+        )]
+        fn import_state(origin, accounts: Vec<(H160, AccountDump)>) {
+            ensure_root(origin)?;
+            let max_code_size = T::MaxCodeSize::get() as usize;
+            ensure!(
+                accounts.iter().all(|(_, dump)| dump.code.len() <= max_code_size),
+                Error::<T>::CodeTooLarge
+            );
+
+            let count = accounts.len() as u32;
+            for (address, dump) in accounts {
+                Self::import_account(&address, dump);
+            }
+            let total = ImportedAccounts::mutate(|total| {
+                *total = total.saturating_add(count as u64);
+                *total
+            });
+            Module::<T>::deposit_event(Event::StateImported(count, total));
+        }
+
+        /// Replace the code of a contract, keeping its storage and balance.
+        #[weight = SimpleDispatchInfo::FixedNormal(50_000)]
+        fn set_code(origin, address: H160, new_code: Vec<u8>) {
+            let sender = T::CodeUpgradeOrigin::ensure_origin(origin)?;
+            let source = T::ConvertAccountId::convert_account_id(&sender);
+
+            ensure!(
+                new_code.len() <= T::MaxCodeSize::get() as usize,
+                Error::<T>::CodeTooLarge
+            );
+            Self::ensure_contract_owner(&address, &source)?;
+
+            let old_hash = Self::account_code_hash(&address);
+            let new_hash = Self::store_code(&address, new_code);
+            Module::<T>::deposit_event(Event::CodeStored(address, new_hash));
+            Module::<T>::deposit_event(Event::CodeUpdated(address, old_hash, new_hash));
+        }
+
+        /// Activate `revision` at block `block_number`, or cancel the fork
+        /// scheduled there if `revision` is `None`.
+        #[weight = SimpleDispatchInfo::FixedOperational(10_000)]
+        fn schedule_fork(origin, block_number: u64, revision: Option<SsvmRevision>) {
+            T::ForkOrigin::ensure_origin(origin)?;
+            let current: u64 = frame_system::Module::<T>::block_number().unique_saturated_into();
+            ensure!(block_number > current, Error::<T>::ForkInPast);
+
+            match revision {
+                Some(revision) => ForkSchedule::insert(block_number, revision),
+                None => ForkSchedule::remove(block_number),
+            }
+            Module::<T>::deposit_event(Event::ForkScheduled(block_number, revision));
+        }
+
+        /// Set the fuel costs of host functions under `revision`, or reset
+        /// them to the default if `schedule` is `None`.
+        #[weight = SimpleDispatchInfo::FixedOperational(10_000)]
+        fn set_gas_schedule(origin, revision: SsvmRevision, schedule: Option<GasCostSchedule>) {
+            ensure_root(origin)?;
+            match schedule {
+                Some(schedule) => {
+                    ensure!(schedule.is_valid(), Error::<T>::InvalidGasSchedule);
+                    GasSchedules::insert(revision, schedule);
+                }
+                None => GasSchedules::remove(revision),
+            }
+            Module::<T>::deposit_event(Event::GasScheduleSet(revision));
+        }
+
+        /// Decode logs of `address` with first topic `topic` into typed events,
+        /// or stop decoding them if `decoder` is `None`.
+        #[weight = SimpleDispatchInfo::FixedOperational(10_000)]
+        fn set_log_decoder(origin, address: H160, topic: H256, decoder: Option<LogDecoder>) {
+            ensure_root(origin)?;
+
+            match decoder {
+                Some(decoder) => LogDecoders::insert(&address, &topic, decoder),
+                None => LogDecoders::remove(&address, &topic),
+            }
+            Module::<T>::deposit_event(Event::LogDecoderSet(address, topic));
+        }
+
+        /// Register a call of `selector` on `contract` at the start of every
+        /// block, with at most `gas`. The hook runs once approved by
+        /// `T::HookOrigin`, and `T::HookDeposit` is held from `source` until it
+        /// is removed.
+        #[weight = SimpleDispatchInfo::FixedNormal(10_000)]
+        fn register_block_hook(origin, source: H160, contract: H160, selector: [u8; 4], gas: u32) {
+            T::EnsureAddressOrigin::ensure_address_origin(&source, origin)?;
+            ensure!(gas <= T::MaxHookGas::get(), Error::<T>::HookGasTooHigh);
+            ensure!(!BlockHooks::contains_key((contract, selector)), Error::<T>::HookExists);
+            ensure!(
+                BlockHookList::get().len() < T::MaxBlockHooks::get() as usize,
+                Error::<T>::TooManyHooks
+            );
+            let deposit = T::HookDeposit::get();
+            ensure!(Self::balance_of(&source) >= deposit, Error::<T>::BalanceLow);
+
+            Accounts::mutate(&source, |account| account.balance -= deposit);
+            BlockHooks::insert((contract, selector), BlockHook {
+                owner: source,
+                gas,
+                deposit,
+                approved: false,
+            });
+            BlockHookList::mutate(|hooks| hooks.push((contract, selector)));
+            Module::<T>::deposit_event(Event::BlockHookRegistered(contract, selector, source));
+        }
+
+        /// Approve a registered block hook, which then runs every block.
+        #[weight = SimpleDispatchInfo::FixedOperational(10_000)]
+        fn approve_block_hook(origin, contract: H160, selector: [u8; 4]) {
+            T::HookOrigin::ensure_origin(origin)?;
+            let mut hook = BlockHooks::get((contract, selector)).ok_or(Error::<T>::HookNotFound)?;
+            hook.approved = true;
+            BlockHooks::insert((contract, selector), hook);
+            Module::<T>::deposit_event(Event::BlockHookApproved(contract, selector));
+        }
+
+        /// Remove a block hook and return its deposit to its owner. Either the
+        /// owner or `T::HookOrigin` may remove it.
+        #[weight = SimpleDispatchInfo::FixedNormal(10_000)]
+        fn remove_block_hook(origin, contract: H160, selector: [u8; 4]) {
+            let hook = BlockHooks::get((contract, selector)).ok_or(Error::<T>::HookNotFound)?;
+            if let Err(origin) = T::HookOrigin::try_origin(origin) {
+                T::EnsureAddressOrigin::ensure_address_origin(&hook.owner, origin)?;
+            }
+
+            BlockHooks::remove((contract, selector));
+            BlockHookList::mutate(|hooks| hooks.retain(|key| *key != (contract, selector)));
+            Accounts::mutate(&hook.owner, |account| {
+                account.balance = account.balance.saturating_add(hook.deposit)
+            });
+            Module::<T>::deposit_event(Event::BlockHookRemoved(contract, selector));
+        }
+
+        /// Register a keeper job calling `target` with `input` every `interval`
+        /// blocks, starting `interval` blocks from now. `prepayment` is taken
+        /// from `source` and pays the fees of the calls. The job is removed,
+        /// and the rest refunded, once it cannot pay for another call.
+        #[weight = SimpleDispatchInfo::FixedNormal(10_000)]
+        fn register_job(
+            origin,
+            source: H160,
+            target: H160,
+            input: Vec<u8>,
+            gas: u32,
+            gas_price: U256,
+            interval: u32,
+            prepayment: U256,
+        ) {
+            T::EnsureAddressOrigin::ensure_address_origin(&source, origin)?;
+            Self::check_input_size(&input)?;
+            ensure!(gas_price >= Self::min_gas_price(), Error::<T>::GasPriceTooLow);
+            let job = Job {
+                owner: source,
+                target,
+                input,
+                gas,
+                gas_price,
+                interval,
+                prepaid: prepayment,
+            };
+            ensure!(
+                gas <= T::MaxJobGas::get() && interval > 0 && prepayment >= job.max_fee(),
+                Error::<T>::InvalidJob
+            );
+            ensure!(Self::balance_of(&source) >= prepayment, Error::<T>::BalanceLow);
+
+            Accounts::mutate(&source, |account| account.balance -= prepayment);
+            let id = NextJobId::mutate(|next| {
+                *next += 1;
+                *next - 1
+            });
+            let current: u64 = frame_system::Module::<T>::block_number().unique_saturated_into();
+            JobSchedule::mutate(current + interval as u64, |jobs| jobs.push(id));
+            Jobs::insert(id, job);
+            Module::<T>::deposit_event(Event::JobRegistered(id, source, target));
+        }
+
+        /// Add `value` from `source` to the prepayment of a keeper job.
+        #[weight = SimpleDispatchInfo::FixedNormal(10_000)]
+        fn top_up_job(origin, source: H160, id: u64, value: U256) {
+            T::EnsureAddressOrigin::ensure_address_origin(&source, origin)?;
+            let mut job = Jobs::get(id).ok_or(Error::<T>::JobNotFound)?;
+            ensure!(Self::balance_of(&source) >= value, Error::<T>::BalanceLow);
+
+            Accounts::mutate(&source, |account| account.balance -= value);
+            job.prepaid = job.prepaid.saturating_add(value);
+            Jobs::insert(id, job);
+        }
+
+        /// Cancel a keeper job of `source` and refund its prepayment.
+        #[weight = SimpleDispatchInfo::FixedNormal(10_000)]
+        fn cancel_job(origin, source: H160, id: u64) {
+            T::EnsureAddressOrigin::ensure_address_origin(&source, origin)?;
+            let job = Jobs::get(id).ok_or(Error::<T>::JobNotFound)?;
+            ensure!(job.owner == source, Error::<T>::JobNotFound);
+            Self::remove_job(id, job);
+        }
+
+        /// Deploy or upgrade the system contract at `address` with `code`, or
+        /// remove it if `code` is `None`. The address must be reserved and
+        /// not a precompile; storage and balance of an upgraded contract are
+        /// kept.
+        #[weight = SimpleDispatchInfo::FixedOperational(50_000)]
+        fn set_system_contract(origin, address: H160, code: Option<Vec<u8>>) {
+            ensure_root(origin)?;
+            ensure!(
+                is_reserved(&address) && !is_precompile(&address),
+                Error::<T>::ReservedAddress
+            );
+
+            match code {
+                Some(code) => {
+                    ensure!(
+                        code.len() <= T::MaxCodeSize::get() as usize,
+                        Error::<T>::CodeTooLarge
+                    );
+                    Self::deploy_system_contract(address, code);
+                }
+                None => {
+                    ensure!(SystemContracts::contains_key(&address), Error::<T>::ContractNotFound);
+                    SystemContracts::remove(&address);
+                    Self::remove_account(&address);
+                    Module::<T>::deposit_event(Event::SystemContractRemoved(address));
+                }
+            }
+        }
+
+        /// Overwrite the nonce and balance of `address`, leaving its code and
+        /// storage untouched.
+        #[weight = SimpleDispatchInfo::FixedOperational(10_000)]
+        fn force_set_account(origin, address: H160, nonce: U256, balance: U256) {
+            T::ForceOrigin::ensure_origin(origin)?;
+            ensure!(nonce <= U256::from(u64::max_value()), Error::<T>::NonceOverflow);
+
+            let old = Accounts::get(&address);
+            Accounts::insert(&address, Account { nonce, balance });
+            Module::<T>::deposit_event(Event::AccountForced(
+                address,
+                old.nonce,
+                nonce,
+                old.balance,
+                balance,
+            ));
+        }
+    }
+}
+
+// Execution state is kept per thread, so that concurrent runtime API calls
+// on the node do not interfere with each other or with block execution.
+#[cfg(feature = "std")]
+thread_local! {
+    static STORAGE_CACHE: RefCell<StorageOverlay> = RefCell::new(StorageOverlay::default());
+    static FUEL_GRANTED: Cell<i64> = Cell::new(0);
+    static FUEL_LEFT: Cell<i64> = Cell::new(0);
+    static GAS_REFUND: Cell<i64> = Cell::new(0);
+    static TX_LOGS: RefCell<Vec<Log>> = RefCell::new(Vec::new());
+    static SELFDESTRUCTS: RefCell<Vec<(H160, H160, U256)>> = RefCell::new(Vec::new());
+    static JOURNAL: RefCell<Vec<JournalEntry>> = RefCell::new(Vec::new());
+    static STORAGE_GROWTH: Cell<StorageGrowth> = Cell::new(StorageGrowth::default());
+    static GAS_SCHEDULE: RefCell<GasCostSchedule> = RefCell::new(GasCostSchedule::default());
+    static HOST_FAILURE: Cell<bool> = Cell::new(false);
+    static CODE_CACHE: RefCell<HashMap<H160, Rc<Vec<u8>>>> = RefCell::new(HashMap::new());
+    static CODE_SIZES: RefCell<HashMap<H160, usize>> = RefCell::new(HashMap::new());
+    static CODE_HASHES: RefCell<HashMap<H160, H256>> = RefCell::new(HashMap::new());
+}
+
+impl<T: Trait> Module<T> {
+    /// The account ID of the SSVM module.
+    pub fn account_id() -> T::AccountId {
+        MODULE_ID.into_account()
+    }
+
+    /// Check whether `address` is reserved, see `is_reserved`, or is the
+    /// address of the module account.
+    pub fn is_reserved_address(address: &H160) -> bool {
+        is_reserved(address)
+            || *address == T::ConvertAccountId::convert_account_id(&Self::account_id())
+    }
+
+    /// Check whether an account is empty.
+    pub fn is_account_empty(address: &H160) -> bool {
+        let account = Accounts::get(address);
+        let code_len = AccountCodes::decode_len(address).unwrap_or(0);
+
+        account.nonce == U256::zero() && account.balance == U256::zero() && code_len == 0
+    }
+
+    /// Remove an account if its empty.
+    pub fn remove_account_if_empty(address: &H160) {
+        if Self::is_account_empty(address) {
+            Self::remove_account(address)
+        }
+    }
+
+    /// Remove an account from state and release the bond of the contract. Its
+    /// storage is queued for clearing over the next blocks, within
+    /// `T::DeletionBudget` slots per block.
+    fn remove_account(address: &H160) {
+        Accounts::remove(address);
+        if let Some((creator, payer, bond)) = ContractBonds::<T>::take(address) {
+            T::Currency::unreserve(&payer, Self::native_bond(bond).unwrap_or_default());
+            Module::<T>::deposit_event(Event::ContractBondRefunded(*address, creator, bond));
+        }
+        AccountCodes::remove(address);
+        AccountCodeHashes::remove(address);
+        CodeFormats::remove(address);
+        Self::invalidate_code(address);
+        ContractOwner::remove(address);
+        ContractMetadata::remove(address);
+
+        if T::DeletionBudget::get() == 0 {
+            AccountStorages::remove_prefix(address);
+        } else if !PendingDeletions::get(address)
+            && Self::storage_range_at(*address, None, 0).next_key.is_some()
+        {
+            PendingDeletions::insert(address, true);
+            DeletionQueue::mutate(|queue| queue.push(*address));
+        }
+    }
+
+    /// Clear storage of removed contracts from the deletion queue, up to
+    /// `T::DeletionBudget` slots and `weight`. Return the weight used.
+    fn process_deletion_queue(weight: Weight) -> Weight {
+        let slots = (weight / DELETION_WEIGHT_PER_SLOT).min(T::DeletionBudget::get() as Weight);
+        let mut budget = slots as u32;
+        let mut queue = DeletionQueue::get();
+        let mut cleared = 0;
+        for address in queue.iter() {
+            if budget == 0 {
+                break;
+            }
+            let range = Self::storage_range_at(*address, None, budget);
+            budget -= range.storage.len() as u32;
+            for (key, _) in range.storage {
+                AccountStorages::remove(address, key);
+            }
+            if range.next_key.is_some() {
+                break;
+            }
+            PendingDeletions::remove(address);
+            Module::<T>::deposit_event(Event::StorageCleared(*address));
+            cleared += 1;
+        }
+        if cleared > 0 {
+            queue.drain(..cleared);
+            DeletionQueue::put(queue);
+        }
+        (slots - budget as Weight).saturating_mul(DELETION_WEIGHT_PER_SLOT)
+    }
+
+    /// Bond of `code_len` bytes of code, in EVM balance units.
+    fn code_bond(code_len: usize) -> U256 {
+        T::ContractBondPerByte::get().saturating_mul(U256::from(code_len))
+    }
+
+    /// Bond in EVM balance units converted into `T::Currency`, rounded up.
+    fn native_bond(bond: U256) -> Result<BalanceOf<T>, Error<T>> {
+        T::FeeConverter::into_native(bond).ok_or(Error::<T>::BalanceConversionOverflow)
+    }
+
+    /// Reserve the bond of `code_len` bytes of code, deployed at `address` by
+    /// `creator`, from `payer`, and record it for release when the contract
+    /// is removed. Returns the bond.
+    fn reserve_code_bond(
+        address: &H160,
+        creator: H160,
+        payer: &T::AccountId,
+        code_len: usize,
+    ) -> Result<U256, Error<T>> {
+        let bond = Self::code_bond(code_len);
+        if !bond.is_zero() {
+            T::Currency::reserve(payer, Self::native_bond(bond)?)
+                .map_err(|_| Error::<T>::BondReserveFailed)?;
+            ContractBonds::<T>::insert(address, (creator, payer.clone(), bond));
+        }
+        Ok(bond)
+    }
+
+    /// Store the code of an account with its hash, and return the hash.
+    fn store_code(address: &H160, code: Vec<u8>) -> H256 {
+        let hash = code_hash(&code);
+        CodeFormats::insert(address, CodeFormat::detect(&code));
+        AccountCodes::insert(address, code);
+        AccountCodeHashes::insert(address, hash);
+        Self::invalidate_code(address);
+        hash
+    }
+
+    /// Keccak hash of the code of an account, the hash of empty code if it
+    /// has none. Hashes cached in `AccountCodeHashes` are not recomputed.
+    pub fn account_code_hash(address: &H160) -> H256 {
+        AccountCodeHashes::get(address).unwrap_or_else(|| code_hash(&AccountCodes::get(address)))
+    }
+
+    /// Insert an account dump into state, replacing the existing account.
+    fn import_account(address: &H160, dump: AccountDump) {
+        Self::remove_account(address);
+        if PendingDeletions::take(address) {
+            DeletionQueue::mutate(|queue| queue.retain(|queued| queued != address));
+        }
+        AccountStorages::remove_prefix(address);
+        Accounts::insert(
+            address,
+            Account {
+                nonce: dump.nonce,
+                balance: dump.balance,
+            },
+        );
+        if !dump.code.is_empty() {
+            Self::store_code(address, dump.code);
+        }
+        for (key, value) in dump.storage {
+            AccountStorages::insert(address, key, value);
+        }
+    }
+
+    /// Dump an account with its code and storage.
+    pub fn account_dump(address: &H160) -> AccountDump {
+        let account = Accounts::get(address);
+        AccountDump {
+            nonce: account.nonce,
+            balance: account.balance,
+            code: AccountCodes::get(address),
+            storage: Self::storage_entries(address),
+        }
+    }
+
+    /// Dump up to `limit` accounts with their code and storage, starting at
+    /// `start`. Accounts are dumped in storage order, followed by contracts
+    /// with code but without an `Accounts` entry.
+    pub fn export_state(start: Option<H160>, limit: u32) -> StateDump {
+        let limit = limit as usize;
+        let mut addresses = Vec::new();
+        let mut next = None;
+
+        let codes_only = start.map_or(false, |start| !Accounts::contains_key(&start));
+        if !codes_only {
+            next = Self::collect_addresses(
+                &Accounts::final_prefix(),
+                start.map(|start| Accounts::hashed_key_for(&start)),
+                limit,
+                |_| false,
+                &mut addresses,
+            );
+        }
+        if next.is_none() {
+            let code_start = if codes_only {
+                start.map(|start| AccountCodes::hashed_key_for(&start))
+            } else {
+                None
+            };
+            next = Self::collect_addresses(
+                &AccountCodes::final_prefix(),
+                code_start,
+                limit,
+                |address| Accounts::contains_key(address),
+                &mut addresses,
+            );
+        }
+
+        StateDump {
+            accounts: addresses
+                .into_iter()
+                .map(|address| (address, Self::account_dump(&address)))
+                .collect(),
+            next,
+        }
+    }
+
+    /// Collect addresses of a map keyed by `H160` with `blake2_128_concat`,
+    /// starting at the storage key `start`. Returns the address to continue
+    /// from once `out` holds `limit` addresses.
+    fn collect_addresses(
+        prefix: &[u8],
+        start: Option<Vec<u8>>,
+        limit: usize,
+        skip: impl Fn(&H160) -> bool,
+        out: &mut Vec<H160>,
+    ) -> Option<H160> {
+        let mut current = match start {
+            Some(key) if unhashed::exists(&key) => Some(key),
+            Some(key) => sp_io::storage::next_key(&key),
+            None => sp_io::storage::next_key(prefix),
+        };
+        while let Some(key) = current.filter(|key| key.starts_with(prefix) && key.len() >= 20) {
+            let address = H160::from_slice(&key[key.len() - 20..]);
+            if !skip(&address) {
+                if out.len() >= limit {
+                    return Some(address);
+                }
+                out.push(address);
+            }
+            current = sp_io::storage::next_key(&key);
+        }
+        None
+    }
+
+    /// Nonce and balance of an account.
+    pub fn account_basic(address: &H160) -> Account {
+        Accounts::get(address)
+    }
+
+    /// Nonce of an account, read without decoding the whole `Account`.
+    pub fn nonce_of(address: &H160) -> U256 {
+        Self::read_account_field(address, 0)
+    }
+
+    /// Balance of an account, read without decoding the whole `Account`.
+    pub fn balance_of(address: &H160) -> U256 {
+        Self::read_account_field(address, 32)
+    }
+
+    /// Read the encoded `U256` field of an `Account` at byte `offset`, zero
+    /// if the account does not exist.
+    fn read_account_field(address: &H160, offset: u32) -> U256 {
+        let mut field = [0u8; 32];
+        match sp_io::storage::read(&Accounts::hashed_key_for(address), &mut field, offset) {
+            Some(len) if len >= 32 => U256::decode(&mut &field[..]).unwrap_or_default(),
+            _ => U256::zero(),
+        }
+    }
+
+    /// Nonce, balance and code hash of an account.
+    pub fn account_info(address: &H160) -> AccountInfo {
+        let account = Accounts::get(address);
+        AccountInfo {
+            address: *address,
+            nonce: account.nonce,
+            balance: account.balance,
+            code_hash: Self::account_code_hash(address),
+        }
+    }
+
+    /// Code of an account, empty if it has none.
+    pub fn account_code(address: &H160) -> Vec<u8> {
+        AccountCodes::get(address)
+    }
+
+    /// Code of an account, empty if it has none. Same as `account_code`.
+    pub fn account_code_at(address: &H160) -> Vec<u8> {
+        Self::account_code(address)
+    }
+
+    /// Value of the contract storage slot `key`, mapped by
+    /// `T::StorageKeyHasher`.
+    pub fn storage_at(address: &H160, key: &H256) -> H256 {
+        Self::read_storage(address, key)
+    }
+
+    /// All storage slots of a contract, keyed as stored in `AccountStorages`.
+    fn storage_entries(address: &H160) -> Vec<(H256, H256)> {
+        Self::storage_range_at(*address, None, u32::max_value()).storage
+    }
+
+    /// Up to `limit` storage slots of a contract in storage order, starting at
+    /// the key `start`, keyed as stored in `AccountStorages`. Continue with the
+    /// returned `next_key` until it is `None`.
+    pub fn storage_range_at(address: H160, start: Option<H256>, limit: u32) -> StorageRange {
+        let prefix = Self::account_storage_prefix(&address);
+
+        let mut storage = Vec::new();
+        let mut current = match start {
+            Some(start) => Some(AccountStorages::hashed_key_for(&address, &start)),
+            None => sp_io::storage::next_key(&prefix),
+        };
+        while let Some(next) = current {
+            if !next.starts_with(&prefix) || next.len() < prefix.len() + 32 {
+                break;
+            }
+            let key = H256::from_slice(&next[next.len() - 32..]);
+            if storage.len() >= limit as usize {
+                return StorageRange {
+                    storage,
+                    next_key: Some(key),
+                };
+            }
+            if let Some(value) = unhashed::get::<H256>(&next) {
+                storage.push((key, value));
+            }
+            current = sp_io::storage::next_key(&next);
+        }
+        StorageRange {
+            storage,
+            next_key: None,
+        }
+    }
+
+    /// Prefix of the raw storage keys of the contract storage of `address`.
+    fn account_storage_prefix(address: &H160) -> Vec<u8> {
+        let mut prefix = AccountStorages::final_prefix().to_vec();
+        prefix.extend_from_slice(&address.using_encoded(Blake2_128Concat::hash));
+        prefix
+    }
+
+    /// Ensure `owner` owns the contract at `address`.
+    fn ensure_contract_owner(address: &H160, owner: &H160) -> DispatchResult {
+        match ContractOwner::get(address) {
+            None => Err(Error::<T>::ContractNotFound.into()),
+            Some(current) if current != *owner => Err(Error::<T>::NotContractOwner.into()),
+            Some(_) => Ok(()),
+        }
+    }
+
+    /// Check that a transaction from `source` has a valid nonce and gas price,
+    /// and that the account can pay `gas_limit * gas_price + value`.
+    pub fn check_transaction(
+        source: &H160,
+        nonce: Option<U256>,
+        value: U256,
+        gas_limit: u32,
+        gas_price: U256,
+    ) -> Result<(), Error<T>> {
+        Self::check_sponsored_transaction(source, source, nonce, value, gas_limit, gas_price)
+    }
+
+    /// Check a transaction from `source` as `check_transaction`, with the fee
+    /// of `gas_limit * gas_price` paid by `fee_payer`.
+    pub fn check_sponsored_transaction(
+        source: &H160,
+        fee_payer: &H160,
+        nonce: Option<U256>,
+        value: U256,
+        gas_limit: u32,
+        gas_price: U256,
+    ) -> Result<(), Error<T>> {
+        ensure!(
+            gas_price >= Self::min_gas_price(),
+            Error::<T>::GasPriceTooLow
+        );
+
+        let account = Accounts::get(source);
+        if let Some(nonce) = nonce {
+            ensure!(nonce == account.nonce, Error::<T>::InvalidNonce);
+        }
+        // Nonces are capped as in EIP-2681.
+        ensure!(
+            account.nonce < U256::from(u64::max_value()),
+            Error::<T>::NonceOverflow
+        );
+
+        let fee = U256::from(gas_limit)
+            .checked_mul(gas_price)
+            .ok_or(Error::<T>::FeeOverflow)?;
+        if fee_payer == source {
+            let total_payment = fee.checked_add(value).ok_or(Error::<T>::PaymentOverflow)?;
+            ensure!(account.balance >= total_payment, Error::<T>::BalanceLow);
+        } else {
+            ensure!(account.balance >= value, Error::<T>::BalanceLow);
+            ensure!(Self::balance_of(fee_payer) >= fee, Error::<T>::BalanceLow);
+        }
+
+        Ok(())
+    }
+
+    /// Payer of the fee of a call, as chosen by `T::Paymaster`. Falls back to
+    /// `source` if the chosen payer can not afford `gas_limit * gas_price`.
+    /// If the call spends the balance of the payer, `source` pays the part of
+    /// the fee the payer can no longer afford.
+    pub fn fee_payer(
+        source: &H160,
+        target: &H160,
+        input: &[u8],
+        gas_limit: u32,
+        gas_price: U256,
+    ) -> H160 {
+        let max_fee = U256::from(gas_limit).saturating_mul(gas_price);
+        match T::Paymaster::fee_payer(source, target, input, max_fee) {
+            Some(payer) if Self::balance_of(&payer) >= max_fee => payer,
+            _ => *source,
+        }
+    }
+
+    /// Hash signed by the fee payer of a sponsored call: keccak of the RLP
+    /// list of the chain id, source, source nonce, target, value, input, gas
+    /// limit and gas price. The nonce prevents replaying the sponsorship.
+    pub fn sponsorship_hash(
+        source: &H160,
+        target: &H160,
+        input: &[u8],
+        value: U256,
+        gas_limit: u32,
+        gas_price: U256,
+    ) -> H256 {
+        let mut stream = rlp::RlpStream::new_list(8);
+        stream.append(&T::ChainId::get());
+        stream.append(source);
+        stream.append(&Self::nonce_of(source));
+        stream.append(target);
+        stream.append(&value);
+        stream.append(&input.to_vec());
+        stream.append(&gas_limit);
+        stream.append(&gas_price);
+        H256::from(keccak_256(&stream.out()))
+    }
+
+    /// Check that `fee_payer` signed the `sponsorship_hash` of a call.
+    pub fn check_payer_signature(
+        source: &H160,
+        target: &H160,
+        input: &[u8],
+        value: U256,
+        gas_limit: u32,
+        gas_price: U256,
+        fee_payer: &H160,
+        payer_signature: &[u8],
+    ) -> Result<(), Error<T>> {
+        ensure!(
+            payer_signature.len() == 65,
+            Error::<T>::InvalidPayerSignature
+        );
+        let mut signature = [0u8; 65];
+        signature.copy_from_slice(payer_signature);
+        let hash = Self::sponsorship_hash(source, target, input, value, gas_limit, gas_price);
+        ensure!(
+            recover_signer(&signature, &hash) == Some(*fee_payer),
+            Error::<T>::InvalidPayerSignature
+        );
+        Ok(())
+    }
+
+    /// Usage of the current rate limit window by `source`.
+    pub fn rate_limit_usage(source: &H160) -> RateLimitUsage {
+        let block_number: u64 = frame_system::Module::<T>::block_number().unique_saturated_into();
+        let window = block_number / T::RateLimitWindow::get().max(1) as u64;
+        let usage = RateLimits::get(source);
+        if usage.window == window {
+            usage
+        } else {
+            RateLimitUsage {
+                window,
+                ..Default::default()
+            }
+        }
+    }
+
+    /// Check that a transaction of `source` with `gas_limit` stays within
+    /// `T::MaxTransactionsPerWindow` and `T::MaxGasPerWindow`.
+    pub fn check_rate_limit(source: &H160, gas_limit: u32) -> Result<(), Error<T>> {
+        let usage = Self::rate_limit_usage(source);
+        let max_transactions = T::MaxTransactionsPerWindow::get();
+        ensure!(
+            max_transactions == 0 || usage.transactions < max_transactions,
+            Error::<T>::RateLimited
+        );
+        let max_gas = T::MaxGasPerWindow::get();
+        ensure!(
+            max_gas == 0 || usage.gas_used.saturating_add(gas_limit as u64) <= max_gas,
+            Error::<T>::RateLimited
+        );
+        Ok(())
+    }
+
+    /// Count a transaction of `source` that used `gas_used` against its rate
+    /// limit window.
+    fn record_rate_limit(source: &H160, gas_used: u64) {
+        let mut usage = Self::rate_limit_usage(source);
+        usage.transactions = usage.transactions.saturating_add(1);
+        usage.gas_used = usage.gas_used.saturating_add(gas_used);
+        RateLimits::insert(source, usage);
+    }
+
+    /// Check that the input of a transaction is within
+    /// `T::MaxTransactionInputSize`.
+    pub fn check_input_size(input: &[u8]) -> Result<(), Error<T>> {
+        ensure!(
+            input.len() <= T::MaxTransactionInputSize::get() as usize,
+            Error::<T>::InputTooLarge
+        );
+        Ok(())
+    }
+
+    /// Check that a call with `input` to `target` will run code, if
+    /// `T::RejectCallsWithoutCode` is set. Plain value transfers without
+    /// input are always accepted.
+    pub fn check_call_target(target: &H160, input: &[u8]) -> Result<(), Error<T>> {
+        ensure!(
+            is_precompile(target) || !Self::is_reserved_address(target),
+            Error::<T>::ReservedAddress
+        );
+        if T::RejectCallsWithoutCode::get() && !input.is_empty() {
+            ensure!(
+                is_precompile(target) || AccountCodes::decode_len(target).unwrap_or(0) > 0,
+                Error::<T>::NoCode
+            );
+        }
+        Ok(())
+    }
+
+    /// Hash of a call from a guarded account that its signatories approve:
+    /// keccak of the RLP list of the target, value and input. Withdrawals
+    /// target the account itself with an empty input.
+    pub fn multisig_call_hash(target: &H160, value: U256, input: &[u8]) -> H256 {
+        let mut stream = rlp::RlpStream::new_list(3);
+        stream.append(target);
+        stream.append(&value);
+        stream.append(&input.to_vec());
+        H256::from(keccak_256(&stream.out()))
+    }
+
+    /// Check that a call from `source` is approved by its multisig guard, if it
+    /// has one and `value` is above `T::MultisigValueThreshold`. The approvals
+    /// are consumed.
+    pub fn ensure_multisig_approved(
+        source: &H160,
+        target: &H160,
+        value: U256,
+        input: &[u8],
+    ) -> Result<(), Error<T>> {
+        if value <= T::MultisigValueThreshold::get() {
+            return Ok(());
+        }
+        Self::consume_multisig_approvals(source, Self::multisig_call_hash(target, value, input))
+    }
+
+    /// Consume the approvals of `call_hash`, failing if fewer than the
+    /// threshold of current signatories of the guard of `account` approved it.
+    fn consume_multisig_approvals(account: &H160, call_hash: H256) -> Result<(), Error<T>> {
+        let guard = match MultisigGuards::get(account) {
+            Some(guard) => guard,
+            None => return Ok(()),
+        };
+        let approved = MultisigApprovals::get(account, call_hash)
+            .iter()
+            .filter(|signatory| guard.signatories.binary_search(signatory).is_ok())
+            .count();
+        ensure!(
+            approved >= guard.threshold as usize,
+            Error::<T>::NotApproved
+        );
+        MultisigApprovals::remove(account, call_hash);
+        Ok(())
+    }
+
+    /// Set the multisig guard of `account`, removing it if `threshold` is 0.
+    /// Replacing an existing guard must be approved with the hash of the
+    /// precompile call, `input`.
+    pub fn set_multisig_guard(
+        account: &H160,
+        threshold: u32,
+        mut signatories: Vec<H160>,
+        input: &[u8],
+    ) -> Result<(), Error<T>> {
+        signatories.sort();
+        signatories.dedup();
+        ensure!(
+            signatories.len() <= T::MaxSignatories::get() as usize
+                && threshold as usize <= signatories.len()
+                && (threshold > 0 || signatories.is_empty()),
+            Error::<T>::InvalidMultisigGuard
+        );
+        Self::consume_multisig_approvals(
+            account,
+            Self::multisig_call_hash(&MULTISIG_PRECOMPILE, U256::zero(), input),
+        )?;
+
+        let count = signatories.len() as u32;
+        if threshold == 0 {
+            MultisigGuards::remove(account);
+        } else {
+            MultisigGuards::insert(
+                account,
+                MultisigGuard {
+                    threshold,
+                    signatories,
+                },
+            );
+        }
+        Module::<T>::deposit_event(Event::MultisigGuardSet(*account, threshold, count));
+        Ok(())
+    }
+
+    /// Approve a call of the guarded `account` as `signatory`.
+    pub fn approve_multisig_call(
+        account: &H160,
+        call_hash: H256,
+        signatory: &H160,
+    ) -> Result<(), Error<T>> {
+        let guard = MultisigGuards::get(account).ok_or(Error::<T>::NotApproved)?;
+        ensure!(
+            guard.signatories.binary_search(signatory).is_ok(),
+            Error::<T>::NotApproved
+        );
+        MultisigApprovals::mutate(account, call_hash, |approvals| {
+            if !approvals.contains(signatory) {
+                approvals.push(*signatory);
+            }
+        });
+        Module::<T>::deposit_event(Event::MultisigApproval(*account, call_hash, *signatory));
+        Ok(())
+    }
+
+    /// Execute a call, or a contract creation if `target` is `None`, without
+    /// changing state. Used by runtime API queries.
+    pub fn dry_run(
+        source: H160,
+        target: Option<H160>,
+        input: Vec<u8>,
+        value: U256,
+        gas_limit: u32,
+        gas_price: U256,
+    ) -> Result<CallInfo, DispatchError> {
+        #[cfg(feature = "std")]
+        {
+            let nonce = Self::nonce_of(&source);
+            let (target, call_kind) = match target {
+                Some(target) => (target, CallKind::EVMC_CALL),
+                None => (create_address(source, nonce), CallKind::EVMC_CREATE),
+            };
+            Self::reset_fuel(gas_limit);
+            if call_kind == CallKind::EVMC_CREATE && Self::address_collides(&target) {
+                return Ok(CallInfo {
+                    output: Vec::new(),
+                    gas_used: U256::from(gas_limit),
+                    gas_refunded: U256::zero(),
+                    effective_gas_used: U256::from(gas_limit),
+                    status_code: STATUS_FAILURE,
+                });
+            }
+            let result = Self::execute_ssvm(
+                Self::tx_context(source, gas_price)?,
+                Message::new(call_kind, source, target, value, input, gas_limit),
+                true,
+            );
+            Self::finish_execution(false);
+            let (output, gas_left, status_code) = result?;
+            let gas_used = gas_used(gas_limit, gas_left);
+            let gas_refunded = Self::take_refund(gas_used, status_code);
+
+            Ok(CallInfo {
+                output,
+                gas_used: U256::from(gas_used),
+                gas_refunded: U256::from(gas_refunded),
+                effective_gas_used: U256::from(gas_used - gas_refunded),
+                status_code: status_code as i32,
+            })
+        }
+        #[cfg(not(feature = "std"))]
+        {
+            let _ = (source, target, input, value, gas_limit, gas_price);
+            Err(DispatchError::Other("SSVM execution requires std"))
+        }
+    }
+
+    /// Execute a call, or a contract creation if `target` is `None`, after
+    /// applying `overrides` to the state, as `eth_call` with a state override
+    /// set. Used by runtime API queries. The overrides only last for the dry
+    /// run: the storage they may change is restored afterwards.
+    pub fn dry_run_with_overrides(
+        source: H160,
+        target: Option<H160>,
+        input: Vec<u8>,
+        value: U256,
+        gas_limit: u32,
+        gas_price: U256,
+        overrides: Vec<(H160, StateOverride)>,
+    ) -> Result<CallInfo, DispatchError> {
+        let snapshots: Vec<_> = overrides
+            .iter()
+            .map(|(address, _)| (*address, Self::account_snapshot(address)))
+            .collect();
+        for (address, state_override) in overrides {
+            Self::apply_state_override(&address, state_override);
+        }
+        let info = Self::dry_run(source, target, input, value, gas_limit, gas_price);
+        // Restore in reverse, so that the snapshot taken first wins for
+        // accounts overridden more than once.
+        for (address, (storage_prefix, entries)) in snapshots.into_iter().rev() {
+            sp_io::storage::clear_prefix(&storage_prefix);
+            for (key, value) in entries {
+                match value {
+                    Some(value) => sp_io::storage::set(&key, &value),
+                    None => sp_io::storage::clear(&key),
+                }
+            }
+            Self::invalidate_code(&address);
+        }
+        info
+    }
+
+    /// Raw storage of an account that a state override may change: the
+    /// prefix of its contract storage, and the keys of its account, code and
+    /// contract storage with their values, `None` for missing keys.
+    fn account_snapshot(address: &H160) -> (Vec<u8>, Vec<(Vec<u8>, Option<Vec<u8>>)>) {
+        let storage_prefix = Self::account_storage_prefix(address);
+        let mut entries: Vec<_> = [
+            Accounts::hashed_key_for(address),
+            AccountCodes::hashed_key_for(address),
+            AccountCodeHashes::hashed_key_for(address),
+            CodeFormats::hashed_key_for(address),
+            PendingDeletions::hashed_key_for(address),
+        ]
+        .iter()
+        .map(|key| (key.clone(), sp_io::storage::get(key)))
+        .collect();
+        let mut current = sp_io::storage::next_key(&storage_prefix);
+        while let Some(key) = current {
+            if !key.starts_with(&storage_prefix) {
+                break;
+            }
+            let value = sp_io::storage::get(&key);
+            current = sp_io::storage::next_key(&key);
+            entries.push((key, value));
+        }
+        (storage_prefix, entries)
+    }
+
+    /// Write a state override of an account into storage, to be restored
+    /// from an `account_snapshot`.
+    fn apply_state_override(address: &H160, state_override: StateOverride) {
+        if state_override.balance.is_some() || state_override.nonce.is_some() {
+            Accounts::mutate(address, |account| {
+                if let Some(balance) = state_override.balance {
+                    account.balance = balance;
+                }
+                if let Some(nonce) = state_override.nonce {
+                    account.nonce = nonce;
+                }
+            });
+        }
+        if let Some(code) = state_override.code {
+            Self::store_code(address, code);
+        }
+        if let Some(state) = state_override.state {
+            AccountStorages::remove_prefix(address);
+            PendingDeletions::remove(address);
+            for (key, value) in state {
+                Self::write_storage(address, &key, &value);
+            }
+        }
+        for (key, value) in state_override.state_diff {
+            Self::write_storage(address, &key, &value);
+        }
+    }
+
+    /// Execute a call like `dry_run`, and decode the output of successful
+    /// executions against `output_types`. Used by runtime API queries.
+    pub fn dry_run_decoded(
+        source: H160,
+        target: H160,
+        input: Vec<u8>,
+        value: U256,
+        gas_limit: u32,
+        gas_price: U256,
+        output_types: &[AbiType],
+    ) -> Result<ExecutionOutput, DispatchError> {
+        let info = Self::dry_run(source, Some(target), input, value, gas_limit, gas_price)?;
+        let decoded = if info.status_code == STATUS_SUCCESS {
+            abi::decode(output_types, &info.output)
+        } else {
+            None
+        };
+        Ok(ExecutionOutput { info, decoded })
+    }
+
+    /// Call `target` with `input` and at most `gas` from `SYSTEM_ADDRESS`,
+    /// committing state changes if the call succeeds. Used by other modules
+    /// and hooks to drive contracts without a signed origin. No fee is
+    /// charged and no nonce is incremented. Must not be called from within an
+    /// SSVM execution, e.g. a precompile or a dispatched runtime call.
+    pub fn system_call(target: H160, input: Vec<u8>, gas: u32) -> Result<CallInfo, DispatchError> {
+        #[cfg(feature = "std")]
+        {
+            Self::reset_fuel(gas);
+            let result = Self::execute_ssvm(
+                Self::tx_context(SYSTEM_ADDRESS, U256::zero())?,
+                Message::new(
+                    CallKind::EVMC_CALL,
+                    SYSTEM_ADDRESS,
+                    target,
+                    U256::zero(),
+                    input,
+                    gas,
+                ),
+                false,
+            );
+            let logs = match result {
+                Ok((_, _, StatusCode::EVMC_SUCCESS)) => Self::finish_execution(true),
+                _ => Self::finish_execution(false),
+            };
+            let (output, gas_left, status_code) = result?;
+            let gas_used = gas_used(gas, gas_left);
+            let gas_refunded = Self::take_refund(gas_used, status_code);
+            T::OnSsvmExecuted::on_ssvm_executed(
+                &SYSTEM_ADDRESS,
+                &target,
+                status_code as i32,
+                gas_used - gas_refunded,
+                &logs,
+            );
+
+            Ok(CallInfo {
+                output,
+                gas_used: U256::from(gas_used),
+                gas_refunded: U256::from(gas_refunded),
+                effective_gas_used: U256::from(gas_used - gas_refunded),
+                status_code: status_code as i32,
+            })
+        }
+        #[cfg(not(feature = "std"))]
+        {
+            let _ = (target, input, gas);
+            Err(DispatchError::Other("SSVM execution requires std"))
+        }
+    }
+
+    /// Call `target` with `input` from `SYSTEM_ADDRESS` without changing
+    /// state, for other modules querying contracts in their own logic, e.g. an
+    /// oracle price feed. The call is static, so state changing operations
+    /// fail, and is given at most `max_gas`, capped by
+    /// `T::MaxStaticCallWeight`. No fee is charged. Must not be called from
+    /// within an SSVM execution.
+    pub fn static_call(
+        target: H160,
+        input: Vec<u8>,
+        max_gas: u32,
+    ) -> Result<CallInfo, DispatchError> {
+        #[cfg(feature = "std")]
+        {
+            let gas = max_gas.min(Self::max_static_call_gas());
+            Self::reset_fuel(gas);
+            let mut message = Message::new(
+                CallKind::EVMC_CALL,
+                SYSTEM_ADDRESS,
+                target,
+                U256::zero(),
+                input,
+                gas,
+            );
+            message.is_static = true;
+            let result = Self::execute_ssvm(
+                Self::tx_context(SYSTEM_ADDRESS, U256::zero())?,
+                message,
+                true,
+            );
+            Self::finish_execution(false);
+            let (output, gas_left, status_code) = result?;
+            let gas_used = gas_used(gas, gas_left);
+
+            Ok(CallInfo {
+                output,
+                gas_used: U256::from(gas_used),
+                gas_refunded: U256::zero(),
+                effective_gas_used: U256::from(gas_used),
+                status_code: status_code as i32,
+            })
+        }
+        #[cfg(not(feature = "std"))]
+        {
+            let _ = (target, input, max_gas);
+            Err(DispatchError::Other("SSVM execution requires std"))
+        }
+    }
+
+    /// Call the approved block hooks through `system_call`, with the block
+    /// number as argument, as long as their gas limit fits in `budget`.
+    /// Return the weight of the gas they used.
+    fn run_block_hooks(block_number: u64, budget: Weight) -> Weight {
+        let mut weight: Weight = 0;
+        for (contract, selector) in BlockHookList::get() {
+            let hook = match BlockHooks::get((contract, selector)) {
+                Some(hook) if hook.approved => hook,
+                _ => continue,
+            };
+            if weight.saturating_add(Self::gas_to_weight(hook.gas as u64)) > budget {
+                break;
+            }
+            let input = abi::encode_call(selector, &[AbiValue::Uint(U256::from(block_number))])
+                .unwrap_or_default();
+            let (status_code, gas_used) = match Self::system_call(contract, input, hook.gas) {
+                Ok(info) => (info.status_code, info.effective_gas_used.low_u64()),
+                Err(_) => (STATUS_FAILURE, hook.gas as u64),
+            };
+            weight = weight.saturating_add(Self::gas_to_weight(gas_used));
+            Module::<T>::deposit_event(Event::BlockHookExecuted(contract, selector, status_code));
+        }
+        weight
+    }
+
+    /// Run the keeper jobs due in `block_number` through `system_call`,
+    /// paying their fees from their prepayment. Jobs beyond
+    /// `T::MaxJobsPerBlock`, or whose gas limit does not fit in `budget`, are
+    /// postponed to the next block, and jobs that cannot pay for another call
+    /// are removed. Return the weight of the gas they used.
+    fn run_jobs(block_number: u64, budget: Weight) -> Weight {
+        let limit = T::MaxJobsPerBlock::get() as usize;
+        let mut weight: Weight = 0;
+        let mut run = 0;
+        let mut postponed = Vec::new();
+        for id in JobSchedule::take(block_number) {
+            // Cancelled jobs stay in the schedule until they are due.
+            let mut job = match Jobs::get(id) {
+                Some(job) => job,
+                None => continue,
+            };
+            if !postponed.is_empty()
+                || run == limit
+                || weight.saturating_add(Self::gas_to_weight(job.gas as u64)) > budget
+            {
+                postponed.push(id);
+                continue;
+            }
+            if job.prepaid < job.max_fee() {
+                Self::remove_job(id, job);
+                continue;
+            }
+            run += 1;
+            let (status_code, gas_used) =
+                match Self::system_call(job.target, job.input.clone(), job.gas) {
+                    Ok(info) => (info.status_code, info.effective_gas_used.low_u64()),
+                    Err(_) => (STATUS_FAILURE, job.gas as u64),
+                };
+            weight = weight.saturating_add(Self::gas_to_weight(gas_used));
+            let fee = Self::fee(gas_used, job.gas_price).min(job.prepaid);
+            job.prepaid = job.prepaid.saturating_sub(fee);
+            T::OnFeePayment::on_fee_payment(&job.owner, fee);
+            Module::<T>::deposit_event(Event::JobExecuted(id, status_code, fee));
+
+            if job.prepaid < job.max_fee() {
+                Self::remove_job(id, job);
+            } else {
+                JobSchedule::mutate(block_number + job.interval as u64, |jobs| jobs.push(id));
+                Jobs::insert(id, job);
+            }
+        }
+        if !postponed.is_empty() {
+            JobSchedule::mutate(block_number + 1, |jobs| jobs.extend(postponed));
+        }
+        weight
+    }
+
+    /// Weight of `gas`, the inverse of `T::WeightToGas` at the scale of the
+    /// block weight limit.
+    fn gas_to_weight(gas: u64) -> Weight {
+        let max_weight = <T as frame_system::Trait>::MaximumBlockWeight::get();
+        let block_gas_limit = T::WeightToGas::convert(max_weight).max(1);
+        (gas as u128 * max_weight as u128 / block_gas_limit as u128).saturated_into()
+    }
+
+    /// Remove a keeper job and refund the rest of its prepayment.
+    fn remove_job(id: u64, job: Job) {
+        Jobs::remove(id);
+        Accounts::mutate(&job.owner, |account| {
+            account.balance = account.balance.saturating_add(job.prepaid)
+        });
+        Module::<T>::deposit_event(Event::JobRemoved(id, job.prepaid));
+    }
+
+    /// Gas cap of `static_call`, the gas of `T::MaxStaticCallWeight`.
+    pub fn max_static_call_gas() -> u32 {
+        T::WeightToGas::convert(T::MaxStaticCallWeight::get()).min(u32::max_value() as u64) as u32
+    }
+
+    /// Estimate the gas used by a call, or a contract creation if `target` is
+    /// `None`.
+    pub fn estimate_gas(
+        source: H160,
+        target: Option<H160>,
+        input: Vec<u8>,
+        value: U256,
+        gas_price: U256,
+    ) -> Result<U256, DispatchError> {
+        let info = Self::dry_run(source, target, input, value, u32::max_value(), gas_price)?;
+        Self::status_to_result(info.status_code)?;
+        Ok(info.gas_used)
+    }
+
+    /// Convert an EVMC status code into a module error.
+    pub fn status_to_result(status_code: i32) -> Result<(), Error<T>> {
+        match status_code {
+            STATUS_SUCCESS => Ok(()),
+            STATUS_REVERT => Err(Error::<T>::ExitReasonRevert),
+            code if code < 0 => Err(Error::<T>::ExitReasonFatal),
+            _ => Err(Error::<T>::ExitReasonFailed),
+        }
+    }
+
+    /// Execute a call transaction from `source`, with its fee paid by
+    /// `fee_payer`.
+    #[cfg(feature = "std")]
+    fn execute_call(
+        source: H160,
+        target: H160,
+        input: Vec<u8>,
+        value: U256,
+        gas_limit: u32,
+        gas_price: U256,
+        fee_payer: H160,
+    ) -> DispatchResult {
+        let nonce = Self::nonce_of(&source);
+        Self::check_input_size(&input)?;
+        Self::check_call_target(&target, &input)?;
+        Self::check_sponsored_transaction(&source, &fee_payer, None, value, gas_limit, gas_price)?;
+        Self::check_rate_limit(&source, gas_limit)?;
+        Self::ensure_multisig_approved(&source, &target, value, &input)?;
+        Self::reset_fuel(gas_limit);
+        let tx_context = Self::tx_context(source, gas_price)?;
+        Self::transfer_value(&source, &target, value);
+        let (result, gas_left, status_code) = Self::execute_ssvm(
+            tx_context,
+            Message::new(CallKind::EVMC_CALL, source, target, value, input, gas_limit),
+            false,
+        )?;
+
+        let success = status_code == StatusCode::EVMC_SUCCESS;
+        let logs = Self::finish_execution(success);
+        if !success {
+            Self::transfer_value(&target, &source, value);
+            Module::<T>::deposit_event(Event::ExecutionFailed(target, status_code as i32));
+        }
+
+        Accounts::mutate(&source, |account| {
+            account.nonce = account.nonce.saturating_add(U256::one());
+        });
+        let gas_used = gas_used(gas_limit, gas_left);
+        let gas_refunded = Self::take_refund(gas_used, status_code);
+        let fee = Self::charge_fee(&fee_payer, gas_used - gas_refunded, gas_price);
+        Self::record_rate_limit(&source, gas_used - gas_refunded);
+        if fee_payer != source {
+            // The payer was checked to afford the fee before execution, which
+            // may have spent its balance. `source` pays the shortfall.
+            let shortfall = Self::fee(gas_used - gas_refunded, gas_price).saturating_sub(fee);
+            if !shortfall.is_zero() {
+                Self::withdraw_fee(&source, shortfall);
+            }
+            Module::<T>::deposit_event(Event::FeeSponsored(fee_payer, source, fee));
+        }
+        Self::record_execution(source, &[source, target], gas_used, gas_refunded, gas_price);
+        Self::deposit_storage_growth(source);
+        Self::record_transaction_status(
+            source,
+            nonce,
+            Some(target),
+            None,
+            status_code as i32,
+            gas_used - gas_refunded,
+        );
+        T::OnSsvmExecuted::on_ssvm_executed(
+            &source,
+            &target,
+            status_code as i32,
+            gas_used - gas_refunded,
+            &logs,
+        );
+        Module::<T>::deposit_event(Event::Call(target));
+        Module::<T>::deposit_event(Event::Output(result.to_owned()));
+        Ok(())
+    }
+
+    /// Execute `code` with `input` in an encoded `VmContext` of a Wasm
+    /// runtime, and return the encoded `ExecutionResult`. Executions at depth
+    /// 0 start and finish a transaction, committing its state changes if it
+    /// succeeds and is not read-only. See `executor`.
+    #[cfg(feature = "std")]
+    pub fn execute_vm(code: &[u8], input: &[u8], context: &[u8]) -> Vec<u8> {
+        let context = match VmContext::decode(&mut &context[..]) {
+            Ok(context) if context.version <= executor::INTERFACE_VERSION => context,
+            _ => return ExecutionResult::failure().encode(),
+        };
+        let kind = match context.kind {
+            0 => CallKind::EVMC_CALL,
+            1 => CallKind::EVMC_DELEGATECALL,
+            2 => CallKind::EVMC_CALLCODE,
+            3 => CallKind::EVMC_CREATE,
+            4 => CallKind::EVMC_CREATE2,
+            _ => return ExecutionResult::failure().encode(),
+        };
+        let message = Message {
+            kind,
+            sender: context.sender,
+            target: context.target,
+            code_address: context.target,
+            value: context.value,
+            input: input.to_vec(),
+            gas: context.gas,
+            depth: context.depth,
+            is_static: context.is_static,
+        };
+        if context.depth == 0 {
+            Self::reset_fuel(context.gas);
+        }
+        let (output, gas_left, status_code) = Self::run_vm(
+            context.tx_context,
+            &message,
+            code,
+            CodeFormat::detect(code),
+            context.read_only,
+        );
+        let logs = if context.depth == 0 {
+            Self::finish_execution(status_code == StatusCode::EVMC_SUCCESS && !context.read_only)
+        } else {
+            Vec::new()
+        };
+        ExecutionResult {
+            status_code: status_code as i32,
+            gas_left,
+            output,
+            logs,
+        }
+        .encode()
+    }
+
+    /// Execute a contract creation transaction from `source`.
+    #[cfg(feature = "std")]
+    fn execute_create(
+        source: H160,
+        code: Vec<u8>,
+        value: U256,
+        gas_limit: u32,
+        gas_price: U256,
+        bond_payer: Option<T::AccountId>,
+    ) -> DispatchResult {
+        let nonce = Self::nonce_of(&source);
+        Self::check_input_size(&code)?;
+        ensure!(
+            T::Vm::default().accepts(CodeFormat::detect(&code)),
+            Error::<T>::UnsupportedCodeFormat
+        );
+        Self::check_transaction(&source, None, value, gas_limit, gas_price)?;
+        Self::check_rate_limit(&source, gas_limit)?;
+        Self::reset_fuel(gas_limit);
+        let created_address = create_address(source, nonce);
+        ensure!(
+            !Self::is_reserved_address(&created_address),
+            Error::<T>::ReservedAddress
+        );
+        ensure!(
+            !PendingDeletions::get(&created_address),
+            Error::<T>::PendingDeletion
+        );
+        let tx_context = Self::tx_context(source, gas_price)?;
+        // As in EIP-684, creating a contract at an address with code or a
+        // nonce fails and consumes all gas, leaving the existing account
+        // untouched. This happens e.g. when nonces are reused after a state
+        // import.
+        let collision = Self::address_collides(&created_address);
+        let init_code_hash = code_hash(&code);
+        let execution = if collision {
+            Ok((Vec::new(), 0, StatusCode::EVMC_FAILURE))
+        } else {
+            Self::transfer_value(&source, &created_address, value);
+            Self::execute_ssvm(
+                tx_context,
+                Message::new(
+                    CallKind::EVMC_CREATE,
+                    source,
+                    created_address,
+                    value,
+                    code,
+                    gas_limit,
+                ),
+                false,
+            )
+        };
+        let (output, mut gas_left, mut status_code) = execution?;
+        // Deploying code above the size limit consumes all gas, as in
+        // EIP-170.
+        if status_code == StatusCode::EVMC_SUCCESS && output.len() > T::MaxCodeSize::get() as usize
+        {
+            gas_left = 0;
+            status_code = StatusCode::EVMC_OUT_OF_GAS;
+        }
+        // The bond of the deployed code is reserved from the signer once the
+        // size of the code is known, failing the creation if it can not be.
+        // Creations by root are not bonded.
+        let mut bond = U256::zero();
+        if let (StatusCode::EVMC_SUCCESS, Some(payer)) = (status_code, &bond_payer) {
+            match Self::reserve_code_bond(&created_address, source, payer, output.len()) {
+                Ok(reserved) => bond = reserved,
+                Err(_) => status_code = StatusCode::EVMC_FAILURE,
+            }
+        }
+
+        // Storage writes, self-destructs, logs and the endowment of a
+        // failed creation are rolled back. The creator's nonce is
+        // incremented either way, as in Ethereum.
+        let success = status_code == StatusCode::EVMC_SUCCESS;
+        let logs = Self::finish_execution(success);
+        let stored_code_hash = if success {
+            Self::add_storage_growth(output.len() as u64, 0);
+            let code_hash = Self::store_code(&created_address, output.to_owned());
+            ContractOwner::insert(created_address, source);
+            Module::<T>::deposit_event(Event::CodeStored(created_address, code_hash));
+            if !bond.is_zero() {
+                Module::<T>::deposit_event(Event::ContractBonded(created_address, source, bond));
+            }
+            Some(code_hash)
+        } else {
+            if !collision {
+                Self::transfer_value(&created_address, &source, value);
+                Self::remove_account_if_empty(&created_address);
+            }
+            None
+        };
+
+        Accounts::mutate(&source, |account| {
+            account.nonce = account.nonce.saturating_add(U256::one());
+        });
+        let gas_used = gas_used(gas_limit, gas_left);
+        let gas_refunded = Self::take_refund(gas_used, status_code);
+        match stored_code_hash {
+            Some(code_hash) => Module::<T>::deposit_event(Event::Created(
+                source,
+                created_address,
+                code_hash,
+                init_code_hash,
+                U256::from(gas_used - gas_refunded),
+            )),
+            None => Module::<T>::deposit_event(Event::CreateFailed(
+                source,
+                created_address,
+                status_code as i32,
+            )),
+        }
+        Self::charge_fee(&source, gas_used - gas_refunded, gas_price);
+        Self::record_rate_limit(&source, gas_used - gas_refunded);
+        Self::record_execution(
+            source,
+            &[source, created_address],
+            gas_used,
+            gas_refunded,
+            gas_price,
+        );
+        Self::deposit_storage_growth(source);
+        Self::record_transaction_status(
+            source,
+            nonce,
+            None,
+            Some(created_address).filter(|_| success),
+            status_code as i32,
+            gas_used - gas_refunded,
+        );
+        T::OnSsvmExecuted::on_ssvm_executed(
+            &source,
+            &created_address,
+            status_code as i32,
+            gas_used - gas_refunded,
+            &logs,
+        );
+        Ok(())
+    }
+
+    /// Spec version of the runtime, sent along with `ExecutionRequest`s.
+    pub fn spec_version() -> u32 {
+        <T as frame_system::Trait>::Version::get().spec_version
+    }
+
+    /// Execute an encoded `ExecutionRequest` of a Wasm runtime, and return the
+    /// encoded `DispatchResult`. Requests of another runtime version fail.
+    /// See `executor`.
+    #[cfg(feature = "std")]
+    pub fn execute_request(request: &[u8]) -> Vec<u8> {
+        let result = match ExecutionRequest::decode(&mut &request[..]) {
+            Ok(request) if request.spec_version() != Self::spec_version() => Err(
+                DispatchError::Other("SSVM execution request of another runtime version"),
+            ),
+            Ok(ExecutionRequest::Call {
+                spec_version: _,
+                source,
+                target,
+                input,
+                value,
+                gas_limit,
+                gas_price,
+                fee_payer,
+            }) => {
+                let fee_payer = fee_payer.unwrap_or_else(|| {
+                    Self::fee_payer(&source, &target, &input, gas_limit, gas_price)
+                });
+                Self::execute_call(
+                    source, target, input, value, gas_limit, gas_price, fee_payer,
+                )
+            }
+            Ok(ExecutionRequest::Create {
+                spec_version: _,
+                source,
+                code,
+                value,
+                gas_limit,
+                gas_price,
+                bond_payer,
+            }) => bond_payer
+                .map(|payer| T::AccountId::decode(&mut &payer[..]))
+                .transpose()
+                .map_err(|_| DispatchError::Other("Invalid SSVM execution request"))
+                .and_then(|bond_payer| {
+                    Self::execute_create(source, code, value, gas_limit, gas_price, bond_payer)
+                }),
+            Err(_) => Err(DispatchError::Other("Invalid SSVM execution request")),
+        };
+        result.encode()
+    }
+
+    /// Withdraw the fee for `gas_used` at `gas_price` from the EVM balance of
+    /// `source`. The fee rounded up by `T::FeeConverter` may exceed the
+    /// balance, in which case only the balance is withdrawn and passed on.
+    /// Returns the withdrawn fee.
+    fn charge_fee(source: &H160, gas_used: u64, gas_price: U256) -> U256 {
+        Self::withdraw_fee(source, Self::fee(gas_used, gas_price))
     }
 
-    /// Check whether an account is empty.
-    pub fn is_account_empty(address: &H160) -> bool {
-        let account = Accounts::get(address);
-        let code_len = AccountCodes::decode_len(address).unwrap_or(0);
+    /// Withdraw at most `fee` from the EVM balance of `source` and hand it to
+    /// `T::OnFeePayment`. Returns the withdrawn fee.
+    fn withdraw_fee(source: &H160, mut fee: U256) -> U256 {
+        Accounts::mutate(source, |account| {
+            fee = fee.min(account.balance);
+            account.balance -= fee;
+        });
+        T::OnFeePayment::on_fee_payment(source, fee);
+        fee
+    }
 
-        account.nonce == U256::zero() && account.balance == U256::zero() && code_len == 0
+    /// Fee of `gas_used` at `gas_price`, rounded up to a whole unit of the
+    /// currency by `T::FeeConverter`, so that no fee is lost to truncation
+    /// when converted into the currency.
+    pub fn fee(gas_used: u64, gas_price: U256) -> U256 {
+        let fee = U256::from(gas_used).saturating_mul(gas_price);
+        T::FeeConverter::into_native(fee)
+            .and_then(T::BalanceConverter::into_evm)
+            .unwrap_or(fee)
     }
 
-    /// Remove an account if its empty.
-    pub fn remove_account_if_empty(address: &H160) {
-        if Self::is_account_empty(address) {
-            Self::remove_account(address)
+    /// Record an executed transaction into the current block summary.
+    fn record_execution(
+        source: H160,
+        touched: &[H160],
+        gas_used: u64,
+        gas_refunded: u64,
+        gas_price: U256,
+    ) {
+        let gas_used = gas_used.saturating_sub(gas_refunded);
+        Module::<T>::deposit_event(Event::GasUsed(
+            source,
+            U256::from(gas_used),
+            U256::from(gas_refunded),
+        ));
+        BlockTxCount::mutate(|count| *count = count.saturating_add(1));
+        BlockGasPrices::mutate(|prices| prices.push((gas_price, gas_used)));
+        BlockGasUsed::mutate(|total| *total = total.saturating_add(U256::from(gas_used)));
+        BlockTouchedAccounts::mutate(|accounts| {
+            for address in touched {
+                if !accounts.contains(address) {
+                    accounts.push(*address);
+                }
+            }
+        });
+    }
+
+    /// Record the status of the SSVM transaction of `source` with `nonce`
+    /// under its `transaction_hash`, for the retention period.
+    fn record_transaction_status(
+        source: H160,
+        nonce: U256,
+        to: Option<H160>,
+        contract_address: Option<H160>,
+        status_code: i32,
+        gas_used: u64,
+    ) {
+        let hash = transaction_hash(&source, nonce);
+        let block_number: u64 = frame_system::Module::<T>::block_number().unique_saturated_into();
+        TransactionStatuses::insert(
+            hash,
+            TransactionStatus {
+                block_number,
+                extrinsic_index: frame_system::Module::<T>::extrinsic_index().unwrap_or_default(),
+                from: source,
+                to,
+                contract_address,
+                outcome: ExecutionOutcome::from_status_code(status_code),
+                gas_used: gas_used.into(),
+            },
+        );
+        BlockTransactionHashes::mutate(block_number, |hashes| hashes.push(hash));
+        BlockMetrics::mutate(|metrics| metrics.record(status_code, gas_used));
+    }
+
+    /// Minimum gas price accepted for a transaction in the current block,
+    /// `T::MinGasPrice` escalated under load.
+    pub fn min_gas_price() -> U256 {
+        let base = T::MinGasPrice::get();
+        let escalation =
+            base.saturating_mul(U256::from(MinGasPriceEscalation::get())) / U256::from(1_000_000);
+        base.saturating_add(escalation)
+    }
+
+    /// Update the average gas usage with the `gas_used` by the current block,
+    /// and grow or decay the escalation of the minimum gas price.
+    fn adjust_min_gas_price(gas_used: U256) {
+        const UNIT: u128 = 1_000_000;
+        let block_gas_limit =
+            T::WeightToGas::convert(<T as frame_system::Trait>::MaximumBlockWeight::get()).max(1);
+        let gas_used = if gas_used > U256::from(block_gas_limit) {
+            block_gas_limit
+        } else {
+            gas_used.low_u64()
+        };
+        let usage = Permill::from_rational_approximation(gas_used, block_gas_limit);
+        // Smoothing factor of 1/8, as the base fee change denominator of
+        // EIP-1559.
+        let average = GasUsageAverage::mutate(|average| {
+            let parts = average.deconstruct() as u64 * 7 + usage.deconstruct() as u64;
+            *average = Permill::from_parts((parts / 8) as u32);
+            *average
+        });
+
+        let adjustment = T::GasPriceAdjustment::get().deconstruct() as u128;
+        MinGasPriceEscalation::mutate(|escalation| {
+            let multiplier = UNIT + *escalation as u128;
+            let multiplier = if average > T::CongestionThreshold::get() {
+                multiplier.saturating_mul(UNIT + adjustment) / UNIT
+            } else {
+                multiplier.saturating_mul(UNIT) / (UNIT + adjustment)
+            };
+            *escalation = multiplier
+                .saturating_sub(UNIT)
+                .min(u64::max_value() as u128) as u64;
+        });
+    }
+
+    /// Record gas statistics of a block into the ring buffer.
+    fn record_gas_stats(block_number: u64, gas_used: U256, transactions: Vec<(U256, u64)>) {
+        let history = T::GasStatsHistory::get();
+        if history == 0 {
+            return;
         }
+        let index = (block_number % history as u64) as u32;
+        GasStats::insert(
+            index,
+            BlockGasStats::new(block_number, gas_used, transactions),
+        );
     }
 
-    /// Remove an account from state.
-    fn remove_account(address: &H160) {
-        Accounts::remove(address);
-        AccountCodes::remove(address);
-        AccountStorages::remove_prefix(address);
+    /// Index an Ethereum transaction ingested in the current extrinsic by the
+    /// keccak hash of its RLP `payload`, and return the hash.
+    pub fn index_transaction(payload: &[u8]) -> H256 {
+        let hash = H256::from(keccak_256(payload));
+        let block_number: u64 = frame_system::Module::<T>::block_number().unique_saturated_into();
+        let index = frame_system::Module::<T>::extrinsic_index().unwrap_or_default();
+        TransactionIndex::insert(hash, (block_number, index));
+        BlockTransactionHashes::mutate(block_number, |hashes| hashes.push(hash));
+        hash
+    }
+
+    /// Remove hashes of transactions and logs that fell out of the retention
+    /// period.
+    fn prune_transaction_index(block_number: u64) {
+        let retention = T::TransactionIndexRetention::get() as u64;
+        if retention == 0 {
+            return;
+        }
+        if let Some(expired) = block_number.checked_sub(retention) {
+            for hash in BlockTransactionHashes::take(expired) {
+                TransactionIndex::remove(hash);
+                TransactionStatuses::remove(hash);
+            }
+            BlockLogs::remove(expired);
+        }
+    }
+
+    /// Deploy the system contracts of the runtime whose code changed, and
+    /// record the version they were deployed from.
+    fn deploy_system_contracts() {
+        for (address, code) in T::SystemContracts::get() {
+            if SystemContracts::get(&address) != Some(code_hash(&code)) {
+                Self::deploy_system_contract(address, code);
+            }
+        }
+        SystemContractsVersion::put(T::SystemContractsVersion::get());
+    }
+
+    /// Store `code` as a system contract at `address`.
+    fn deploy_system_contract(address: H160, code: Vec<u8>) {
+        let hash = Self::store_code(&address, code);
+        SystemContracts::insert(&address, hash);
+        Accounts::mutate(&address, |account| {
+            account.nonce = account.nonce.max(U256::one())
+        });
+        Module::<T>::deposit_event(Event::CodeStored(address, hash));
+        Module::<T>::deposit_event(Event::SystemContractDeployed(address, hash));
+    }
+
+    /// Snapshot the block fields seen by contracts in the current block, and
+    /// remove the snapshot that fell out of the retention period.
+    fn snapshot_block_context(block_number: u64) {
+        if let Ok(context) = Self::current_block_context() {
+            BlockContexts::insert(block_number, context);
+        }
+        let retention = T::BlockContextRetention::get() as u64;
+        if retention == 0 {
+            return;
+        }
+        if let Some(expired) = block_number.checked_sub(retention) {
+            BlockContexts::remove(expired);
+        }
+    }
+
+    /// Store the Ethereum header of the current block.
+    fn store_eth_block(block_number: u64, gas_used: U256, logs_bloom: Bloom, state_root: H256) {
+        let parent_hash = block_number
+            .checked_sub(1)
+            .and_then(EthBlocks::get)
+            .map(|parent| parent.hash())
+            .unwrap_or_default();
+
+        let mut transactions = rlp::RlpStream::new();
+        transactions.append_list::<H256, H256>(&BlockTransactionHashes::get(block_number));
+        let mut receipts = rlp::RlpStream::new();
+        let logs = BlockLogs::get(block_number);
+        receipts.begin_list(logs.len());
+        for (index, log) in logs.iter() {
+            receipts.begin_list(4);
+            receipts.append(index);
+            receipts.append(&log.address);
+            receipts.append_list::<H256, H256>(&log.topics);
+            receipts.append(&log.data);
+        }
+
+        let gas_limit =
+            T::WeightToGas::convert(<T as frame_system::Trait>::MaximumBlockWeight::get());
+        let timestamp: u64 = pallet_timestamp::Module::<T>::get().unique_saturated_into();
+        let header = EthBlockHeader {
+            parent_hash,
+            state_root,
+            transactions_root: H256::from(keccak_256(&transactions.out())),
+            receipts_root: H256::from(keccak_256(&receipts.out())),
+            logs_bloom,
+            number: block_number,
+            gas_used,
+            gas_limit: gas_limit.into(),
+            timestamp,
+        };
+        EthBlockNumbers::insert(header.hash(), block_number);
+        EthBlocks::insert(block_number, header);
+    }
+
+    /// Hard fork whose rules contracts currently execute under.
+    pub fn revision() -> SsvmRevision {
+        ActiveRevision::get().unwrap_or_else(T::Revision::get)
+    }
+
+    /// Fuel costs of host functions under the current revision.
+    pub fn gas_schedule() -> GasCostSchedule {
+        GasSchedules::get(Self::revision()).unwrap_or_default()
+    }
+
+    /// Activate the hard fork scheduled at `block_number`, if any.
+    fn activate_fork(block_number: u64) {
+        if let Some(revision) = ForkSchedule::take(block_number) {
+            ActiveRevision::put(revision);
+            Module::<T>::deposit_event(Event::ForkActivated(block_number, revision));
+        }
+    }
+
+    /// Gas statistics of up to `count` most recent blocks, newest first.
+    pub fn gas_stats(count: u32) -> Vec<BlockGasStats> {
+        let history = T::GasStatsHistory::get() as u64;
+        let current: u64 = frame_system::Module::<T>::block_number().unique_saturated_into();
+        let mut stats = Vec::new();
+        for offset in 0..(count as u64).min(history) {
+            let block_number = match current.checked_sub(offset) {
+                Some(block_number) => block_number,
+                None => break,
+            };
+            match GasStats::get((block_number % history) as u32) {
+                Some(entry) if entry.block_number == block_number => stats.push(entry),
+                _ => {}
+            }
+        }
+        stats
+    }
+
+    /// Fee history of up to `block_count` blocks ending at `newest`, with
+    /// tips at the given `percentiles`. The range is cut short at the first
+    /// block not covered by the gas statistics. The block gas limit is the
+    /// maximum block weight converted to gas, and the base fee the minimum
+    /// gas price of each block.
+    pub fn fee_history(block_count: u32, newest: u64, percentiles: Vec<Permill>) -> FeeHistory {
+        let history = T::GasStatsHistory::get() as u64;
+        let current: u64 = frame_system::Module::<T>::block_number().unique_saturated_into();
+        let newest = newest.min(current);
+        let min_gas_price = Self::min_gas_price();
+        let block_gas_limit =
+            T::WeightToGas::convert(<T as frame_system::Trait>::MaximumBlockWeight::get()).max(1);
+
+        let mut blocks = Vec::new();
+        for offset in 0..(block_count as u64).min(history) {
+            let block_number = match newest.checked_sub(offset) {
+                Some(block_number) => block_number,
+                None => break,
+            };
+            match GasStats::get((block_number % history) as u32) {
+                Some(entry) if entry.block_number == block_number => blocks.push(entry),
+                _ => break,
+            }
+        }
+        blocks.reverse();
+
+        let mut fee_history = FeeHistory {
+            oldest_block: blocks.first().map(|b| b.block_number).unwrap_or(newest),
+            ..Default::default()
+        };
+        for block in blocks.iter() {
+            let base_fee = BlockContexts::get(block.block_number)
+                .map(|context| context.base_fee)
+                .unwrap_or(min_gas_price);
+            fee_history.base_fee_per_gas.push(base_fee);
+            let gas_used = if block.gas_used > U256::from(block_gas_limit) {
+                block_gas_limit
+            } else {
+                block.gas_used.low_u64()
+            };
+            fee_history
+                .gas_used_ratio
+                .push(Permill::from_rational_approximation(
+                    gas_used,
+                    block_gas_limit,
+                ));
+            if !percentiles.is_empty() {
+                fee_history
+                    .reward
+                    .push(block.rewards(base_fee, &percentiles));
+            }
+        }
+        fee_history.base_fee_per_gas.push(min_gas_price);
+        fee_history
+    }
+
+    /// Compute a root hash over the state of the given accounts.
+    fn touched_root(mut accounts: Vec<H160>) -> H256 {
+        accounts.sort();
+        let mut leaves = Vec::with_capacity(accounts.len() * 32);
+        for address in accounts.iter() {
+            let account = Accounts::get(address);
+            let mut leaf = address.as_bytes().to_vec();
+            leaf.extend_from_slice(&account.encode());
+            leaf.extend_from_slice(Self::account_code_hash(address).as_bytes());
+            leaves.extend_from_slice(&keccak_256(&leaf));
+        }
+        H256::from(keccak_256(&leaves))
+    }
+
+    /// Read a contract storage slot from `AccountStorages`. Storage of removed
+    /// contracts pending deletion reads as zero.
+    fn read_storage(address: &H160, key: &H256) -> H256 {
+        if PendingDeletions::get(address) {
+            return H256::zero();
+        }
+        AccountStorages::get(address, T::StorageKeyHasher::hash_key(key))
+    }
+
+    /// Write a contract storage slot into `AccountStorages`, removing zero values.
+    fn write_storage(address: &H160, key: &H256, value: &H256) {
+        let key = T::StorageKeyHasher::hash_key(key);
+        if *value == H256::zero() {
+            AccountStorages::remove(address, key);
+        } else {
+            AccountStorages::insert(address, key, value);
+        }
     }
 
     /// Get storage value through storage cache
     #[cfg(feature = "std")]
     fn get_storage(address: H160, key: H256) -> H256 {
-        if !STORAGE_CACHE.lock().unwrap().contains_key(&(address, key)) {
-            let value = AccountStorages::get(H160::from(address), H256::from(key));
-            STORAGE_CACHE.lock().unwrap().insert((address, key), value);
-        }
-        STORAGE_CACHE
-            .lock()
-            .unwrap()
-            .get(&(address, key))
-            .unwrap()
-            .to_owned()
+        STORAGE_CACHE.with(|cache| cache.borrow_mut().get::<T>(address, key))
+    }
+
+    /// Check whether a storage slot was already accessed in this transaction
+    #[cfg(feature = "std")]
+    pub fn is_storage_warm(address: H160, key: H256) -> bool {
+        STORAGE_CACHE.with(|cache| cache.borrow().is_warm(address, key))
     }
 
     /// Set storage value to storage cache
     #[cfg(feature = "std")]
     fn set_storage(address: H160, key: H256, value: H256) -> StorageStatus {
-        STORAGE_CACHE.lock().unwrap().insert((address, key), value);
-        StorageStatus::EVMC_STORAGE_MODIFIED
+        STORAGE_CACHE.with(|cache| cache.borrow_mut().set::<T>(address, key, value))
+    }
+
+    /// Code of an account, read from storage once per transaction.
+    #[cfg(feature = "std")]
+    fn code(address: &H160) -> Rc<Vec<u8>> {
+        CODE_CACHE.with(|cache| {
+            cache
+                .borrow_mut()
+                .entry(*address)
+                .or_insert_with(|| Rc::new(AccountCodes::get(address)))
+                .clone()
+        })
+    }
+
+    /// Whether a contract can not be created at `address`, as it already
+    /// has code or a nonce.
+    #[cfg(feature = "std")]
+    fn address_collides(address: &H160) -> bool {
+        Self::code_size(address) > 0 || !Self::nonce_of(address).is_zero()
+    }
+
+    /// Code size of an account, memoized for the transaction.
+    #[cfg(feature = "std")]
+    fn code_size(address: &H160) -> usize {
+        CODE_SIZES.with(|sizes| {
+            *sizes
+                .borrow_mut()
+                .entry(*address)
+                .or_insert_with(|| AccountCodes::decode_len(address).unwrap_or(0))
+        })
+    }
+
+    /// Code hash of an account, memoized for the transaction.
+    #[cfg(feature = "std")]
+    fn cached_code_hash(address: &H160) -> H256 {
+        CODE_HASHES.with(|hashes| {
+            *hashes
+                .borrow_mut()
+                .entry(*address)
+                .or_insert_with(|| Self::account_code_hash(address))
+        })
+    }
+
+    /// Forget the memoized code, code size and code hash of an account whose
+    /// code changed.
+    fn invalidate_code(address: &H160) {
+        if_std! {
+            CODE_CACHE.with(|cache| cache.borrow_mut().remove(address));
+            CODE_SIZES.with(|sizes| sizes.borrow_mut().remove(address));
+            CODE_HASHES.with(|hashes| hashes.borrow_mut().remove(address));
+        }
+    }
+
+    /// Copy the code of an account from `offset` into `buffer`, at most
+    /// `T::MaxCodeSize` bytes, and return the number of bytes copied.
+    #[cfg(feature = "std")]
+    fn copy_code(address: &H160, offset: usize, buffer: &mut [u8]) -> usize {
+        let code = Self::code(address);
+        if offset >= code.len() {
+            return 0;
+        }
+        let len = buffer
+            .len()
+            .min(code.len() - offset)
+            .min(T::MaxCodeSize::get() as usize);
+        buffer[..len].copy_from_slice(&code[offset..offset + len]);
+        len
     }
 
     /// Drop storage cache
     #[cfg(feature = "std")]
     fn drop_storage_cache() {
-        STORAGE_CACHE.lock().unwrap().clear();
+        STORAGE_CACHE.with(|cache| cache.borrow_mut().clear());
+    }
+
+    /// Grant fuel for a transaction with the given gas limit, and clear the
+    /// gas refunds, logs and self-destructs of the previous one
+    #[cfg(feature = "std")]
+    fn reset_fuel(gas_limit: u32) {
+        let fuel = (gas_limit as u64).saturating_mul(T::FuelPerGas::get());
+        let fuel = fuel.min(i64::max_value() as u64) as i64;
+        FUEL_GRANTED.with(|fuel_granted| fuel_granted.set(fuel));
+        FUEL_LEFT.with(|fuel_left| fuel_left.set(fuel));
+        GAS_REFUND.with(|refund| refund.set(0));
+        TX_LOGS.with(|logs| logs.borrow_mut().clear());
+        SELFDESTRUCTS.with(|selfdestructs| selfdestructs.borrow_mut().clear());
+        JOURNAL.with(|journal| journal.borrow_mut().clear());
+        STORAGE_GROWTH.with(|growth| growth.set(StorageGrowth::default()));
+        GAS_SCHEDULE.with(|schedule| schedule.replace(Self::gas_schedule()));
+        HOST_FAILURE.with(|failure| failure.set(false));
+        CODE_CACHE.with(|cache| cache.borrow_mut().clear());
+        CODE_SIZES.with(|sizes| sizes.borrow_mut().clear());
+        CODE_HASHES.with(|hashes| hashes.borrow_mut().clear());
+    }
+
+    /// Commit the storage writes, self-destructs and logs buffered by an
+    /// execution if it succeeded, or discard them and undo the state changes
+    /// of its precompiles. Return the committed logs.
+    #[cfg(feature = "std")]
+    fn finish_execution(success: bool) -> Vec<Log> {
+        let logs = TX_LOGS.with(|logs| logs.replace(Vec::new()));
+        let selfdestructs = SELFDESTRUCTS.with(|selfdestructs| selfdestructs.replace(Vec::new()));
+        if success {
+            JOURNAL.with(|journal| journal.borrow_mut().clear());
+            Self::sync_storage();
+        } else {
+            Self::undo_journal(0);
+        }
+        Self::drop_storage_cache();
+        if !success {
+            return Vec::new();
+        }
+        for (address, beneficiary, balance) in selfdestructs {
+            Self::apply_selfdestruct(address, beneficiary, balance);
+        }
+        for log in logs.iter() {
+            Self::deposit_log_event(log.clone());
+        }
+        logs
+    }
+
+    /// Checkpoint of the current execution, to roll a failed call frame back
+    /// to.
+    #[cfg(feature = "std")]
+    pub(crate) fn checkpoint() -> Checkpoint {
+        Checkpoint {
+            storage: STORAGE_CACHE.with(|cache| cache.borrow().checkpoint()),
+            journal: JOURNAL.with(|journal| journal.borrow().len()),
+            logs: TX_LOGS.with(|logs| logs.borrow().len()),
+            selfdestructs: SELFDESTRUCTS.with(|selfdestructs| selfdestructs.borrow().len()),
+            refund: GAS_REFUND.with(|refund| refund.get()),
+        }
+    }
+
+    /// Undo the storage writes, logs, self-destructs, gas refunds and
+    /// precompile state changes of the current execution since `checkpoint`.
+    #[cfg(feature = "std")]
+    pub(crate) fn revert_to_checkpoint(checkpoint: Checkpoint) {
+        STORAGE_CACHE.with(|cache| cache.borrow_mut().revert_to(checkpoint.storage));
+        Self::undo_journal(checkpoint.journal);
+        TX_LOGS.with(|logs| logs.borrow_mut().truncate(checkpoint.logs));
+        SELFDESTRUCTS.with(|selfdestructs| {
+            selfdestructs
+                .borrow_mut()
+                .truncate(checkpoint.selfdestructs)
+        });
+        GAS_REFUND.with(|refund| refund.set(checkpoint.refund));
+    }
+
+    /// Record a state change of a precompile, undone if its call frame or the
+    /// transaction fails.
+    #[cfg(feature = "std")]
+    pub(crate) fn journal(entry: JournalEntry) {
+        JOURNAL.with(|journal| journal.borrow_mut().push(entry));
+    }
+
+    /// Undo the precompile state changes recorded after the first `len`,
+    /// latest first.
+    #[cfg(feature = "std")]
+    fn undo_journal(len: usize) {
+        let entries = JOURNAL.with(|journal| {
+            let mut journal = journal.borrow_mut();
+            let len = len.min(journal.len());
+            journal.split_off(len)
+        });
+        for entry in entries.into_iter().rev() {
+            match entry {
+                JournalEntry::Account(address, account) => {
+                    if account == Account::default() {
+                        Accounts::remove(address);
+                    } else {
+                        Accounts::insert(address, account);
+                    }
+                }
+                JournalEntry::Allowance(token, key, allowance) => {
+                    if allowance.is_zero() {
+                        Allowances::remove(token, key);
+                    } else {
+                        Allowances::insert(token, key, allowance);
+                    }
+                }
+                JournalEntry::MultisigApprovals(account, call_hash, approvals) => {
+                    if approvals.is_empty() {
+                        MultisigApprovals::remove(account, call_hash);
+                    } else {
+                        MultisigApprovals::insert(account, call_hash, approvals);
+                    }
+                }
+                JournalEntry::AssetTransfer(asset, from, to, amount) => {
+                    let from = T::ConvertAddress::convert_address(&from);
+                    let to = T::ConvertAddress::convert_address(&to);
+                    // Later transfers of the asset are undone first, so the
+                    // recipient still holds the amount.
+                    let _ = T::Assets::transfer(asset, &to, &from, amount);
+                }
+                JournalEntry::Bond(address) => {
+                    if let Some((_, payer, bond)) = ContractBonds::<T>::take(address) {
+                        T::Currency::unreserve(&payer, Self::native_bond(bond).unwrap_or_default());
+                    }
+                }
+                JournalEntry::Code(address) => {
+                    AccountCodes::remove(address);
+                    AccountCodeHashes::remove(address);
+                    CodeFormats::remove(address);
+                    ContractOwner::remove(address);
+                    Self::invalidate_code(&address);
+                }
+            }
+        }
+    }
+
+    /// Mutate the nonce and balance of an account from a precompile,
+    /// journaling the change.
+    #[cfg(feature = "std")]
+    pub(crate) fn mutate_account(address: &H160, f: impl FnOnce(&mut Account)) {
+        Self::journal(JournalEntry::Account(*address, Accounts::get(address)));
+        Accounts::mutate(address, f);
+    }
+
+    /// Move `value` between EVM balances, journaling the change. The sender's
+    /// balance is checked by the caller.
+    #[cfg(feature = "std")]
+    pub(crate) fn journal_transfer(from: &H160, to: &H160, value: U256) {
+        if value.is_zero() || from == to {
+            return;
+        }
+        Self::mutate_account(from, |account| {
+            account.balance = account.balance.saturating_sub(value)
+        });
+        Self::mutate_account(to, |account| {
+            account.balance = account.balance.saturating_add(value)
+        });
+    }
+
+    /// Set an ERC-20 allowance of a precompiled token, journaling the change.
+    #[cfg(feature = "std")]
+    pub(crate) fn set_allowance(token: &H160, key: (H160, H160), allowance: U256) {
+        Self::journal(JournalEntry::Allowance(
+            *token,
+            key,
+            Allowances::get(token, key),
+        ));
+        Allowances::insert(token, key, allowance);
+    }
+
+    /// Buffer a self-destruct of the current execution, along with the
+    /// balance the beneficiary receives. Return false if the contract already
+    /// self-destructed in this execution, in which case no refund is due.
+    #[cfg(feature = "std")]
+    fn schedule_selfdestruct(address: H160, beneficiary: H160) -> bool {
+        SELFDESTRUCTS.with(|selfdestructs| {
+            let mut selfdestructs = selfdestructs.borrow_mut();
+            if selfdestructs
+                .iter()
+                .any(|(destroyed, _, _)| *destroyed == address)
+            {
+                return false;
+            }
+            let balance = Self::balance_of(&address);
+            selfdestructs.push((address, beneficiary, balance));
+            true
+        })
+    }
+
+    /// Move the balance a self-destructed contract had when it self-destructed
+    /// to the beneficiary, and remove the contract with its storage. As in
+    /// Ethereum, the contract keeps its code and storage until the end of the
+    /// transaction, and value it receives after self-destructing is burnt, as
+    /// is its balance if it is its own beneficiary. Once removed, a contract
+    /// may be created again at the same address.
+    fn apply_selfdestruct(address: H160, beneficiary: H160, balance: U256) {
+        let balance = balance.min(Self::balance_of(&address));
+        if_std! {
+            let code_len = AccountCodes::decode_len(&address).unwrap_or(0);
+            Self::add_storage_growth(0, code_len as u64);
+        }
+        if address != beneficiary {
+            Accounts::mutate(&beneficiary, |account| {
+                account.balance = account.balance.saturating_add(balance)
+            });
+        }
+        Self::remove_account(&address);
+        Module::<T>::deposit_event(Event::Selfdestructed(address, beneficiary));
+    }
+
+    /// Move `value` between EVM balances. The sender's balance is checked
+    /// before execution.
+    fn transfer_value(from: &H160, to: &H160, value: U256) {
+        if value.is_zero() || from == to {
+            return;
+        }
+        Accounts::mutate(from, |account| {
+            account.balance = account.balance.saturating_sub(value)
+        });
+        Accounts::mutate(to, |account| {
+            account.balance = account.balance.saturating_add(value)
+        });
+    }
+
+    /// Accrue a gas refund for the current transaction
+    #[cfg(feature = "std")]
+    fn add_refund(refund: i64) {
+        GAS_REFUND.with(|accrued| accrued.set(accrued.get().saturating_add(refund)));
+    }
+
+    /// Take the gas refunded to a transaction that used `gas_used`. Refunds
+    /// only apply to successful executions and are capped at a share of the
+    /// gas used set by the revision.
+    #[cfg(feature = "std")]
+    fn take_refund(gas_used: u64, status_code: StatusCode) -> u64 {
+        let accrued = GAS_REFUND.with(|refund| refund.replace(0)).max(0) as u64;
+        if status_code != StatusCode::EVMC_SUCCESS {
+            return 0;
+        }
+        accrued.min(gas_used / Self::revision().max_refund_quotient())
+    }
+
+    /// Burn the fuel priced by `cost` in the gas cost schedule of the
+    /// transaction. Return false if fuel is exhausted.
+    #[cfg(feature = "std")]
+    fn charge_fuel(cost: impl FnOnce(&GasCostSchedule) -> u64) -> bool {
+        let cost = GAS_SCHEDULE.with(|schedule| cost(&schedule.borrow()));
+        FUEL_LEFT.with(|fuel_left| {
+            let cost = cost.min(i64::max_value() as u64) as i64;
+            fuel_left.set(fuel_left.get().saturating_sub(cost));
+            fuel_left.get() >= 0
+        })
+    }
+
+    /// Gas charged by host functions so far in the transaction, the fuel
+    /// burnt rounded up to whole units of gas.
+    #[cfg(feature = "std")]
+    fn host_gas_used() -> u64 {
+        let fuel_per_gas = T::FuelPerGas::get().max(1);
+        let granted = FUEL_GRANTED.with(|fuel_granted| fuel_granted.get());
+        let left = FUEL_LEFT.with(|fuel_left| fuel_left.get()).max(0);
+        let burnt = granted.saturating_sub(left).max(0) as u64;
+        burnt / fuel_per_gas + (burnt % fuel_per_gas != 0) as u64
+    }
+
+    /// Gas left for host functions in the transaction.
+    #[cfg(feature = "std")]
+    pub(crate) fn host_gas_left() -> u64 {
+        let left = FUEL_LEFT.with(|fuel_left| fuel_left.get()).max(0) as u64;
+        left / T::FuelPerGas::get().max(1)
+    }
+
+    /// Exhaust the fuel left, ending the execution with a failure status and
+    /// consuming all gas.
+    #[cfg(feature = "std")]
+    fn fail_execution() {
+        FUEL_LEFT.with(|fuel_left| fuel_left.set(-1));
+        HOST_FAILURE.with(|failure| failure.set(true));
+    }
+
+    /// Check whether fuel is exhausted
+    #[cfg(feature = "std")]
+    fn fuel_exhausted() -> bool {
+        FUEL_LEFT.with(|fuel_left| fuel_left.get() < 0)
     }
 
     /// Sync storage cache to storage
     #[cfg(feature = "std")]
     fn sync_storage() {
-        for (key, val) in STORAGE_CACHE.lock().unwrap().iter() {
-            AccountStorages::insert(key.0, key.1, val);
+        let growth = STORAGE_CACHE.with(|cache| cache.borrow().commit::<T>());
+        Self::add_storage_growth(growth.bytes_added, growth.bytes_removed);
+    }
+
+    /// Account contract state bytes added and removed by the current
+    /// transaction.
+    #[cfg(feature = "std")]
+    fn add_storage_growth(added: u64, removed: u64) {
+        STORAGE_GROWTH.with(|growth| {
+            let mut total = growth.get();
+            total.bytes_added = total.bytes_added.saturating_add(added);
+            total.bytes_removed = total.bytes_removed.saturating_add(removed);
+            growth.set(total);
+        });
+    }
+
+    /// Deposit the contract state growth of the current transaction sent by
+    /// `source`, if any. Storage of destroyed contracts is not counted, only
+    /// their code.
+    #[cfg(feature = "std")]
+    fn deposit_storage_growth(source: H160) {
+        let growth = STORAGE_GROWTH.with(|growth| growth.replace(StorageGrowth::default()));
+        if !growth.is_empty() {
+            Module::<T>::deposit_event(Event::StorageGrowth(source, growth));
         }
     }
 
-    /// Execute precompiles contract.
+    /// Buffer a log of the current execution, deposited once it is committed.
+    #[cfg(feature = "std")]
+    fn deposit_log(log: Log) {
+        TX_LOGS.with(|logs| logs.borrow_mut().push(log));
+    }
+
+    /// Deposit a log event and accrue it into the block logs bloom.
+    fn deposit_log_event(log: Log) {
+        BlockLogsBloom::mutate(|bloom| bloom.accrue_log(&log));
+        let block_number: u64 = frame_system::Module::<T>::block_number().unique_saturated_into();
+        let index = frame_system::Module::<T>::extrinsic_index().unwrap_or_default();
+        BlockLogs::mutate(block_number, |logs| logs.push((index, log.clone())));
+        let decoded = log
+            .topics
+            .first()
+            .and_then(|topic| LogDecoders::get(&log.address, topic))
+            .and_then(|decoder| Self::decode_log(decoder, &log));
+        Module::<T>::deposit_event(Event::Log(log));
+        if let Some(event) = decoded {
+            Module::<T>::deposit_event(event);
+        }
+    }
+
+    /// Decode a log into a typed event, `None` if it does not match the
+    /// layout of the decoder.
+    fn decode_log(decoder: LogDecoder, log: &Log) -> Option<Event> {
+        if log.topics.len() != 3 || log.data.len() != 32 {
+            return None;
+        }
+        let first = H160::from(log.topics[1]);
+        let second = H160::from(log.topics[2]);
+        let value = U256::from_big_endian(&log.data);
+        Some(match decoder {
+            LogDecoder::Erc20Transfer => Event::Erc20Transfer(log.address, first, second, value),
+            LogDecoder::Erc20Approval => Event::Erc20Approval(log.address, first, second, value),
+        })
+    }
+
+    /// Execute the precompile at the code address of `message`, if any.
+    /// `read_only` dry runs skip state changes, while static calls fail them.
     #[cfg(feature = "std")]
     fn execute_precompiles(
-        target: &H160,
-        value: &U256,
-        data: &Vec<u8>,
-        gas_limit: &u32,
-        gas_price: &U256,
-    ) -> (bool, Vec<u8>, i64) {
+        message: &Message,
+        read_only: bool,
+    ) -> Option<(Vec<u8>, i64, StatusCode)> {
+        let target = &message.code_address;
+        let source = &message.sender;
+        let data = &message.input;
+        let gas_limit = message.gas;
+        let gas_left = gas_limit as i64;
+        let caller = precompiles::Caller {
+            kind: message.kind,
+            source: message.sender,
+            read_only,
+            is_static: message.is_static,
+            nested: message.depth > 0,
+        };
+        if let Some(function) = extended_host_function(target) {
+            return Some(precompiles::extended_host::<T>(
+                function, caller, data, gas_limit,
+            ));
+        }
+        if let Some(asset) = asset_id(target) {
+            return Some(precompiles::erc20::<T>(
+                precompiles::Token::Asset(asset),
+                caller,
+                data,
+                gas_limit,
+            ));
+        }
         match &hex::encode(target)[..] {
-            "0000000000000000000000000000000000000002" => {
-                return (true, Sha256::digest(&data).to_vec(), *gas_limit as i64);
+            "0000000000000000000000000000000000000001" => {
+                Some(precompiles::ecrecover(data, gas_limit))
+            }
+            "0000000000000000000000000000000000000002" => Some((
+                Sha256::digest(&data).to_vec(),
+                gas_left,
+                StatusCode::EVMC_SUCCESS,
+            )),
+            "0000000000000000000000000000000000000009" => Some((
+                keccak_256(&data).to_vec(),
+                gas_left,
+                StatusCode::EVMC_SUCCESS,
+            )),
+            "0000000000000000000000000000000000000800" => Some(precompiles::erc20::<T>(
+                precompiles::Token::Native,
+                caller,
+                data,
+                gas_limit,
+            )),
+            "0000000000000000000000000000000000000801" => {
+                Some(precompiles::dispatch::<T>(caller, data, gas_limit))
+            }
+            "0000000000000000000000000000000000000802" => {
+                Some(precompiles::randomness::<T>(source, data, gas_limit))
+            }
+            "0000000000000000000000000000000000000804" => {
+                Some(precompiles::multisig::<T>(caller, data, gas_limit))
             }
-            "0000000000000000000000000000000000000009" => {
-                return (true, Keccak256::digest(&data).to_vec(), *gas_limit as i64);
+            "0000000000000000000000000000000000000805" => {
+                Some(precompiles::gas_left::<T>(gas_limit))
             }
-            _ => {
-                return (false, vec![0u8], *gas_limit as i64);
+            #[cfg(feature = "xcm")]
+            "0000000000000000000000000000000000000803" => {
+                Some(precompiles::xcm::<T>(caller, data, gas_limit))
             }
+            _ => None,
         }
     }
 
-    /// Execute SSVM.
+    /// Block fields seen by contracts. Queries against the state of a
+    /// finalized block use its snapshot in `BlockContexts`, as the digest
+    /// identifying the author is gone by then.
+    pub fn block_context() -> Result<BlockContext, Error<T>> {
+        let number: u128 = frame_system::Module::<T>::block_number().unique_saturated_into();
+        let number: u64 = number
+            .try_into()
+            .map_err(|_| Error::<T>::TxContextOverflow)?;
+        match BlockContexts::get(number) {
+            Some(context) => Ok(context),
+            None => Self::current_block_context(),
+        }
+    }
+
+    /// Block fields of the block being built.
+    fn current_block_context() -> Result<BlockContext, Error<T>> {
+        let number: u128 = frame_system::Module::<T>::block_number().unique_saturated_into();
+        let number: u64 = number
+            .try_into()
+            .map_err(|_| Error::<T>::TxContextOverflow)?;
+        let timestamp: u128 = pallet_timestamp::Module::<T>::get().unique_saturated_into();
+        let digest = frame_system::Module::<T>::digest();
+        let pre_runtime_digests = digest.logs.iter().filter_map(|d| d.as_pre_runtime());
+        let randao = T::Randomness::random(&(&b"ssvm/randao"[..], number).encode());
+        Ok(BlockContext {
+            number,
+            timestamp: timestamp
+                .try_into()
+                .map_err(|_| Error::<T>::TxContextOverflow)?,
+            coinbase: T::FindAuthor::find_author(pre_runtime_digests).unwrap_or_default(),
+            gas_limit: T::WeightToGas::convert(
+                <T as frame_system::Trait>::MaximumBlockWeight::get(),
+            ),
+            // No difficulty in substrate nodes. Contracts see a random value
+            // per block instead, as `PREVRANDAO` after the merge.
+            difficulty: U256::from(randao.as_bytes()),
+            base_fee: Self::min_gas_price(),
+        })
+    }
+
+    /// Context of a transaction sent by `origin`, shared by all nested calls.
+    #[cfg(feature = "std")]
+    fn tx_context(origin: H160, gas_price: U256) -> Result<TxContext, Error<T>> {
+        let block = Self::block_context()?;
+        let context = TxContext::new(
+            gas_price,
+            origin,
+            block.coinbase,
+            block.number,
+            block.timestamp,
+            block.gas_limit,
+            block.difficulty,
+            U256::from(T::ChainId::get()),
+        );
+        ensure!(
+            context.evmc_block_fields().is_some(),
+            Error::<T>::TxContextOverflow
+        );
+        Ok(context)
+    }
+
+    /// Execute a message with SSVM. The code of `message.code_address`, or
+    /// the input of contract creations, runs in the storage and balance
+    /// context of `message.target`.
     #[cfg(feature = "std")]
     fn execute_ssvm(
-        source: H160,
-        target: H160,
-        value: U256,
-        data: Vec<u8>,
-        gas_limit: u32,
-        gas_price: U256,
-        nonce: U256,
-        call_kind: CallKind,
+        tx_context: TxContext,
+        message: Message,
+        read_only: bool,
     ) -> Result<(Vec<u8>, i64, StatusCode), Error<T>> {
-        // No coinbase, difficulty in substrate nodes.
-        let coinbase = H160::zero();
-        let difficulty = U256::zero();
-        let chain_id = U256::from(sp_io::misc::chain_id());
-        let block_number: u128 = frame_system::Module::<T>::block_number().unique_saturated_into();
-        let timestamp: u128 = pallet_timestamp::Module::<T>::get().unique_saturated_into();
-
-        let (is_precompiles, output, gas_left) =
-            Self::execute_precompiles(&target, &value, &data, &gas_limit, &gas_price);
-        if is_precompiles {
-            return Ok((output.to_vec(), gas_left, StatusCode::EVMC_SUCCESS));
+        // Precompiles act as `message.sender`. They are only served to plain
+        // calls of their own address: a delegate call or call code would let
+        // a contract act as the account calling it.
+        if is_precompile(&message.code_address)
+            && (message.kind != CallKind::EVMC_CALL || message.target != message.code_address)
+        {
+            return Ok((Vec::new(), 0, StatusCode::EVMC_FAILURE));
+        }
+        if let Some(result) = Self::execute_precompiles(&message, read_only) {
+            return Ok(result);
         }
 
-        let code = match call_kind {
-            CallKind::EVMC_CALL => AccountCodes::get(&target),
-            CallKind::EVMC_CREATE => data.to_owned(),
-            _ => data.to_owned(),
+        // As in Ethereum, calling an address without code, e.g. to transfer
+        // value to it, succeeds without running anything.
+        let code = match message.kind {
+            CallKind::EVMC_CREATE | CallKind::EVMC_CREATE2 => Rc::new(message.input.to_owned()),
+            _ => match Self::code(&message.code_address) {
+                code if code.is_empty() => {
+                    return Ok((Vec::new(), message.gas as i64, StatusCode::EVMC_SUCCESS));
+                }
+                code => code,
+            },
         };
-        let tx_context = TxContext::new(
-            gas_price,
-            source,
-            coinbase,
-            block_number.try_into().unwrap(),
-            timestamp.try_into().unwrap(),
-            gas_limit.into(),
-            difficulty,
-            chain_id,
-        );
-        let context = HostContext::<T>::new(tx_context);
-        let depth = 0;
-        let create2_salt = [0u8; 32];
-        let vm = ssvm::create();
-        let (output, gas_left, status_code) = vm.execute(
+        let format = match message.kind {
+            CallKind::EVMC_CREATE | CallKind::EVMC_CREATE2 => CodeFormat::detect(&code),
+            _ => Self::code_format(&message.code_address),
+        };
+        Ok(Self::run_vm(
+            tx_context,
+            &message,
+            &code[..],
+            format,
+            read_only,
+        ))
+    }
+
+    /// Create a contract from a running contract, returning the output of a
+    /// failed init code, the gas left, the created address and the status.
+    /// As for transactions, the address derives from the creator's nonce,
+    /// which is incremented even if the creation fails, and creating at an
+    /// address with code or a nonce fails and consumes all gas. The bond of
+    /// the deployed code is reserved from the payer of the creating
+    /// contract's bond. The host interface does not pass the salt of CREATE2,
+    /// which fails.
+    #[cfg(feature = "std")]
+    pub(crate) fn execute_nested_create(
+        tx_context: TxContext,
+        mut message: Message,
+        read_only: bool,
+    ) -> (Vec<u8>, i64, H160, StatusCode) {
+        let failure = |gas_left| (Vec::new(), gas_left, H160::zero(), StatusCode::EVMC_FAILURE);
+        let source = message.sender;
+        let value = message.value;
+        if message.kind != CallKind::EVMC_CREATE
+            || message.is_static
+            || Self::balance_of(&source) < value
+        {
+            return failure(message.gas as i64);
+        }
+        let created_address = create_address(source, Self::nonce_of(&source));
+        if !read_only {
+            Self::mutate_account(&source, |account| {
+                account.nonce = account.nonce.saturating_add(U256::one())
+            });
+        }
+        if Self::is_reserved_address(&created_address)
+            || PendingDeletions::get(&created_address)
+            || Self::address_collides(&created_address)
+        {
+            return failure(0);
+        }
+
+        let checkpoint = Self::checkpoint();
+        if !read_only {
+            Self::journal_transfer(&source, &created_address, value);
+        }
+        message.target = created_address;
+        message.code_address = created_address;
+        let (output, mut gas_left, mut status_code) =
+            match Self::execute_ssvm(tx_context, message, read_only) {
+                Ok(result) => result,
+                Err(_) => (Vec::new(), 0, StatusCode::EVMC_FAILURE),
+            };
+        if status_code == StatusCode::EVMC_SUCCESS && output.len() > T::MaxCodeSize::get() as usize
+        {
+            gas_left = 0;
+            status_code = StatusCode::EVMC_OUT_OF_GAS;
+        }
+        if status_code != StatusCode::EVMC_SUCCESS {
+            Self::revert_to_checkpoint(checkpoint);
+            return (output, gas_left, H160::zero(), status_code);
+        }
+        if !read_only {
+            // The bond of the deployed code is paid by the payer of the bond
+            // of the creating contract. Creating contracts without a bond
+            // payer may only deploy code without a bond.
+            let bonded = match ContractBonds::<T>::get(&source) {
+                Some((_, payer, _)) => {
+                    Self::reserve_code_bond(&created_address, source, &payer, output.len()).is_ok()
+                }
+                None => Self::code_bond(output.len()).is_zero(),
+            };
+            if !bonded {
+                Self::revert_to_checkpoint(checkpoint);
+                return failure(gas_left);
+            }
+            Self::journal(JournalEntry::Bond(created_address));
+            Self::journal(JournalEntry::Code(created_address));
+            Self::add_storage_growth(output.len() as u64, 0);
+            Self::store_code(&created_address, output);
+            ContractOwner::insert(created_address, source);
+        }
+        (
+            Vec::new(),
+            gas_left,
+            created_address,
+            StatusCode::EVMC_SUCCESS,
+        )
+    }
+
+    /// Run `code` in the `Trait::Vm` engine for `message`.
+    #[cfg(feature = "std")]
+    fn run_vm(
+        tx_context: TxContext,
+        message: &Message,
+        code: &[u8],
+        format: CodeFormat,
+        read_only: bool,
+    ) -> (Vec<u8>, i64, StatusCode) {
+        let context = HostContext::<T>::new(tx_context, message.target, read_only);
+        let started = std::time::Instant::now();
+        let (output, gas_left, status_code) = T::Vm::default().execute(
             Box::new(context),
-            Revision::EVMC_BYZANTIUM,
-            call_kind,
-            false,
-            depth,
-            gas_limit.into(),
-            target.as_fixed_bytes(),
-            source.as_fixed_bytes(),
-            &data[..],
-            &value.into(),
-            &code,
-            &create2_salt,
+            Self::revision().evmc_revision(),
+            message,
+            code,
+            format,
         );
-        return Ok((output.to_vec(), gas_left, status_code));
+        metrics::record_execution(started.elapsed());
+        if Self::fuel_exhausted() {
+            let status_code = if HOST_FAILURE.with(|failure| failure.get()) {
+                StatusCode::EVMC_FAILURE
+            } else {
+                StatusCode::EVMC_OUT_OF_GAS
+            };
+            return (Vec::new(), 0, status_code);
+        }
+        // The VM only meters its own gas. The gas charged by host functions in
+        // this and all nested executions is deducted once, from the gas left
+        // of the transaction.
+        let gas_left = if message.depth == 0 {
+            gas_left.saturating_sub(Self::host_gas_used() as i64).max(0)
+        } else {
+            gas_left
+        };
+        (output, gas_left, status_code)
     }
 }
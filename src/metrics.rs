@@ -0,0 +1,84 @@
+// Copyright (C) 2020 Second State.
+// This file is part of Pallet-SSVM.
+
+// Pallet-SSVM is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+
+// Pallet-SSVM is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+//! Node-side counters of VM executions.
+//!
+//! Timings depend on the machine and must not reach runtime state, so they are
+//! kept in process-wide counters that the node can export, e.g. to Prometheus,
+//! by polling `vm_metrics`.
+
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+
+static VM_INSTANTIATIONS: AtomicU64 = AtomicU64::new(0);
+static VM_INSTANTIATION_NANOS: AtomicU64 = AtomicU64::new(0);
+static VM_EXECUTIONS: AtomicU64 = AtomicU64::new(0);
+static VM_EXECUTION_NANOS: AtomicU64 = AtomicU64::new(0);
+
+/// Totals of VM instantiations and executions since the node started.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct VmMetrics {
+    /// Number of VM instances created.
+    pub instantiations: u64,
+    /// Time spent creating VM instances, in nanoseconds.
+    pub instantiation_nanos: u64,
+    /// Number of contract executions.
+    pub executions: u64,
+    /// Time spent executing contracts, in nanoseconds.
+    pub execution_nanos: u64,
+}
+
+impl VmMetrics {
+    /// Average time to create a VM instance, in nanoseconds.
+    pub fn average_instantiation_nanos(&self) -> u64 {
+        self.instantiation_nanos
+            .checked_div(self.instantiations)
+            .unwrap_or(0)
+    }
+
+    /// Average time of a contract execution, in nanoseconds.
+    pub fn average_execution_nanos(&self) -> u64 {
+        self.execution_nanos
+            .checked_div(self.executions)
+            .unwrap_or(0)
+    }
+}
+
+/// Current totals of VM instantiations and executions.
+pub fn vm_metrics() -> VmMetrics {
+    VmMetrics {
+        instantiations: VM_INSTANTIATIONS.load(Ordering::Relaxed),
+        instantiation_nanos: VM_INSTANTIATION_NANOS.load(Ordering::Relaxed),
+        executions: VM_EXECUTIONS.load(Ordering::Relaxed),
+        execution_nanos: VM_EXECUTION_NANOS.load(Ordering::Relaxed),
+    }
+}
+
+fn nanos(duration: Duration) -> u64 {
+    duration.as_nanos().min(u64::max_value() as u128) as u64
+}
+
+/// Count a VM instantiation that took `duration`.
+pub(crate) fn record_instantiation(duration: Duration) {
+    VM_INSTANTIATIONS.fetch_add(1, Ordering::Relaxed);
+    VM_INSTANTIATION_NANOS.fetch_add(nanos(duration), Ordering::Relaxed);
+}
+
+/// Count a contract execution that took `duration`.
+pub(crate) fn record_execution(duration: Duration) {
+    VM_EXECUTIONS.fetch_add(1, Ordering::Relaxed);
+    VM_EXECUTION_NANOS.fetch_add(nanos(duration), Ordering::Relaxed);
+}
@@ -0,0 +1,324 @@
+// Copyright (C) 2020 Second State.
+// This file is part of Pallet-SSVM.
+
+// Pallet-SSVM is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+
+// Pallet-SSVM is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+//! Test runtime of the module.
+
+#[cfg(feature = "xcm")]
+use crate::XcmSender;
+use crate::{
+    Account, ConvertAccountId, EnsureAddressMapped, GenesisConfig, HashedConvertAddress,
+    RawStorageKey, ScaledBalanceConverter, Ssvm, SsvmRevision, Trait,
+};
+use frame_support::traits::{FindAuthor, Get, Randomness};
+use frame_support::weights::Weight;
+use frame_support::{impl_outer_event, impl_outer_origin, parameter_types};
+use frame_system::{self as system, EnsureRoot, EnsureSigned};
+use sp_core::{Blake2Hasher, H160, H256, U256};
+use sp_io::hashing::keccak_256;
+use sp_runtime::testing::Header;
+use sp_runtime::traits::{BlakeTwo256, ConvertInto, IdentityLookup};
+use sp_runtime::{ConsensusEngineId, Perbill, Permill};
+#[cfg(feature = "xcm")]
+use sp_runtime::{DispatchError, DispatchResult};
+use std::cell::RefCell;
+
+mod ssvm {
+    pub use crate::Event;
+}
+
+impl_outer_origin! {
+    pub enum Origin for Test {}
+}
+
+impl_outer_event! {
+    pub enum MetaEvent for Test {
+        system<T>,
+        pallet_balances<T>,
+        ssvm,
+    }
+}
+
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub struct Test;
+
+parameter_types! {
+    pub const BlockHashCount: u64 = 250;
+    pub const MaximumBlockWeight: Weight = 1_000_000_000;
+    pub const MaximumBlockLength: u32 = 2 * 1024 * 1024;
+    pub const AvailableBlockRatio: Perbill = Perbill::one();
+}
+
+impl frame_system::Trait for Test {
+    type Origin = Origin;
+    type Call = ();
+    type Index = u64;
+    type BlockNumber = u64;
+    type Hash = H256;
+    type Hashing = BlakeTwo256;
+    type AccountId = u64;
+    type Lookup = IdentityLookup<u64>;
+    type Header = Header;
+    type Event = MetaEvent;
+    type BlockHashCount = BlockHashCount;
+    type MaximumBlockWeight = MaximumBlockWeight;
+    type MaximumBlockLength = MaximumBlockLength;
+    type AvailableBlockRatio = AvailableBlockRatio;
+    type Version = ();
+    type ModuleToIndex = ();
+    type AccountData = pallet_balances::AccountData<u64>;
+    type OnNewAccount = ();
+    type OnKilledAccount = ();
+}
+
+parameter_types! {
+    pub const ExistentialDeposit: u64 = 10;
+}
+
+impl pallet_balances::Trait for Test {
+    type Balance = u64;
+    type DustRemoval = ();
+    type Event = MetaEvent;
+    type ExistentialDeposit = ExistentialDeposit;
+    type AccountStore = System;
+}
+
+parameter_types! {
+    pub const MinimumPeriod: u64 = 1;
+}
+
+impl pallet_timestamp::Trait for Test {
+    type Moment = u64;
+    type OnTimestampSet = ();
+    type MinimumPeriod = MinimumPeriod;
+}
+
+/// Addresses of test accounts: a marker byte out of the reserved ranges,
+/// followed by the big endian account id. As in runtimes, addresses act on
+/// other modules as hashed accounts, which is not the inverse.
+pub struct TestAddressMapping;
+
+impl ConvertAccountId<u64> for TestAddressMapping {
+    fn convert_account_id(account_id: &u64) -> H160 {
+        let mut address = [0u8; 20];
+        address[0] = 0x10;
+        address[12..].copy_from_slice(&account_id.to_be_bytes());
+        H160::from(address)
+    }
+}
+
+/// Randomness hashing the subject, for reproducible tests.
+pub struct TestRandomness;
+
+impl Randomness<H256> for TestRandomness {
+    fn random(subject: &[u8]) -> H256 {
+        H256::from(keccak_256(subject))
+    }
+}
+
+/// Blocks without author.
+pub struct NoAuthor;
+
+impl FindAuthor<H160> for NoAuthor {
+    fn find_author<'a, I>(_digests: I) -> Option<H160>
+    where
+        I: 'a + IntoIterator<Item = (ConsensusEngineId, &'a [u8])>,
+    {
+        None
+    }
+}
+
+/// Parachain without cross-chain messaging.
+#[cfg(feature = "xcm")]
+pub struct NoXcm;
+
+#[cfg(feature = "xcm")]
+impl XcmSender<u64> for NoXcm {
+    fn reserve_transfer(
+        _origin: &u64,
+        _dest: u32,
+        _beneficiary: H256,
+        _amount: U256,
+    ) -> DispatchResult {
+        Err(DispatchError::Other("no cross-chain messaging"))
+    }
+
+    fn transact(_origin: &u64, _dest: u32, _call: Vec<u8>, _weight: Weight) -> DispatchResult {
+        Err(DispatchError::Other("no cross-chain messaging"))
+    }
+}
+
+parameter_types! {
+    pub const MinGasPrice: U256 = U256::zero();
+    pub const CongestionThreshold: Permill = Permill::one();
+    pub const GasPriceAdjustment: Permill = Permill::zero();
+    pub const FuelPerGas: u64 = 1;
+    /// 12 decimals balances bridged to 18 decimals EVM balances.
+    pub const BalanceScale: u32 = 6;
+    pub const MaxMetadataSize: u32 = 1024;
+    pub const GasStatsHistory: u32 = 16;
+    pub const TransactionIndexRetention: u32 = 0;
+    pub const Revision: SsvmRevision = SsvmRevision::Istanbul;
+    pub const ChainId: u64 = 42;
+    pub const AllowUnprotectedTransactions: bool = true;
+    pub const MaxCodeSize: u32 = 24 * 1024;
+    pub const MaxLogDataSize: u32 = 1024;
+    pub const MaxTopics: u32 = 4;
+    pub const MaxTransactionInputSize: u32 = 128 * 1024;
+    pub const RejectCallsWithoutCode: bool = false;
+    pub const MultisigValueThreshold: U256 = U256::max_value();
+    pub const MaxSignatories: u32 = 8;
+    pub const RateLimitWindow: u32 = 0;
+    pub const MaxTransactionsPerWindow: u32 = u32::max_value();
+    pub const MaxGasPerWindow: u64 = u64::max_value();
+    pub const DeletionBudget: u32 = 0;
+    pub const MaxStaticCallWeight: Weight = 10_000_000;
+    pub const HookDeposit: U256 = U256::zero();
+    pub const MaxHookGas: u32 = 2_000_000;
+    pub const MaxBlockHooks: u32 = 4;
+    pub const MaxJobGas: u32 = 100_000;
+    pub const MaxJobsPerBlock: u32 = 4;
+    pub const MaxInitializeWeight: Weight = 1_000_000;
+    pub const BlockContextRetention: u32 = 0;
+    pub SystemContracts: Vec<(H160, Vec<u8>)> = Vec::new();
+    pub const SystemContractsVersion: u32 = 0;
+}
+
+thread_local! {
+    static CONTRACT_BOND_PER_BYTE: RefCell<U256> = RefCell::new(U256::zero());
+}
+
+/// Contract bond per byte, set by tests through `set_contract_bond_per_byte`.
+pub struct ContractBondPerByte;
+
+impl Get<U256> for ContractBondPerByte {
+    fn get() -> U256 {
+        CONTRACT_BOND_PER_BYTE.with(|bond| *bond.borrow())
+    }
+}
+
+pub fn set_contract_bond_per_byte(bond: U256) {
+    CONTRACT_BOND_PER_BYTE.with(|per_byte| *per_byte.borrow_mut() = bond);
+}
+
+impl Trait for Test {
+    type ConvertAccountId = TestAddressMapping;
+    type EnsureAddressOrigin = EnsureAddressMapped<Test>;
+    type Currency = Balances;
+    type BalanceConverter = ScaledBalanceConverter<BalanceScale>;
+    type FeeConverter = ScaledBalanceConverter<BalanceScale>;
+    type Event = MetaEvent;
+    type MinGasPrice = MinGasPrice;
+    type CongestionThreshold = CongestionThreshold;
+    type GasPriceAdjustment = GasPriceAdjustment;
+    type FuelPerGas = FuelPerGas;
+    type ConvertAddress = HashedConvertAddress<Blake2Hasher>;
+    type DispatchCall = pallet_balances::Call<Test>;
+    type DispatchFilter = ();
+    type WeightToGas = ConvertInto;
+    type Assets = ();
+    type Randomness = TestRandomness;
+    type ExtendedHostFunctions = ();
+    #[cfg(feature = "xcm")]
+    type XcmSender = NoXcm;
+    #[cfg(feature = "xcm")]
+    type MaxXcmWeight = MaxStaticCallWeight;
+    type MaxMetadataSize = MaxMetadataSize;
+    type StorageKeyHasher = RawStorageKey;
+    type GasStatsHistory = GasStatsHistory;
+    type CodeUpgradeOrigin = EnsureSigned<u64>;
+    type TransactionIndexRetention = TransactionIndexRetention;
+    type OnFeePayment = ();
+    type OnSsvmExecuted = ();
+    type Paymaster = ();
+    type Vm = Ssvm;
+    type Revision = Revision;
+    type ForkOrigin = EnsureRoot<u64>;
+    type ChainId = ChainId;
+    type AllowUnprotectedTransactions = AllowUnprotectedTransactions;
+    type MaxCodeSize = MaxCodeSize;
+    type MaxLogDataSize = MaxLogDataSize;
+    type MaxTopics = MaxTopics;
+    type MaxTransactionInputSize = MaxTransactionInputSize;
+    type RejectCallsWithoutCode = RejectCallsWithoutCode;
+    type MultisigValueThreshold = MultisigValueThreshold;
+    type MaxSignatories = MaxSignatories;
+    type RateLimitWindow = RateLimitWindow;
+    type MaxTransactionsPerWindow = MaxTransactionsPerWindow;
+    type MaxGasPerWindow = MaxGasPerWindow;
+    type DeletionBudget = DeletionBudget;
+    type MaxStaticCallWeight = MaxStaticCallWeight;
+    type HookOrigin = EnsureRoot<u64>;
+    type HookDeposit = HookDeposit;
+    type MaxHookGas = MaxHookGas;
+    type MaxBlockHooks = MaxBlockHooks;
+    type MaxJobGas = MaxJobGas;
+    type MaxJobsPerBlock = MaxJobsPerBlock;
+    type MaxInitializeWeight = MaxInitializeWeight;
+    type FindAuthor = NoAuthor;
+    type BlockContextRetention = BlockContextRetention;
+    type SystemContracts = SystemContracts;
+    type SystemContractsVersion = SystemContractsVersion;
+    type ForceOrigin = EnsureRoot<u64>;
+    type ContractBondPerByte = ContractBondPerByte;
+}
+
+pub type System = frame_system::Module<Test>;
+pub type Balances = pallet_balances::Module<Test>;
+pub type SSVM = crate::Module<Test>;
+
+pub const ALICE: u64 = 1;
+pub const BOB: u64 = 2;
+
+/// EVM address of a test account.
+pub fn address_of(account_id: u64) -> H160 {
+    TestAddressMapping::convert_account_id(&account_id)
+}
+
+/// Externalities where `ALICE` and `BOB` hold currency and EVM balances.
+pub fn new_test_ext() -> sp_io::TestExternalities {
+    let mut storage = system::GenesisConfig::default()
+        .build_storage::<Test>()
+        .unwrap();
+    pallet_balances::GenesisConfig::<Test> {
+        balances: vec![(ALICE, 1_000_000_000), (BOB, 1_000_000_000)],
+    }
+    .assimilate_storage(&mut storage)
+    .unwrap();
+    GenesisConfig {
+        accounts: vec![
+            (
+                address_of(ALICE),
+                Account {
+                    nonce: U256::zero(),
+                    balance: U256::exp10(18),
+                },
+            ),
+            (
+                address_of(BOB),
+                Account {
+                    nonce: U256::zero(),
+                    balance: U256::exp10(18),
+                },
+            ),
+        ],
+    }
+    .assimilate_storage(&mut storage)
+    .unwrap();
+
+    let mut ext = sp_io::TestExternalities::new(storage);
+    ext.execute_with(|| System::set_block_number(1));
+    ext
+}
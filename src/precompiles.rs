@@ -0,0 +1,622 @@
+// Copyright (C) 2020 Second State.
+// This file is part of Pallet-SSVM.
+
+// Pallet-SSVM is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+
+// Pallet-SSVM is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+//! Precompiled contracts implemented by the pallet.
+
+use crate::backend::JournalEntry;
+use crate::transaction::recover_signer;
+use crate::{
+    asset_address, Allowances, Assets, CallFilter, ConvertAddress, ExtendedHostFunctions, Log,
+    Module, Trait,
+};
+#[cfg(feature = "xcm")]
+use crate::{XcmMessage, XcmSender};
+use codec::{Decode, Encode};
+use frame_support::storage::StorageDoubleMap;
+#[cfg(feature = "xcm")]
+use frame_support::traits::Get;
+use frame_support::traits::{Currency, Randomness};
+use frame_support::weights::GetDispatchInfo;
+use sp_core::{H160, H256, U256};
+use sp_runtime::traits::{Convert, Dispatchable};
+use sp_std::vec::Vec;
+use ssvm::types::{CallKind, StatusCode};
+
+/// Gas charged by read-only ERC-20 methods.
+pub const ERC20_READ_GAS: i64 = 1_000;
+/// Gas charged by ERC-20 methods writing state.
+pub const ERC20_WRITE_GAS: i64 = 10_000;
+
+/// Gas charged by the ecrecover precompile.
+pub const ECRECOVER_GAS: i64 = 3_000;
+
+/// Gas charged by the randomness precompile.
+pub const RANDOMNESS_GAS: i64 = 1_000;
+
+/// Gas charged by read-only multisig methods.
+pub const MULTISIG_READ_GAS: i64 = 1_000;
+/// Gas charged by multisig methods writing state.
+pub const MULTISIG_WRITE_GAS: i64 = 10_000;
+
+/// Gas charged by the gas left precompile.
+pub const GAS_LEFT_GAS: i64 = 2;
+
+/// Base gas charged by the XCM precompile.
+#[cfg(feature = "xcm")]
+pub const XCM_BASE_GAS: i64 = 10_000;
+
+/// `totalSupply()`
+const SELECTOR_TOTAL_SUPPLY: [u8; 4] = [0x18, 0x16, 0x0d, 0xdd];
+/// `balanceOf(address)`
+const SELECTOR_BALANCE_OF: [u8; 4] = [0x70, 0xa0, 0x82, 0x31];
+/// `allowance(address,address)`
+const SELECTOR_ALLOWANCE: [u8; 4] = [0xdd, 0x62, 0xed, 0x3e];
+/// `decimals()`
+const SELECTOR_DECIMALS: [u8; 4] = [0x31, 0x3c, 0xe5, 0x67];
+/// `transfer(address,uint256)`
+const SELECTOR_TRANSFER: [u8; 4] = [0xa9, 0x05, 0x9c, 0xbb];
+/// `approve(address,uint256)`
+const SELECTOR_APPROVE: [u8; 4] = [0x09, 0x5e, 0xa7, 0xb3];
+/// `transferFrom(address,address,uint256)`
+const SELECTOR_TRANSFER_FROM: [u8; 4] = [0x23, 0xb8, 0x72, 0xdd];
+
+/// `register(uint16,address[])`
+const SELECTOR_REGISTER: [u8; 4] = [0x32, 0x65, 0xbe, 0x03];
+/// `approve(address,bytes32)`
+const SELECTOR_MULTISIG_APPROVE: [u8; 4] = [0x5c, 0xd2, 0xf4, 0xd3];
+/// `approvals(address,bytes32)`
+const SELECTOR_APPROVALS: [u8; 4] = [0x8e, 0xdc, 0x5c, 0x04];
+
+/// Topic of `Transfer(address,address,uint256)`.
+const TOPIC_TRANSFER: [u8; 32] = [
+    0xdd, 0xf2, 0x52, 0xad, 0x1b, 0xe2, 0xc8, 0x9b, 0x69, 0xc2, 0xb0, 0x68, 0xfc, 0x37, 0x8d, 0xaa,
+    0x95, 0x2b, 0xa7, 0xf1, 0x63, 0xc4, 0xa1, 0x16, 0x28, 0xf5, 0x5a, 0x4d, 0xf5, 0x23, 0xb3, 0xef,
+];
+/// Topic of `Approval(address,address,uint256)`.
+const TOPIC_APPROVAL: [u8; 32] = [
+    0x8c, 0x5b, 0xe1, 0xe5, 0xeb, 0xec, 0x7d, 0x5b, 0xd1, 0x4f, 0x71, 0x42, 0x7d, 0x1e, 0x84, 0xf3,
+    0xdd, 0x03, 0x14, 0xc0, 0xf7, 0xb2, 0x29, 0x1e, 0x5b, 0x20, 0x0a, 0xc8, 0xc7, 0xc3, 0xb9, 0x25,
+];
+
+/// Result of a precompile: output, gas left and status code.
+pub type PrecompileResult = (Vec<u8>, i64, StatusCode);
+
+/// Account a precompile acts as, and how it is called.
+#[derive(Clone, Copy)]
+pub struct Caller {
+    /// Kind of the call.
+    pub kind: CallKind,
+    /// Sender of the call, which the precompile acts as.
+    pub source: H160,
+    /// Dry run: state changes are skipped but reported as done, so that gas
+    /// estimates cover them.
+    pub read_only: bool,
+    /// Static call: state changes fail.
+    pub is_static: bool,
+    /// Call from a contract rather than a transaction, rolled back if a
+    /// calling frame fails.
+    pub nested: bool,
+}
+
+/// Result of a failed precompile, consuming all gas.
+pub fn failure() -> PrecompileResult {
+    (Vec::new(), 0, StatusCode::EVMC_FAILURE)
+}
+
+/// Result of a reverted precompile, keeping the gas left.
+pub fn revert(gas_left: i64) -> PrecompileResult {
+    (Vec::new(), gas_left, StatusCode::EVMC_REVERT)
+}
+
+/// Split the 4 bytes function selector from the ABI encoded arguments.
+pub fn split_selector(input: &[u8]) -> Option<([u8; 4], &[u8])> {
+    if input.len() < 4 {
+        return None;
+    }
+    let mut selector = [0u8; 4];
+    selector.copy_from_slice(&input[..4]);
+    Some((selector, &input[4..]))
+}
+
+/// Read the ABI encoded word at `index` of `args`.
+pub fn word(args: &[u8], index: usize) -> Option<&[u8]> {
+    args.get(index * 32..(index + 1) * 32)
+}
+
+/// Read the ABI encoded address at `index` of `args`.
+pub fn address_arg(args: &[u8], index: usize) -> Option<H160> {
+    word(args, index).map(|word| H160::from_slice(&word[12..]))
+}
+
+/// Read the ABI encoded uint256 at `index` of `args`.
+pub fn u256_arg(args: &[u8], index: usize) -> Option<U256> {
+    word(args, index).map(U256::from_big_endian)
+}
+
+/// Read the ABI encoded `address[]` whose offset is at `index` of `args`.
+pub fn address_array_arg(args: &[u8], index: usize) -> Option<Vec<H160>> {
+    let offset = u256_arg(args, index)?;
+    if offset % 32 != U256::zero() || offset > U256::from(args.len()) {
+        return None;
+    }
+    let array = &args[offset.as_usize()..];
+    let len = u256_arg(array, 0)?;
+    if len > U256::from(array.len() / 32) {
+        return None;
+    }
+    (1..=len.as_usize())
+        .map(|i| address_arg(array, i))
+        .collect()
+}
+
+/// ABI encode a uint256.
+pub fn encode_u256(value: U256) -> Vec<u8> {
+    let mut output = [0u8; 32];
+    value.to_big_endian(&mut output);
+    output.to_vec()
+}
+
+/// ABI encode a bool.
+pub fn encode_bool(value: bool) -> Vec<u8> {
+    encode_u256(if value { U256::one() } else { U256::zero() })
+}
+
+/// Topic holding an address.
+pub fn address_topic(address: &H160) -> H256 {
+    H256::from(*address)
+}
+
+/// Token exposed through the ERC-20 precompiles.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Token {
+    /// Native EVM balances in `Accounts`.
+    Native,
+    /// An asset of `T::Assets`.
+    Asset(u128),
+}
+
+impl Token {
+    /// Address of the token precompile.
+    pub fn address(&self) -> H160 {
+        match self {
+            Token::Native => crate::ERC20_PRECOMPILE,
+            Token::Asset(id) => asset_address(*id),
+        }
+    }
+
+    fn total_supply<T: Trait>(&self) -> Option<U256> {
+        match self {
+            Token::Native => {
+                let pot = T::Currency::free_balance(&Module::<T>::account_id());
+                Some(T::BalanceConverter::into_evm(pot).unwrap_or_else(U256::max_value))
+            }
+            Token::Asset(id) => T::Assets::total_supply(*id),
+        }
+    }
+
+    fn balance_of<T: Trait>(&self, owner: &H160) -> U256 {
+        match self {
+            Token::Native => Module::<T>::balance_of(owner),
+            Token::Asset(id) => T::Assets::balance(*id, &T::ConvertAddress::convert_address(owner)),
+        }
+    }
+
+    fn decimals<T: Trait>(&self) -> Option<U256> {
+        match self {
+            Token::Native => Some(U256::from(18)),
+            Token::Asset(_) => None,
+        }
+    }
+
+    /// Move balance, emitting an ERC-20 `Transfer` log. Changes are journaled,
+    /// and undone if the call frame fails.
+    fn transfer<T: Trait>(&self, from: &H160, to: &H160, amount: U256, read_only: bool) -> bool {
+        if self.balance_of::<T>(from) < amount {
+            return false;
+        }
+        if read_only {
+            return true;
+        }
+        if *self == Token::Native {
+            let call_hash = Module::<T>::multisig_call_hash(to, amount, &[]);
+            Module::<T>::journal(JournalEntry::MultisigApprovals(
+                *from,
+                call_hash,
+                Module::<T>::multisig_approvals(from, call_hash),
+            ));
+            if Module::<T>::ensure_multisig_approved(from, to, amount, &[]).is_err() {
+                return false;
+            }
+        }
+        if from != to {
+            match self {
+                Token::Native => {
+                    Module::<T>::mutate_account(from, |account| account.balance -= amount);
+                    Module::<T>::mutate_account(to, |account| {
+                        account.balance = account.balance.saturating_add(amount)
+                    });
+                }
+                Token::Asset(id) => {
+                    let from_account = T::ConvertAddress::convert_address(from);
+                    let to_account = T::ConvertAddress::convert_address(to);
+                    if T::Assets::transfer(*id, &from_account, &to_account, amount).is_err() {
+                        return false;
+                    }
+                    Module::<T>::journal(JournalEntry::AssetTransfer(*id, *from, *to, amount));
+                }
+            }
+        }
+        self.log::<T>(TOPIC_TRANSFER, from, to, amount);
+        true
+    }
+
+    /// Emit an ERC-20 log with two indexed addresses and an amount.
+    fn log<T: Trait>(&self, topic: [u8; 32], first: &H160, second: &H160, amount: U256) {
+        Module::<T>::deposit_log(Log {
+            address: self.address(),
+            topics: vec![
+                H256::from(topic),
+                address_topic(first),
+                address_topic(second),
+            ],
+            data: encode_u256(amount),
+        });
+    }
+}
+
+/// ERC-20 interface over the balances of `token`. Methods changing balances
+/// or allowances fail in static calls.
+pub fn erc20<T: Trait>(
+    token: Token,
+    caller: Caller,
+    input: &[u8],
+    gas_limit: u32,
+) -> PrecompileResult {
+    let gas = gas_limit as i64;
+    let (selector, args) = match split_selector(input) {
+        Some(split) => split,
+        None => return failure(),
+    };
+    let writes = match selector {
+        SELECTOR_TRANSFER | SELECTOR_APPROVE | SELECTOR_TRANSFER_FROM => true,
+        _ => false,
+    };
+    if writes && caller.is_static {
+        return failure();
+    }
+    let cost = if writes {
+        ERC20_WRITE_GAS
+    } else {
+        ERC20_READ_GAS
+    };
+    if gas < cost {
+        return (Vec::new(), 0, StatusCode::EVMC_OUT_OF_GAS);
+    }
+    let gas_left = gas - cost;
+    let address = token.address();
+    let source = &caller.source;
+    let read_only = caller.read_only;
+
+    let output = match selector {
+        SELECTOR_TOTAL_SUPPLY => token.total_supply::<T>().map(encode_u256),
+        SELECTOR_BALANCE_OF => {
+            address_arg(args, 0).map(|owner| encode_u256(token.balance_of::<T>(&owner)))
+        }
+        SELECTOR_ALLOWANCE => match (address_arg(args, 0), address_arg(args, 1)) {
+            (Some(owner), Some(spender)) => {
+                Some(encode_u256(Allowances::get(&address, (owner, spender))))
+            }
+            _ => None,
+        },
+        SELECTOR_DECIMALS => token.decimals::<T>().map(encode_u256),
+        SELECTOR_TRANSFER => match (address_arg(args, 0), u256_arg(args, 1)) {
+            (Some(to), Some(amount)) if token.transfer::<T>(source, &to, amount, read_only) => {
+                Some(encode_bool(true))
+            }
+            _ => None,
+        },
+        SELECTOR_APPROVE => match (address_arg(args, 0), u256_arg(args, 1)) {
+            (Some(spender), Some(amount)) => {
+                if !read_only {
+                    Module::<T>::set_allowance(&address, (*source, spender), amount);
+                    token.log::<T>(TOPIC_APPROVAL, source, &spender, amount);
+                }
+                Some(encode_bool(true))
+            }
+            _ => None,
+        },
+        SELECTOR_TRANSFER_FROM => {
+            match (
+                address_arg(args, 0),
+                address_arg(args, 1),
+                u256_arg(args, 2),
+            ) {
+                (Some(from), Some(to), Some(amount)) => {
+                    let allowance = Allowances::get(&address, (from, *source));
+                    if allowance < amount || !token.transfer::<T>(&from, &to, amount, read_only) {
+                        None
+                    } else {
+                        if !read_only && allowance != U256::max_value() {
+                            Module::<T>::set_allowance(
+                                &address,
+                                (from, *source),
+                                allowance - amount,
+                            );
+                        }
+                        Some(encode_bool(true))
+                    }
+                }
+                _ => None,
+            }
+        }
+        _ => None,
+    };
+
+    match output {
+        Some(output) => (output, gas_left, StatusCode::EVMC_SUCCESS),
+        None => revert(gas_left),
+    }
+}
+
+/// Dispatch a SCALE encoded runtime call with the signed origin of the caller.
+/// Only plain calls may dispatch, so that no contract acts as its caller
+/// through a delegate call, and static calls fail. The effects of a
+/// dispatched call can not be undone if a calling frame fails, so only
+/// transactions may dispatch, not nested calls from contracts.
+pub fn dispatch<T: Trait>(caller: Caller, input: &[u8], gas_limit: u32) -> PrecompileResult {
+    if caller.kind != CallKind::EVMC_CALL || caller.nested || caller.is_static {
+        return failure();
+    }
+    let call = match T::DispatchCall::decode(&mut &input[..]) {
+        Ok(call) => call,
+        Err(_) => return failure(),
+    };
+    if !T::DispatchFilter::filter(&call) {
+        return failure();
+    }
+
+    let weight = call.get_dispatch_info().weight;
+    let cost = T::WeightToGas::convert(weight).min(i64::max_value() as u64) as i64;
+    let gas = gas_limit as i64;
+    if gas < cost {
+        return (Vec::new(), 0, StatusCode::EVMC_OUT_OF_GAS);
+    }
+    let gas_left = gas - cost;
+    if caller.read_only {
+        return (Vec::new(), gas_left, StatusCode::EVMC_SUCCESS);
+    }
+
+    let origin =
+        frame_system::RawOrigin::Signed(T::ConvertAddress::convert_address(&caller.source));
+    match call.dispatch(origin.into()) {
+        Ok(_) => (Vec::new(), gas_left, StatusCode::EVMC_SUCCESS),
+        Err(_) => revert(gas_left),
+    }
+}
+
+/// Recover the address that signed a message hash, as the Ethereum
+/// `ecrecover` precompile. The input is the hash, `v`, `r` and `s` as 32 bytes
+/// words, padded with zeros. The output is the left-padded address, or empty
+/// if the signature is invalid.
+pub fn ecrecover(input: &[u8], gas_limit: u32) -> PrecompileResult {
+    let gas = gas_limit as i64;
+    if gas < ECRECOVER_GAS {
+        return (Vec::new(), 0, StatusCode::EVMC_OUT_OF_GAS);
+    }
+    let gas_left = gas - ECRECOVER_GAS;
+
+    let mut padded = [0u8; 128];
+    let len = input.len().min(128);
+    padded[..len].copy_from_slice(&input[..len]);
+    let v = U256::from_big_endian(&padded[32..64]);
+    if v != U256::from(27) && v != U256::from(28) {
+        return (Vec::new(), gas_left, StatusCode::EVMC_SUCCESS);
+    }
+    let mut signature = [0u8; 65];
+    signature[..64].copy_from_slice(&padded[64..128]);
+    signature[64] = padded[63] - 27;
+    match recover_signer(&signature, &H256::from_slice(&padded[..32])) {
+        Some(signer) => (
+            address_topic(&signer).as_bytes().to_vec(),
+            gas_left,
+            StatusCode::EVMC_SUCCESS,
+        ),
+        None => (Vec::new(), gas_left, StatusCode::EVMC_SUCCESS),
+    }
+}
+
+/// Return runtime randomness for a subject derived from the caller, its nonce
+/// and the input.
+pub fn randomness<T: Trait>(source: &H160, input: &[u8], gas_limit: u32) -> PrecompileResult {
+    let gas = gas_limit as i64;
+    if gas < RANDOMNESS_GAS {
+        return (Vec::new(), 0, StatusCode::EVMC_OUT_OF_GAS);
+    }
+
+    let mut subject = (source, Module::<T>::nonce_of(source)).encode();
+    subject.extend_from_slice(input);
+    let random = T::Randomness::random(&subject);
+    (
+        random.as_bytes().to_vec(),
+        gas - RANDOMNESS_GAS,
+        StatusCode::EVMC_SUCCESS,
+    )
+}
+
+/// Multisig guards over the balance of the caller.
+///
+/// `register(threshold, signatories)` sets the guard of the caller, or removes
+/// it with a zero threshold. Changing an existing guard must be approved by its
+/// signatories, like any call. `approve(account, callHash)` approves a call of a
+/// guarded account, see `Module::multisig_call_hash`, and `approvals(account,
+/// callHash)` returns the number of approvals of it. Only plain calls are
+/// served. `register` and `approve` fail in static calls, and as they can not
+/// be undone if a calling frame fails, they revert in nested calls from
+/// contracts.
+pub fn multisig<T: Trait>(caller: Caller, input: &[u8], gas_limit: u32) -> PrecompileResult {
+    if caller.kind != CallKind::EVMC_CALL {
+        return failure();
+    }
+    let gas = gas_limit as i64;
+    let (selector, args) = match split_selector(input) {
+        Some(split) => split,
+        None => return failure(),
+    };
+    let cost = match selector {
+        SELECTOR_APPROVALS => MULTISIG_READ_GAS,
+        _ if caller.is_static => return failure(),
+        _ => MULTISIG_WRITE_GAS,
+    };
+    let source = &caller.source;
+    if gas < cost {
+        return (Vec::new(), 0, StatusCode::EVMC_OUT_OF_GAS);
+    }
+    let gas_left = gas - cost;
+
+    let output = match selector {
+        SELECTOR_REGISTER => match (u256_arg(args, 0), address_array_arg(args, 1)) {
+            (Some(threshold), Some(signatories)) if threshold <= U256::from(u16::max_value()) => {
+                if caller.read_only || caller.nested {
+                    None
+                } else {
+                    Module::<T>::set_multisig_guard(source, threshold.as_u32(), signatories, input)
+                        .ok()
+                        .map(|_| encode_bool(true))
+                }
+            }
+            _ => None,
+        },
+        SELECTOR_MULTISIG_APPROVE => match (address_arg(args, 0), word(args, 1)) {
+            (Some(account), Some(call_hash)) => {
+                if caller.read_only || caller.nested {
+                    None
+                } else {
+                    Module::<T>::approve_multisig_call(
+                        &account,
+                        H256::from_slice(call_hash),
+                        source,
+                    )
+                    .ok()
+                    .map(|_| encode_bool(true))
+                }
+            }
+            _ => None,
+        },
+        SELECTOR_APPROVALS => match (address_arg(args, 0), word(args, 1)) {
+            (Some(account), Some(call_hash)) => Some(encode_u256(U256::from(
+                Module::<T>::multisig_approvals(account, H256::from_slice(call_hash)).len(),
+            ))),
+            _ => None,
+        },
+        _ => None,
+    };
+
+    match output {
+        Some(output) => (output, gas_left, StatusCode::EVMC_SUCCESS),
+        None => revert(gas_left),
+    }
+}
+
+/// Return the gas left as a uint256: the gas passed to the precompile, or the
+/// gas left for host functions in the transaction if lower. Contracts use it
+/// instead of `gasleft()`, which does not see the gas charged by the host.
+pub fn gas_left<T: Trait>(gas_limit: u32) -> PrecompileResult {
+    let gas = gas_limit as i64;
+    if gas < GAS_LEFT_GAS {
+        return (Vec::new(), 0, StatusCode::EVMC_OUT_OF_GAS);
+    }
+    let gas_left = gas - GAS_LEFT_GAS;
+    let host_gas_left = Module::<T>::host_gas_left().min(i64::max_value() as u64) as i64;
+    (
+        encode_u256(U256::from(gas_left.min(host_gas_left))),
+        gas_left,
+        StatusCode::EVMC_SUCCESS,
+    )
+}
+
+/// Call an extended host function, charging the gas it prices the call at.
+/// Its state changes can not be undone if a calling frame fails, so nested
+/// calls from contracts are read-only, as are static calls.
+pub fn extended_host<T: Trait>(
+    function: u32,
+    caller: Caller,
+    input: &[u8],
+    gas_limit: u32,
+) -> PrecompileResult {
+    let gas = match T::ExtendedHostFunctions::gas(function, input) {
+        Some(gas) => gas,
+        None => return failure(),
+    };
+    if gas > gas_limit as u64 {
+        return (Vec::new(), 0, StatusCode::EVMC_OUT_OF_GAS);
+    }
+
+    let gas_left = gas_limit as i64 - gas as i64;
+    let read_only = caller.read_only || caller.is_static || caller.nested;
+    match T::ExtendedHostFunctions::call(function, &caller.source, input, read_only) {
+        Ok(output) => (output, gas_left, StatusCode::EVMC_SUCCESS),
+        Err(_) => revert(gas_left),
+    }
+}
+
+/// Send a SCALE encoded `XcmMessage` with the caller as origin. Only plain
+/// calls from transactions are served: a sent message can not be undone if a
+/// calling frame fails.
+#[cfg(feature = "xcm")]
+pub fn xcm<T: Trait>(caller: Caller, input: &[u8], gas_limit: u32) -> PrecompileResult {
+    if caller.kind != CallKind::EVMC_CALL || caller.nested || caller.is_static {
+        return failure();
+    }
+    let message = match XcmMessage::decode(&mut &input[..]) {
+        Ok(message) => message,
+        Err(_) => return failure(),
+    };
+
+    let weight = match &message {
+        XcmMessage::ReserveTransfer { .. } => 0,
+        XcmMessage::Transact { weight, .. } => *weight,
+    };
+    if weight > T::MaxXcmWeight::get() {
+        return failure();
+    }
+    let cost = T::WeightToGas::convert(weight)
+        .saturating_add(XCM_BASE_GAS as u64)
+        .min(i64::max_value() as u64) as i64;
+    let gas = gas_limit as i64;
+    if gas < cost {
+        return (Vec::new(), 0, StatusCode::EVMC_OUT_OF_GAS);
+    }
+    let gas_left = gas - cost;
+    if caller.read_only {
+        return (Vec::new(), gas_left, StatusCode::EVMC_SUCCESS);
+    }
+
+    let origin = T::ConvertAddress::convert_address(&caller.source);
+    let result = match message {
+        XcmMessage::ReserveTransfer {
+            dest,
+            beneficiary,
+            amount,
+        } => T::XcmSender::reserve_transfer(&origin, dest, beneficiary, amount),
+        XcmMessage::Transact { dest, call, weight } => {
+            T::XcmSender::transact(&origin, dest, call, weight)
+        }
+    };
+    match result {
+        Ok(_) => (Vec::new(), gas_left, StatusCode::EVMC_SUCCESS),
+        Err(_) => revert(gas_left),
+    }
+}
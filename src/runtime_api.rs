@@ -0,0 +1,127 @@
+// Copyright (C) 2020 Second State.
+// This file is part of Pallet-SSVM.
+
+// Pallet-SSVM is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+
+// Pallet-SSVM is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+//! Runtime API of the SSVM module.
+
+use crate::{
+    AbiType, Account, AccountDump, AccountInfo, BlockContext, BlockGasStats, CallInfo,
+    EthBlockHeader, ExecutionMetrics, ExecutionOutput, FeeHistory, Log, StateDump, StateOverride,
+    StorageRange, TransactionStatus,
+};
+use sp_core::{H160, H256, U256};
+use sp_runtime::{DispatchError, Permill};
+use sp_std::vec::Vec;
+
+sp_api::decl_runtime_apis! {
+    /// API for querying SSVM state and executing read-only calls.
+    ///
+    /// Queries never change state, so the node may serve them concurrently.
+    pub trait SsvmApi {
+        /// Execute a call without changing state.
+        fn call(
+            from: H160,
+            to: H160,
+            data: Vec<u8>,
+            value: U256,
+            gas_limit: u32,
+            gas_price: U256,
+        ) -> Result<CallInfo, DispatchError>;
+        /// Execute a call, or a contract creation if `to` is `None`, after
+        /// applying state overrides of accounts, without changing state.
+        fn call_with_overrides(
+            from: H160,
+            to: Option<H160>,
+            data: Vec<u8>,
+            value: U256,
+            gas_limit: u32,
+            gas_price: U256,
+            overrides: Vec<(H160, StateOverride)>,
+        ) -> Result<CallInfo, DispatchError>;
+        /// Execute a call without changing state, and decode its output
+        /// against `output_types`, e.g. for explorers showing typed values.
+        fn call_decoded(
+            from: H160,
+            to: H160,
+            data: Vec<u8>,
+            value: U256,
+            gas_limit: u32,
+            gas_price: U256,
+            output_types: Vec<AbiType>,
+        ) -> Result<ExecutionOutput, DispatchError>;
+        /// Execute a contract creation without changing state.
+        fn create(
+            from: H160,
+            data: Vec<u8>,
+            value: U256,
+            gas_limit: u32,
+            gas_price: U256,
+        ) -> Result<CallInfo, DispatchError>;
+        /// Estimate the gas used by a call, or a contract creation if `to` is `None`.
+        fn estimate_gas(
+            from: H160,
+            to: Option<H160>,
+            data: Vec<u8>,
+            value: U256,
+            gas_price: U256,
+        ) -> Result<U256, DispatchError>;
+        /// Dump accounts with their code and storage, in the format accepted
+        /// by `import_state`.
+        fn export_accounts(addresses: Vec<H160>) -> Vec<(H160, AccountDump)>;
+        /// Dump up to `limit` accounts of the whole state, starting at `start`.
+        /// Continue with the returned `next` address until it is `None`.
+        fn export_state(start: Option<H160>, limit: u32) -> StateDump;
+        /// Gas statistics of up to `count` most recent blocks, newest first.
+        fn gas_stats(count: u32) -> Vec<BlockGasStats>;
+        /// Fee history of up to `block_count` blocks ending at `newest`, as
+        /// returned by `eth_feeHistory`.
+        fn fee_history(block_count: u32, newest: u64, percentiles: Vec<Permill>) -> FeeHistory;
+        /// Block number and extrinsic index of an Ethereum transaction by hash.
+        fn transaction_by_hash(hash: H256) -> Option<(u64, u32)>;
+        /// Up to `limit` storage slots of a contract, starting at the key
+        /// `start`. Continue with the returned `next_key` until it is `None`.
+        fn storage_range_at(address: H160, start: Option<H256>, limit: u32) -> StorageRange;
+        /// Nonce and balance of an account.
+        fn account_basic(address: H160) -> Account;
+        /// Nonce, balance and code hash of an account.
+        fn account_info(address: H160) -> AccountInfo;
+        /// Code of an account, empty if it has none.
+        fn account_code_at(address: H160) -> Vec<u8>;
+        /// Value of a contract storage slot.
+        fn storage_at(address: H160, key: H256) -> H256;
+        /// Chain id used for EIP-155 replay protection.
+        fn chain_id() -> u64;
+        /// Minimum gas price accepted by the module in the current block.
+        fn min_gas_price() -> U256;
+        /// Logs emitted in block `number`, with the index of the extrinsic
+        /// that emitted them, while the block is in the retention period.
+        fn block_logs(number: u64) -> Vec<(u32, Log)>;
+        /// Ethereum header of block `number`.
+        fn eth_block_by_number(number: u64) -> Option<EthBlockHeader>;
+        /// Ethereum header of a block by its hash.
+        fn eth_block_by_hash(hash: H256) -> Option<EthBlockHeader>;
+        /// Hashes of the Ethereum transactions ingested and SSVM transactions
+        /// executed in block `number`, while the block is in the retention
+        /// period.
+        fn block_transaction_hashes(number: u64) -> Vec<H256>;
+        /// Status of an SSVM transaction by hash, while its block is in the
+        /// retention period.
+        fn transaction_status(hash: H256) -> Option<TransactionStatus>;
+        /// Block fields contracts saw in block `number`.
+        fn block_context(number: u64) -> Option<BlockContext>;
+        /// Execution metrics of the last finalized block.
+        fn last_block_metrics() -> ExecutionMetrics;
+    }
+}
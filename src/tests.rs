@@ -0,0 +1,1052 @@
+// Copyright (C) 2020 Second State.
+// This file is part of Pallet-SSVM.
+
+// Pallet-SSVM is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+
+// Pallet-SSVM is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+//! Tests of the module against Ewasm contracts written in WAT.
+
+use crate::conformance::{self, Fixture};
+use crate::executor::{self, ssvm_runtime_interface, INTERFACE_VERSION};
+use crate::mock::*;
+use crate::{
+    create_address, Account, Accounts, BalanceConverter, ConvertAddress, ExecutionRequest,
+    FeeConverter, HashedConvertAddress, Message, ScaledBalanceConverter, StateOverride, TxContext,
+    VmContext, ERC20_PRECOMPILE,
+};
+use codec::{Decode, Encode};
+use frame_support::traits::{Get, OnInitialize};
+use frame_support::{assert_noop, assert_ok, StorageMap};
+use frame_system::RawOrigin;
+use sp_core::{Blake2Hasher, H160, H256, U256};
+use sp_runtime::DispatchResult;
+use ssvm::types::{CallKind, StatusCode};
+
+/// Gas limit of test transactions.
+const GAS_LIMIT: u32 = 1_000_000;
+
+/// Memory layout of the test contracts.
+const CALLER_WORD: u32 = 0;
+const ORIGIN_WORD: u32 = 32;
+const SLOT_0: u32 = 64;
+const SLOT_1: u32 = 96;
+const TARGET: u32 = 128;
+const VALUE: u32 = 160;
+const INPUT: u32 = 192;
+const SLOT_2: u32 = 1024;
+const CALL_VALUE_WORD: u32 = 1056;
+
+/// WAT string literal of `bytes`.
+fn wat_bytes(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("\\{:02x}", b)).collect()
+}
+
+/// Assemble an Ewasm contract whose `main` runs `body` over the test memory
+/// layout, with `TARGET` holding `target` and `INPUT` holding `input`.
+fn contract(target: H160, input: &[u8], body: &str) -> Vec<u8> {
+    let mut slot_1 = [0u8; 32];
+    slot_1[31] = 1;
+    let source = format!(
+        r#"(module
+            (import "ethereum" "getCaller" (func $getCaller (param i32)))
+            (import "ethereum" "getTxOrigin" (func $getTxOrigin (param i32)))
+            (import "ethereum" "getCallValue" (func $getCallValue (param i32)))
+            (import "ethereum" "storageStore" (func $storageStore (param i32 i32)))
+            (import "ethereum" "call" (func $call (param i64 i32 i32 i32 i32) (result i32)))
+            (import "ethereum" "callStatic" (func $callStatic (param i64 i32 i32 i32) (result i32)))
+            (import "ethereum" "getGasLeft" (func $getGasLeft (result i64)))
+            (import "ethereum" "create" (func $create (param i32 i32 i32 i32) (result i32)))
+            (import "ethereum" "selfDestruct" (func $selfDestruct (param i32)))
+            (import "ethereum" "revert" (func $revert (param i32 i32)))
+            (memory 1)
+            (data (i32.const {slot_1}) "{slot_1_bytes}")
+            (data (i32.const {target}) "{target_bytes}")
+            (data (i32.const {input}) "{input_bytes}")
+            (export "memory" (memory 0))
+            (export "main" (func $main))
+            (func $main {body}))"#,
+        slot_1 = SLOT_1,
+        slot_1_bytes = wat_bytes(&slot_1),
+        target = TARGET,
+        target_bytes = wat_bytes(target.as_bytes()),
+        input = INPUT,
+        input_bytes = wat_bytes(input),
+        body = body,
+    );
+    wat::parse_str(source).expect("test contracts are valid WAT")
+}
+
+/// Contract storing its caller in slot 0 and the transaction origin in slot
+/// 1, then calling `next` if given.
+fn recorder(next: Option<H160>) -> Vec<u8> {
+    forwarder(next, 0)
+}
+
+/// `recorder` that also stores its call value in slot 2 as the 128 bits
+/// little endian Ewasm value, and calls `next` with `value`.
+fn forwarder(next: Option<H160>, value: u64) -> Vec<u8> {
+    let call = match next {
+        Some(_) => format!(
+            "(i64.store (i32.const {}) (i64.const {}))
+             (drop (call $call (i64.div_u (call $getGasLeft) (i64.const 2)) (i32.const {}) (i32.const {}) (i32.const 0) (i32.const 0)))",
+            VALUE, value, TARGET, VALUE,
+        ),
+        None => String::new(),
+    };
+    let body = format!(
+        "(call $getCaller (i32.const {caller}))
+         (call $storageStore (i32.const {slot_0}) (i32.const {caller_word}))
+         (call $getTxOrigin (i32.const {origin}))
+         (call $storageStore (i32.const {slot_1}) (i32.const {origin_word}))
+         (i32.store8 (i32.const {slot_2_key}) (i32.const 2))
+         (call $getCallValue (i32.const {call_value}))
+         (call $storageStore (i32.const {slot_2}) (i32.const {call_value}))
+         {call}",
+        caller = CALLER_WORD + 12,
+        caller_word = CALLER_WORD,
+        origin = ORIGIN_WORD + 12,
+        origin_word = ORIGIN_WORD,
+        slot_0 = SLOT_0,
+        slot_1 = SLOT_1,
+        slot_2_key = SLOT_2 + 31,
+        slot_2 = SLOT_2,
+        call_value = CALL_VALUE_WORD,
+        call = call,
+    );
+    contract(next.unwrap_or_default(), &[], &body)
+}
+
+/// Contract calling `target` with `input`, statically if `is_static`, and
+/// storing the status of the call in slot 0.
+fn caller_of(target: H160, input: &[u8], is_static: bool) -> Vec<u8> {
+    let call = if is_static {
+        format!(
+            "(call $callStatic (i64.div_u (call $getGasLeft) (i64.const 2)) (i32.const {}) (i32.const {}) (i32.const {}))",
+            TARGET, INPUT, input.len(),
+        )
+    } else {
+        format!(
+            "(call $call (i64.div_u (call $getGasLeft) (i64.const 2)) (i32.const {}) (i32.const {}) (i32.const {}) (i32.const {}))",
+            TARGET, VALUE, INPUT, input.len(),
+        )
+    };
+    let body = format!(
+        "(i32.store8 (i32.const {status}) {call})
+         (call $storageStore (i32.const {slot_0}) (i32.const {caller_word}))",
+        status = CALLER_WORD + 31,
+        call = call,
+        slot_0 = SLOT_0,
+        caller_word = CALLER_WORD,
+    );
+    contract(target, input, &body)
+}
+
+/// Contract storing its caller in slot 0, then self-destructing in favour of
+/// `beneficiary`.
+fn destroyer(beneficiary: H160) -> Vec<u8> {
+    let body = format!(
+        "(call $getCaller (i32.const {caller}))
+         (call $storageStore (i32.const {slot_0}) (i32.const {caller_word}))
+         (call $selfDestruct (i32.const {target}))",
+        caller = CALLER_WORD + 12,
+        caller_word = CALLER_WORD,
+        slot_0 = SLOT_0,
+        target = TARGET,
+    );
+    contract(beneficiary, &[], &body)
+}
+
+/// Contract calling `target`, then reverting.
+fn reverter_of(target: H160) -> Vec<u8> {
+    let body = format!(
+        "(drop (call $call (i64.div_u (call $getGasLeft) (i64.const 2)) (i32.const {}) (i32.const {}) (i32.const 0) (i32.const 0)))
+         (call $revert (i32.const 0) (i32.const 0))",
+        TARGET, VALUE,
+    );
+    contract(target, &[], &body)
+}
+
+/// Init code deploying `runtime`.
+fn init_code(runtime: &[u8]) -> Vec<u8> {
+    let source = format!(
+        r#"(module
+            (import "ethereum" "finish" (func $finish (param i32 i32)))
+            (memory 1)
+            (data (i32.const 0) "{runtime}")
+            (export "memory" (memory 0))
+            (export "main" (func $main))
+            (func $main (call $finish (i32.const 0) (i32.const {len}))))"#,
+        runtime = wat_bytes(runtime),
+        len = runtime.len(),
+    );
+    wat::parse_str(source).expect("test contracts are valid WAT")
+}
+
+/// Init code storing 1 in slot 0, then ending with `end`, e.g. a revert or a
+/// trap.
+fn failing_init_code(end: &str) -> Vec<u8> {
+    let source = format!(
+        r#"(module
+            (import "ethereum" "storageStore" (func $storageStore (param i32 i32)))
+            (import "ethereum" "revert" (func $revert (param i32 i32)))
+            (memory 1)
+            (data (i32.const 63) "\01")
+            (export "memory" (memory 0))
+            (export "main" (func $main))
+            (func $main
+                (call $storageStore (i32.const 0) (i32.const 32))
+                {end}))"#,
+        end = end,
+    );
+    wat::parse_str(source).expect("test contracts are valid WAT")
+}
+
+/// Contract creating a contract with `init_code`, storing the status of the
+/// creation in slot 0 and the created address in slot 1.
+fn factory(init_code: &[u8]) -> Vec<u8> {
+    let body = format!(
+        "(i32.store8 (i32.const {status}) (call $create (i32.const {value}) (i32.const {input}) (i32.const {len}) (i32.const {created})))
+         (call $storageStore (i32.const {slot_0}) (i32.const {caller_word}))
+         (call $storageStore (i32.const {slot_1}) (i32.const {origin_word}))",
+        status = CALLER_WORD + 31,
+        value = VALUE,
+        input = INPUT,
+        len = init_code.len(),
+        created = ORIGIN_WORD + 12,
+        slot_0 = SLOT_0,
+        slot_1 = SLOT_1,
+        caller_word = CALLER_WORD,
+        origin_word = ORIGIN_WORD,
+    );
+    contract(H160::zero(), init_code, &body)
+}
+
+/// Input of the ERC-20 `transfer(to, amount)`.
+fn transfer_input(to: &H160, amount: U256) -> Vec<u8> {
+    let mut input = vec![0xa9, 0x05, 0x9c, 0xbb];
+    input.extend_from_slice(H256::from(*to).as_bytes());
+    let mut word = [0u8; 32];
+    amount.to_big_endian(&mut word);
+    input.extend_from_slice(&word);
+    input
+}
+
+fn deploy(address: H160, code: Vec<u8>) {
+    SSVM::store_code(&address, code);
+}
+
+fn contract_address(n: u8) -> H160 {
+    let mut address = [0xc0u8; 20];
+    address[19] = n;
+    H160::from(address)
+}
+
+fn call(target: H160) {
+    call_from(ALICE, target)
+}
+
+fn call_from(sender: u64, target: H160) {
+    assert_ok!(SSVM::call(
+        Origin::signed(sender),
+        address_of(sender),
+        target,
+        Vec::new(),
+        U256::zero(),
+        GAS_LIMIT,
+        U256::zero(),
+    ));
+}
+
+/// Storage word of a call value stored by `forwarder`.
+fn call_value_word(value: u64) -> H256 {
+    let mut word = H256::zero();
+    word.as_bytes_mut()[..8].copy_from_slice(&value.to_le_bytes());
+    word
+}
+
+#[test]
+fn nested_calls_see_immediate_caller_transaction_origin_and_value() {
+    new_test_ext().execute_with(|| {
+        let (a, b, c) = (
+            contract_address(1),
+            contract_address(2),
+            contract_address(3),
+        );
+        deploy(c, forwarder(None, 0));
+        deploy(b, forwarder(Some(c), 100));
+        deploy(a, forwarder(Some(b), 200));
+
+        assert_ok!(SSVM::call(
+            Origin::signed(ALICE),
+            address_of(ALICE),
+            a,
+            Vec::new(),
+            U256::from(300),
+            GAS_LIMIT,
+            U256::zero(),
+        ));
+
+        let origin = H256::from(address_of(ALICE));
+        for (contract, caller, value) in &[(a, address_of(ALICE), 300), (b, a, 200), (c, b, 100)] {
+            assert_eq!(
+                SSVM::storage_at(contract, &H256::zero()),
+                H256::from(*caller)
+            );
+            assert_eq!(
+                SSVM::storage_at(contract, &H256::from_low_u64_be(1)),
+                origin
+            );
+            assert_eq!(
+                SSVM::storage_at(contract, &H256::from_low_u64_be(2)),
+                call_value_word(*value)
+            );
+            assert_eq!(SSVM::balance_of(contract), U256::from(100));
+        }
+        assert_eq!(
+            SSVM::balance_of(&address_of(ALICE)),
+            U256::exp10(18) - U256::from(300)
+        );
+    });
+}
+
+#[test]
+fn nested_call_fails_without_the_balance_for_its_value() {
+    new_test_ext().execute_with(|| {
+        let (a, b) = (contract_address(1), contract_address(2));
+        deploy(b, forwarder(None, 0));
+        deploy(a, forwarder(Some(b), 200));
+
+        call(a);
+
+        assert_eq!(SSVM::storage_at(&b, &H256::zero()), H256::zero());
+        assert_eq!(SSVM::balance_of(&a), U256::zero());
+        assert_eq!(SSVM::balance_of(&b), U256::zero());
+    });
+}
+
+#[test]
+fn account_fields_are_read_at_their_encoded_offsets() {
+    new_test_ext().execute_with(|| {
+        let account = Account {
+            nonce: U256::from(7),
+            balance: U256::max_value() - U256::from(1),
+        };
+        let encoded = account.encode();
+        assert_eq!(encoded.len(), 64);
+        assert_eq!(&encoded[..32], &account.nonce.encode()[..]);
+        assert_eq!(&encoded[32..], &account.balance.encode()[..]);
+
+        let address = contract_address(1);
+        Accounts::insert(address, account.clone());
+
+        assert_eq!(SSVM::nonce_of(&address), account.nonce);
+        assert_eq!(SSVM::balance_of(&address), account.balance);
+        assert_eq!(SSVM::nonce_of(&contract_address(2)), U256::zero());
+        assert_eq!(SSVM::balance_of(&contract_address(2)), U256::zero());
+    });
+}
+
+#[test]
+fn precompile_transfer_succeeds_in_plain_call() {
+    new_test_ext().execute_with(|| {
+        let contract = contract_address(1);
+        let input = transfer_input(&address_of(BOB), U256::from(1));
+        deploy(contract, caller_of(ERC20_PRECOMPILE, &input, false));
+        Accounts::insert(
+            contract,
+            Account {
+                nonce: U256::zero(),
+                balance: U256::from(10),
+            },
+        );
+
+        call(contract);
+
+        assert_eq!(SSVM::storage_at(&contract, &H256::zero()), H256::zero());
+        assert_eq!(SSVM::balance_of(&contract), U256::from(9));
+        assert_eq!(
+            SSVM::balance_of(&address_of(BOB)),
+            U256::exp10(18) + U256::from(1)
+        );
+    });
+}
+
+#[test]
+fn precompile_transfer_fails_in_static_call() {
+    new_test_ext().execute_with(|| {
+        let contract = contract_address(1);
+        let input = transfer_input(&address_of(BOB), U256::from(1));
+        deploy(contract, caller_of(ERC20_PRECOMPILE, &input, true));
+        Accounts::insert(
+            contract,
+            Account {
+                nonce: U256::zero(),
+                balance: U256::from(10),
+            },
+        );
+
+        call(contract);
+
+        // Ewasm call status 1 is a failure.
+        assert_eq!(
+            SSVM::storage_at(&contract, &H256::zero()),
+            H256::from_low_u64_be(1)
+        );
+        assert_eq!(SSVM::balance_of(&contract), U256::from(10));
+        assert_eq!(SSVM::balance_of(&address_of(BOB)), U256::exp10(18));
+    });
+}
+
+#[test]
+fn rounded_fee_is_capped_at_payer_balance() {
+    new_test_ext().execute_with(|| {
+        let payer = contract_address(1);
+        Accounts::insert(
+            payer,
+            Account {
+                nonce: U256::zero(),
+                balance: U256::from(1_000_005),
+            },
+        );
+
+        // 1_500_000 rounds up to 2_000_000, a whole unit of the currency.
+        assert_eq!(SSVM::fee(1, U256::from(1_500_000)), U256::from(2_000_000));
+        assert_eq!(
+            SSVM::charge_fee(&payer, 1, U256::from(1_500_000)),
+            U256::from(1_000_005)
+        );
+        assert_eq!(SSVM::balance_of(&payer), U256::zero());
+    });
+}
+
+#[test]
+fn executor_fails_requests_of_other_runtime_versions() {
+    new_test_ext().execute_with(|| {
+        let request = |spec_version| ExecutionRequest::Create {
+            spec_version,
+            source: address_of(ALICE),
+            code: recorder(None),
+            value: U256::zero(),
+            gas_limit: GAS_LIMIT,
+            gas_price: U256::zero(),
+            bond_payer: Some(ALICE.encode()),
+        };
+        let execute = |request: ExecutionRequest| {
+            DispatchResult::decode(&mut &SSVM::execute_request(&request.encode())[..]).unwrap()
+        };
+
+        assert!(execute(request(SSVM::spec_version() + 1)).is_err());
+        assert_eq!(SSVM::nonce_of(&address_of(ALICE)), U256::zero());
+        assert_ok!(execute(request(SSVM::spec_version())));
+        assert_eq!(SSVM::nonce_of(&address_of(ALICE)), U256::one());
+    });
+}
+
+#[test]
+fn contracts_create_contracts_at_the_address_of_their_nonce() {
+    new_test_ext().execute_with(|| {
+        let factory_address = contract_address(1);
+        let runtime = recorder(None);
+        deploy(factory_address, factory(&init_code(&runtime)));
+
+        call(factory_address);
+
+        let created = create_address(factory_address, U256::zero());
+        assert_eq!(
+            SSVM::storage_at(&factory_address, &H256::zero()),
+            H256::zero()
+        );
+        assert_eq!(
+            SSVM::storage_at(&factory_address, &H256::from_low_u64_be(1)),
+            H256::from(created)
+        );
+        assert_eq!(SSVM::account_code(&created), runtime);
+        assert_eq!(SSVM::nonce_of(&factory_address), U256::one());
+    });
+}
+
+#[test]
+fn nested_creation_fails_on_reused_nonce() {
+    new_test_ext().execute_with(|| {
+        let factory_address = contract_address(1);
+        deploy(factory_address, factory(&init_code(&recorder(None))));
+        // An imported account already lives at the address of nonce 0.
+        let existing = create_address(factory_address, U256::zero());
+        Accounts::insert(
+            existing,
+            Account {
+                nonce: U256::one(),
+                balance: U256::from(5),
+            },
+        );
+
+        call(factory_address);
+
+        // Ewasm create status 1 is a failure.
+        assert_eq!(
+            SSVM::storage_at(&factory_address, &H256::zero()),
+            H256::from_low_u64_be(1)
+        );
+        assert!(SSVM::account_code(&existing).is_empty());
+        assert_eq!(SSVM::nonce_of(&existing), U256::one());
+        assert_eq!(SSVM::balance_of(&existing), U256::from(5));
+        assert_eq!(SSVM::nonce_of(&factory_address), U256::one());
+    });
+}
+
+#[test]
+fn nested_create2_fails() {
+    new_test_ext().execute_with(|| {
+        let factory_address = contract_address(1);
+        let message = Message {
+            kind: CallKind::EVMC_CREATE2,
+            sender: factory_address,
+            target: H160::zero(),
+            code_address: H160::zero(),
+            value: U256::zero(),
+            input: init_code(&recorder(None)),
+            gas: GAS_LIMIT,
+            depth: 1,
+            is_static: false,
+        };
+
+        // The host interface does not pass the salt of CREATE2.
+        let (_, gas_left, created, status_code) =
+            SSVM::execute_nested_create(vm_context(factory_address).tx_context, message, false);
+
+        assert!(status_code == StatusCode::EVMC_FAILURE);
+        assert_eq!(gas_left, GAS_LIMIT as i64);
+        assert_eq!(created, H160::zero());
+        assert_eq!(SSVM::nonce_of(&factory_address), U256::zero());
+    });
+}
+
+#[test]
+fn creation_fails_on_reused_nonce() {
+    new_test_ext().execute_with(|| {
+        let existing = create_address(address_of(ALICE), U256::zero());
+        let code = vec![1, 2, 3];
+        SSVM::store_code(&existing, code.clone());
+
+        assert_ok!(SSVM::create(
+            Origin::signed(ALICE),
+            address_of(ALICE),
+            init_code(&recorder(None)),
+            U256::zero(),
+            GAS_LIMIT,
+            U256::zero(),
+        ));
+
+        assert_eq!(SSVM::account_code(&existing), code);
+        assert_eq!(SSVM::nonce_of(&address_of(ALICE)), U256::one());
+    });
+}
+
+#[test]
+fn failed_constructors_roll_back_storage_and_endowment() {
+    for end in &[
+        "(call $revert (i32.const 0) (i32.const 0))",
+        "(unreachable)",
+    ] {
+        new_test_ext().execute_with(|| {
+            let created = create_address(address_of(ALICE), U256::zero());
+
+            assert_ok!(SSVM::create(
+                Origin::signed(ALICE),
+                address_of(ALICE),
+                failing_init_code(end),
+                U256::from(1_000),
+                GAS_LIMIT,
+                U256::zero(),
+            ));
+
+            assert_eq!(SSVM::storage_at(&created, &H256::zero()), H256::zero());
+            assert!(SSVM::account_code(&created).is_empty());
+            assert_eq!(SSVM::balance_of(&created), U256::zero());
+            assert_eq!(SSVM::balance_of(&address_of(ALICE)), U256::exp10(18));
+            assert_eq!(SSVM::nonce_of(&address_of(ALICE)), U256::one());
+        });
+    }
+}
+
+#[test]
+fn state_overrides_only_last_for_the_dry_run() {
+    new_test_ext().execute_with(|| {
+        let contract = contract_address(1);
+        let runtime = recorder(None);
+        deploy(contract, runtime.clone());
+        Accounts::insert(
+            contract,
+            Account {
+                nonce: U256::one(),
+                balance: U256::from(5),
+            },
+        );
+        let (kept, added) = (H256::from_low_u64_be(1), H256::from_low_u64_be(2));
+        SSVM::write_storage(&contract, &kept, &H256::from_low_u64_be(7));
+        let state = vec![(added, H256::from_low_u64_be(9))]
+            .into_iter()
+            .collect();
+        let overrides = vec![(
+            contract,
+            StateOverride {
+                balance: Some(U256::from(100)),
+                nonce: Some(U256::from(3)),
+                code: Some(caller_of(ERC20_PRECOMPILE, &[], false)),
+                state: Some(state),
+                ..Default::default()
+            },
+        )];
+
+        assert_ok!(SSVM::dry_run_with_overrides(
+            address_of(ALICE),
+            Some(contract),
+            Vec::new(),
+            U256::zero(),
+            GAS_LIMIT,
+            U256::zero(),
+            overrides,
+        ));
+
+        assert_eq!(SSVM::balance_of(&contract), U256::from(5));
+        assert_eq!(SSVM::nonce_of(&contract), U256::one());
+        assert_eq!(SSVM::account_code(&contract), runtime);
+        assert_eq!(SSVM::storage_at(&contract, &kept), H256::from_low_u64_be(7));
+        assert_eq!(SSVM::storage_at(&contract, &added), H256::zero());
+    });
+}
+
+#[test]
+fn block_hooks_run_within_the_initialize_weight() {
+    new_test_ext().execute_with(|| {
+        let (small, large) = (contract_address(1), contract_address(2));
+        deploy(small, recorder(None));
+        deploy(large, recorder(None));
+        let selector = [0u8; 4];
+        // The gas limit of the second hook exceeds `MaxInitializeWeight`.
+        for (contract, gas) in &[(small, 100_000), (large, 2_000_000)] {
+            assert_ok!(SSVM::register_block_hook(
+                Origin::signed(ALICE),
+                address_of(ALICE),
+                *contract,
+                selector,
+                *gas,
+            ));
+            assert_ok!(SSVM::approve_block_hook(
+                RawOrigin::Root.into(),
+                *contract,
+                selector
+            ));
+        }
+
+        let weight = SSVM::on_initialize(2);
+
+        assert!(weight > 0 && weight <= MaxInitializeWeight::get());
+        assert_ne!(SSVM::storage_at(&small, &H256::zero()), H256::zero());
+        assert_eq!(SSVM::storage_at(&large, &H256::zero()), H256::zero());
+    });
+}
+
+/// Create a contract running `runtime` from `ALICE`, returning its address.
+fn create(runtime: &[u8]) -> H160 {
+    let address = create_address(address_of(ALICE), SSVM::nonce_of(&address_of(ALICE)));
+    assert_ok!(SSVM::create(
+        Origin::signed(ALICE),
+        address_of(ALICE),
+        init_code(runtime),
+        U256::zero(),
+        GAS_LIMIT,
+        U256::zero(),
+    ));
+    address
+}
+
+#[test]
+fn creation_reserves_the_bond_of_the_deployed_code() {
+    new_test_ext().execute_with(|| {
+        // 100_000 units of the currency per byte. `ALICE` can afford the
+        // bond of the code, but not the bond of `MaxCodeSize` bytes.
+        set_contract_bond_per_byte(U256::exp10(11));
+        let runtime = recorder(None);
+
+        let created = create(&runtime);
+
+        assert_eq!(SSVM::account_code(&created), runtime);
+        assert_eq!(
+            Balances::reserved_balance(ALICE),
+            runtime.len() as u64 * 100_000
+        );
+    });
+}
+
+#[test]
+fn creation_fails_if_the_bond_can_not_be_reserved() {
+    new_test_ext().execute_with(|| {
+        // The bond of the code exceeds the balance of `ALICE`.
+        set_contract_bond_per_byte(U256::exp10(15));
+
+        let created = create(&recorder(None));
+
+        assert!(SSVM::account_code(&created).is_empty());
+        assert_eq!(SSVM::contract_bond(&created), None);
+        assert_eq!(Balances::reserved_balance(ALICE), 0);
+        assert_eq!(SSVM::nonce_of(&address_of(ALICE)), U256::one());
+    });
+}
+
+#[test]
+fn bond_is_reserved_from_and_released_to_the_signer() {
+    new_test_ext().execute_with(|| {
+        // Addresses act on other modules as hashed accounts, so the signer
+        // is not the account of its address.
+        let mapped: u64 =
+            <HashedConvertAddress<Blake2Hasher> as ConvertAddress<u64>>::convert_address(
+                &address_of(ALICE),
+            );
+        assert_ne!(mapped, ALICE);
+        set_contract_bond_per_byte(U256::exp10(6));
+        let runtime = destroyer(address_of(BOB));
+
+        let created = create(&runtime);
+
+        assert_eq!(Balances::reserved_balance(ALICE), runtime.len() as u64);
+        assert_eq!(Balances::reserved_balance(mapped), 0);
+        assert_eq!(
+            SSVM::contract_bond(&created),
+            Some((
+                address_of(ALICE),
+                ALICE,
+                U256::from(runtime.len()) * U256::exp10(6)
+            ))
+        );
+
+        call(created);
+
+        assert!(SSVM::account_code(&created).is_empty());
+        assert_eq!(SSVM::contract_bond(&created), None);
+        assert_eq!(Balances::reserved_balance(ALICE), 0);
+    });
+}
+
+#[test]
+fn nested_creation_reserves_the_bond_from_the_payer_of_the_creator() {
+    new_test_ext().execute_with(|| {
+        set_contract_bond_per_byte(U256::exp10(6));
+        let runtime = recorder(None);
+        let factory_code = factory(&init_code(&runtime));
+        let factory_address = create(&factory_code);
+
+        call(factory_address);
+
+        let created = create_address(factory_address, U256::zero());
+        assert_eq!(SSVM::account_code(&created), runtime);
+        assert_eq!(
+            SSVM::contract_bond(&created),
+            Some((
+                factory_address,
+                ALICE,
+                U256::from(runtime.len()) * U256::exp10(6)
+            ))
+        );
+        assert_eq!(
+            Balances::reserved_balance(ALICE),
+            (factory_code.len() + runtime.len()) as u64
+        );
+    });
+}
+
+#[test]
+fn nested_creation_fails_without_a_bond_payer() {
+    new_test_ext().execute_with(|| {
+        set_contract_bond_per_byte(U256::exp10(6));
+        let factory_address = contract_address(1);
+        deploy(factory_address, factory(&init_code(&recorder(None))));
+
+        call(factory_address);
+
+        // Ewasm create status 1 is a failure.
+        assert_eq!(
+            SSVM::storage_at(&factory_address, &H256::zero()),
+            H256::from_low_u64_be(1)
+        );
+        let created = create_address(factory_address, U256::zero());
+        assert!(SSVM::account_code(&created).is_empty());
+    });
+}
+
+#[test]
+fn reverted_nested_creation_releases_its_bond() {
+    new_test_ext().execute_with(|| {
+        set_contract_bond_per_byte(U256::exp10(6));
+        let factory_code = factory(&init_code(&recorder(None)));
+        let factory_address = create(&factory_code);
+        let reverter = contract_address(1);
+        deploy(reverter, reverter_of(factory_address));
+
+        call(reverter);
+
+        let created = create_address(factory_address, U256::zero());
+        assert!(SSVM::account_code(&created).is_empty());
+        assert_eq!(SSVM::contract_bond(&created), None);
+        assert_eq!(Balances::reserved_balance(ALICE), factory_code.len() as u64);
+    });
+}
+
+/// Context of a top level call of `target` by `ALICE`.
+fn vm_context(target: H160) -> VmContext {
+    VmContext {
+        version: INTERFACE_VERSION,
+        tx_context: TxContext::new(
+            U256::zero(),
+            address_of(ALICE),
+            H160::zero(),
+            1,
+            0,
+            u32::max_value() as u64,
+            U256::zero(),
+            U256::from(ChainId::get()),
+        ),
+        kind: 0,
+        sender: address_of(ALICE),
+        target,
+        value: U256::zero(),
+        gas: GAS_LIMIT,
+        depth: 0,
+        is_static: false,
+        read_only: false,
+    }
+}
+
+#[test]
+fn fixtures_conform_across_execution_strategies() {
+    let contract = contract_address(1);
+    let transfer = transfer_input(&address_of(BOB), U256::from(1));
+    let fixtures = vec![
+        Fixture::new(
+            "store caller and origin",
+            recorder(None),
+            Vec::new(),
+            &vm_context(contract),
+        ),
+        Fixture::new(
+            "static precompile transfer",
+            caller_of(ERC20_PRECOMPILE, &transfer, true),
+            Vec::new(),
+            &vm_context(contract),
+        ),
+        Fixture::new(
+            "invalid code",
+            vec![0, 1, 2, 3],
+            Vec::new(),
+            &vm_context(contract),
+        ),
+    ];
+    executor::register::<Test>();
+
+    for fixture in fixtures {
+        let mismatches = conformance::run(
+            &fixture,
+            new_test_ext,
+            SSVM::execute_vm,
+            ssvm_runtime_interface::execute,
+        );
+        assert!(mismatches.is_empty(), "{}: {:?}", fixture.name, mismatches);
+    }
+}
+
+type Converter = ScaledBalanceConverter<BalanceScale>;
+type BalancesError = pallet_balances::Error<Test, pallet_balances::DefaultInstance>;
+
+fn evm_balance(id: u64) -> U256 {
+    Accounts::get(address_of(id)).balance
+}
+
+#[test]
+fn scaled_conversion_keeps_dust_out_of_the_currency() {
+    assert_eq!(
+        <Converter as BalanceConverter<u64>>::into_evm(3),
+        Some(U256::from(3_000_000))
+    );
+    assert_eq!(
+        <Converter as BalanceConverter<u64>>::from_evm(U256::from(3_000_999)),
+        Some((3, U256::from(999)))
+    );
+    assert_eq!(
+        <Converter as BalanceConverter<u64>>::from_evm(U256::from(999)),
+        Some((0, U256::from(999)))
+    );
+    assert_eq!(
+        <Converter as FeeConverter<u64>>::into_native(U256::from(1)),
+        Some(1)
+    );
+}
+
+#[test]
+fn deposit_is_scaled_into_the_evm_balance() {
+    new_test_ext().execute_with(|| {
+        assert_ok!(SSVM::deposit_balance(Origin::signed(ALICE), 100));
+
+        assert_eq!(Balances::free_balance(ALICE), 1_000_000_000 - 100);
+        assert_eq!(Balances::free_balance(SSVM::account_id()), 100);
+        assert_eq!(
+            evm_balance(ALICE),
+            U256::exp10(18) + U256::from(100_000_000)
+        );
+    });
+}
+
+#[test]
+fn deposit_fails_if_it_leaves_dust_below_the_existential_deposit() {
+    new_test_ext().execute_with(|| {
+        assert_noop!(
+            SSVM::deposit_balance(Origin::signed(ALICE), 1_000_000_000 - 5),
+            BalancesError::KeepAlive
+        );
+    });
+}
+
+#[test]
+fn deposit_of_the_whole_balance_reaps_the_sender() {
+    new_test_ext().execute_with(|| {
+        assert_ok!(SSVM::deposit_balance(Origin::signed(ALICE), 1_000_000_000));
+
+        assert_eq!(Balances::free_balance(ALICE), 0);
+        assert_eq!(Balances::free_balance(SSVM::account_id()), 1_000_000_000);
+        assert_eq!(
+            evm_balance(ALICE),
+            U256::exp10(18) + U256::from(1_000_000_000) * U256::exp10(6)
+        );
+    });
+}
+
+#[test]
+fn withdrawal_leaves_dust_in_the_evm_account() {
+    new_test_ext().execute_with(|| {
+        assert_ok!(SSVM::deposit_balance(Origin::signed(BOB), 1_000));
+        Accounts::insert(
+            address_of(ALICE),
+            Account {
+                nonce: U256::zero(),
+                balance: U256::from(50_000_123),
+            },
+        );
+
+        assert_ok!(SSVM::withdraw_balance(Origin::signed(ALICE), 50));
+
+        assert_eq!(Balances::free_balance(ALICE), 1_000_000_000 + 50);
+        assert_eq!(Balances::free_balance(SSVM::account_id()), 1_000 - 50);
+        assert_eq!(evm_balance(ALICE), U256::from(123));
+    });
+}
+
+#[test]
+fn withdrawal_fails_if_it_reaps_the_module_account() {
+    new_test_ext().execute_with(|| {
+        assert_ok!(SSVM::deposit_balance(Origin::signed(BOB), 100));
+
+        assert_noop!(
+            SSVM::withdraw_balance(Origin::signed(ALICE), 95),
+            BalancesError::KeepAlive
+        );
+    });
+}
+
+#[test]
+fn withdrawal_fails_if_the_recipient_stays_below_the_existential_deposit() {
+    new_test_ext().execute_with(|| {
+        let charlie = 3;
+        assert_ok!(SSVM::deposit_balance(Origin::signed(BOB), 1_000));
+        Accounts::insert(
+            address_of(charlie),
+            Account {
+                nonce: U256::zero(),
+                balance: U256::from(5_000_000),
+            },
+        );
+
+        assert_noop!(
+            SSVM::withdraw_balance(Origin::signed(charlie), 5),
+            BalancesError::ExistentialDeposit
+        );
+    });
+}
+
+#[test]
+fn selfdestruct_moves_the_balance_and_removes_the_contract() {
+    new_test_ext().execute_with(|| {
+        let contract = contract_address(1);
+        let beneficiary = contract_address(2);
+        deploy(contract, destroyer(beneficiary));
+        Accounts::insert(
+            contract,
+            Account {
+                nonce: U256::one(),
+                balance: U256::from(1_000),
+            },
+        );
+
+        call(contract);
+
+        assert_eq!(SSVM::balance_of(&beneficiary), U256::from(1_000));
+        assert_eq!(SSVM::balance_of(&contract), U256::zero());
+        assert_eq!(SSVM::nonce_of(&contract), U256::zero());
+        assert!(SSVM::account_code(&contract).is_empty());
+        // The storage written before self-destructing goes with the contract.
+        assert_eq!(SSVM::storage_at(&contract, &H256::zero()), H256::zero());
+    });
+}
+
+#[test]
+fn selfdestruct_to_itself_burns_the_balance() {
+    new_test_ext().execute_with(|| {
+        let contract = contract_address(1);
+        deploy(contract, destroyer(contract));
+        Accounts::insert(
+            contract,
+            Account {
+                nonce: U256::one(),
+                balance: U256::from(1_000),
+            },
+        );
+
+        call(contract);
+
+        assert_eq!(SSVM::balance_of(&contract), U256::zero());
+        assert!(SSVM::account_code(&contract).is_empty());
+    });
+}
+
+#[test]
+fn selfdestructed_contract_can_be_created_again() {
+    new_test_ext().execute_with(|| {
+        let address = create_address(address_of(ALICE), U256::zero());
+        deploy(address, destroyer(address_of(BOB)));
+        // Call from `BOB`, so that the nonce of `ALICE` still creates at
+        // `address`.
+        call_from(BOB, address);
+        assert!(SSVM::account_code(&address).is_empty());
+
+        let runtime = recorder(None);
+        assert_ok!(SSVM::create(
+            Origin::signed(ALICE),
+            address_of(ALICE),
+            init_code(&runtime),
+            U256::zero(),
+            GAS_LIMIT,
+            U256::zero(),
+        ));
+
+        assert_eq!(SSVM::account_code(&address), runtime);
+        assert_eq!(SSVM::nonce_of(&address_of(ALICE)), U256::one());
+    });
+}
@@ -0,0 +1,538 @@
+// Copyright (C) 2020 Second State.
+// This file is part of Pallet-SSVM.
+
+// Pallet-SSVM is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+
+// Pallet-SSVM is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+//! Decoding and signature recovery of signed Ethereum transactions: legacy
+//! transactions, with or without EIP-155 replay protection, and EIP-2718
+//! typed EIP-2930 and EIP-1559 transactions.
+
+use codec::{Decode, Encode};
+use rlp::{DecoderError, Rlp, RlpStream};
+#[cfg(feature = "std")]
+use serde::{Deserialize, Serialize};
+use sp_core::{H160, H256, U256};
+use sp_io::hashing::keccak_256;
+use sp_std::vec::Vec;
+
+/// EIP-2718 type of EIP-2930 transactions.
+pub const EIP2930_TYPE: u8 = 1;
+/// EIP-2718 type of EIP-1559 transactions.
+pub const EIP1559_TYPE: u8 = 2;
+
+/// Half of the secp256k1 curve order. Signatures with a higher `s` are
+/// malleable and rejected, as in EIP-2.
+const SECP256K1_HALF_N: [u8; 32] = [
+    0x7f, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff,
+    0x5d, 0x57, 0x6e, 0x73, 0x57, 0xa4, 0x50, 0x1d, 0xdf, 0xe9, 0x2f, 0x46, 0x68, 0x1b, 0x20, 0xa0,
+];
+
+#[derive(Clone, Copy, Eq, PartialEq, Encode, Decode)]
+#[cfg_attr(feature = "std", derive(Debug))]
+/// Reason a signed Ethereum transaction was rejected.
+pub enum TransactionError {
+    /// The payload is not a well-formed RLP encoded transaction.
+    InvalidRlp,
+    /// The EIP-2718 transaction type is not supported.
+    UnsupportedType,
+    /// The signature is malformed or does not recover a sender.
+    InvalidSignature,
+}
+
+impl From<DecoderError> for TransactionError {
+    fn from(_: DecoderError) -> Self {
+        TransactionError::InvalidRlp
+    }
+}
+
+#[derive(Clone, Copy, Eq, PartialEq, Encode, Decode)]
+#[cfg_attr(feature = "std", derive(Debug, Serialize, Deserialize))]
+/// Recipient of a transaction.
+pub enum TransactionAction {
+    /// Message call to an address.
+    Call(H160),
+    /// Contract creation.
+    Create,
+}
+
+impl TransactionAction {
+    fn decode(rlp: &Rlp) -> Result<Self, DecoderError> {
+        if rlp.is_empty() {
+            if rlp.is_data() {
+                Ok(TransactionAction::Create)
+            } else {
+                Err(DecoderError::RlpExpectedToBeData)
+            }
+        } else {
+            Ok(TransactionAction::Call(rlp.as_val()?))
+        }
+    }
+
+    fn append(&self, stream: &mut RlpStream) {
+        match self {
+            TransactionAction::Call(address) => stream.append(address),
+            TransactionAction::Create => stream.append_empty_data(),
+        };
+    }
+}
+
+#[derive(Clone, Eq, PartialEq, Encode, Decode)]
+#[cfg_attr(feature = "std", derive(Debug, Serialize, Deserialize))]
+/// Storage keys of an address warmed up by an EIP-2930 access list.
+pub struct AccessListItem {
+    pub address: H160,
+    pub storage_keys: Vec<H256>,
+}
+
+fn decode_access_list(rlp: &Rlp) -> Result<Vec<AccessListItem>, DecoderError> {
+    rlp.iter()
+        .map(|item| {
+            if item.item_count()? != 2 {
+                return Err(DecoderError::RlpIncorrectListLen);
+            }
+            Ok(AccessListItem {
+                address: item.val_at(0)?,
+                storage_keys: item.list_at(1)?,
+            })
+        })
+        .collect()
+}
+
+fn append_access_list(stream: &mut RlpStream, access_list: &[AccessListItem]) {
+    stream.begin_list(access_list.len());
+    for item in access_list {
+        stream.begin_list(2);
+        stream.append(&item.address);
+        stream.append_list::<H256, H256>(&item.storage_keys);
+    }
+}
+
+#[derive(Clone, Eq, PartialEq, Encode, Decode)]
+#[cfg_attr(feature = "std", derive(Debug))]
+/// Legacy transaction. `v` is 27 or 28, or `chain_id * 2 + 35` or `+ 36`
+/// with EIP-155 replay protection.
+pub struct LegacyTransaction {
+    pub nonce: U256,
+    pub gas_price: U256,
+    pub gas_limit: U256,
+    pub action: TransactionAction,
+    pub value: U256,
+    pub input: Vec<u8>,
+    pub v: u64,
+    pub r: H256,
+    pub s: H256,
+}
+
+#[derive(Clone, Eq, PartialEq, Encode, Decode)]
+#[cfg_attr(feature = "std", derive(Debug))]
+/// EIP-2930 transaction with an access list.
+pub struct Eip2930Transaction {
+    pub chain_id: u64,
+    pub nonce: U256,
+    pub gas_price: U256,
+    pub gas_limit: U256,
+    pub action: TransactionAction,
+    pub value: U256,
+    pub input: Vec<u8>,
+    pub access_list: Vec<AccessListItem>,
+    pub odd_y_parity: bool,
+    pub r: H256,
+    pub s: H256,
+}
+
+#[derive(Clone, Eq, PartialEq, Encode, Decode)]
+#[cfg_attr(feature = "std", derive(Debug))]
+/// EIP-1559 transaction with a priority fee and a fee cap.
+pub struct Eip1559Transaction {
+    pub chain_id: u64,
+    pub nonce: U256,
+    pub max_priority_fee_per_gas: U256,
+    pub max_fee_per_gas: U256,
+    pub gas_limit: U256,
+    pub action: TransactionAction,
+    pub value: U256,
+    pub input: Vec<u8>,
+    pub access_list: Vec<AccessListItem>,
+    pub odd_y_parity: bool,
+    pub r: H256,
+    pub s: H256,
+}
+
+#[derive(Clone, Eq, PartialEq, Encode, Decode)]
+#[cfg_attr(feature = "std", derive(Debug))]
+/// Signed Ethereum transaction.
+pub enum Transaction {
+    Legacy(LegacyTransaction),
+    Eip2930(Eip2930Transaction),
+    Eip1559(Eip1559Transaction),
+}
+
+/// Decode a signed typed transaction payload, the RLP list following the
+/// type byte, with `fields` fields before the signature.
+fn decode_typed_fields(payload: &[u8], fields: usize) -> Result<Rlp, DecoderError> {
+    let rlp = Rlp::new(payload);
+    ensure_exact(&rlp, payload)?;
+    if rlp.item_count()? != fields + 3 {
+        return Err(DecoderError::RlpIncorrectListLen);
+    }
+    Ok(rlp)
+}
+
+/// Reject payloads with trailing bytes after the RLP item.
+fn ensure_exact(rlp: &Rlp, payload: &[u8]) -> Result<(), DecoderError> {
+    if !rlp.is_list() {
+        return Err(DecoderError::RlpExpectedToBeList);
+    }
+    let info = rlp.payload_info()?;
+    if info.header_len + info.value_len != payload.len() {
+        return Err(DecoderError::RlpInconsistentLengthAndData);
+    }
+    Ok(())
+}
+
+fn decode_parity(rlp: &Rlp, index: usize) -> Result<bool, DecoderError> {
+    match rlp.val_at::<u8>(index)? {
+        0 => Ok(false),
+        1 => Ok(true),
+        _ => Err(DecoderError::Custom("invalid y parity")),
+    }
+}
+
+impl Transaction {
+    /// Decode a raw transaction, as sent to `eth_sendRawTransaction`.
+    pub fn decode(bytes: &[u8]) -> Result<Self, TransactionError> {
+        match bytes.first() {
+            None => Err(TransactionError::InvalidRlp),
+            // An RLP list prefix starts a legacy transaction.
+            Some(first) if *first >= 0xc0 => Self::decode_legacy(bytes),
+            Some(&EIP2930_TYPE) => Self::decode_eip2930(&bytes[1..]),
+            Some(&EIP1559_TYPE) => Self::decode_eip1559(&bytes[1..]),
+            Some(_) => Err(TransactionError::UnsupportedType),
+        }
+    }
+
+    fn decode_legacy(bytes: &[u8]) -> Result<Self, TransactionError> {
+        let rlp = Rlp::new(bytes);
+        ensure_exact(&rlp, bytes)?;
+        if rlp.item_count()? != 9 {
+            return Err(DecoderError::RlpIncorrectListLen.into());
+        }
+        Ok(Transaction::Legacy(LegacyTransaction {
+            nonce: rlp.val_at(0)?,
+            gas_price: rlp.val_at(1)?,
+            gas_limit: rlp.val_at(2)?,
+            action: TransactionAction::decode(&rlp.at(3)?)?,
+            value: rlp.val_at(4)?,
+            input: rlp.val_at(5)?,
+            v: rlp.val_at(6)?,
+            r: rlp.val_at(7)?,
+            s: rlp.val_at(8)?,
+        }))
+    }
+
+    fn decode_eip2930(payload: &[u8]) -> Result<Self, TransactionError> {
+        let rlp = decode_typed_fields(payload, 8)?;
+        Ok(Transaction::Eip2930(Eip2930Transaction {
+            chain_id: rlp.val_at(0)?,
+            nonce: rlp.val_at(1)?,
+            gas_price: rlp.val_at(2)?,
+            gas_limit: rlp.val_at(3)?,
+            action: TransactionAction::decode(&rlp.at(4)?)?,
+            value: rlp.val_at(5)?,
+            input: rlp.val_at(6)?,
+            access_list: decode_access_list(&rlp.at(7)?)?,
+            odd_y_parity: decode_parity(&rlp, 8)?,
+            r: rlp.val_at(9)?,
+            s: rlp.val_at(10)?,
+        }))
+    }
+
+    fn decode_eip1559(payload: &[u8]) -> Result<Self, TransactionError> {
+        let rlp = decode_typed_fields(payload, 9)?;
+        Ok(Transaction::Eip1559(Eip1559Transaction {
+            chain_id: rlp.val_at(0)?,
+            nonce: rlp.val_at(1)?,
+            max_priority_fee_per_gas: rlp.val_at(2)?,
+            max_fee_per_gas: rlp.val_at(3)?,
+            gas_limit: rlp.val_at(4)?,
+            action: TransactionAction::decode(&rlp.at(5)?)?,
+            value: rlp.val_at(6)?,
+            input: rlp.val_at(7)?,
+            access_list: decode_access_list(&rlp.at(8)?)?,
+            odd_y_parity: decode_parity(&rlp, 9)?,
+            r: rlp.val_at(10)?,
+            s: rlp.val_at(11)?,
+        }))
+    }
+
+    pub fn nonce(&self) -> U256 {
+        match self {
+            Transaction::Legacy(tx) => tx.nonce,
+            Transaction::Eip2930(tx) => tx.nonce,
+            Transaction::Eip1559(tx) => tx.nonce,
+        }
+    }
+
+    pub fn gas_limit(&self) -> U256 {
+        match self {
+            Transaction::Legacy(tx) => tx.gas_limit,
+            Transaction::Eip2930(tx) => tx.gas_limit,
+            Transaction::Eip1559(tx) => tx.gas_limit,
+        }
+    }
+
+    /// Gas price the sender pays at most, the fee cap of EIP-1559
+    /// transactions.
+    pub fn max_gas_price(&self) -> U256 {
+        match self {
+            Transaction::Legacy(tx) => tx.gas_price,
+            Transaction::Eip2930(tx) => tx.gas_price,
+            Transaction::Eip1559(tx) => tx.max_fee_per_gas,
+        }
+    }
+
+    pub fn action(&self) -> TransactionAction {
+        match self {
+            Transaction::Legacy(tx) => tx.action,
+            Transaction::Eip2930(tx) => tx.action,
+            Transaction::Eip1559(tx) => tx.action,
+        }
+    }
+
+    pub fn value(&self) -> U256 {
+        match self {
+            Transaction::Legacy(tx) => tx.value,
+            Transaction::Eip2930(tx) => tx.value,
+            Transaction::Eip1559(tx) => tx.value,
+        }
+    }
+
+    pub fn input(&self) -> &[u8] {
+        match self {
+            Transaction::Legacy(tx) => &tx.input,
+            Transaction::Eip2930(tx) => &tx.input,
+            Transaction::Eip1559(tx) => &tx.input,
+        }
+    }
+
+    /// Chain id the transaction is signed for, `None` for a legacy
+    /// transaction without replay protection.
+    pub fn chain_id(&self) -> Option<u64> {
+        match self {
+            Transaction::Legacy(tx) if tx.v >= 35 => Some((tx.v - 35) / 2),
+            Transaction::Legacy(_) => None,
+            Transaction::Eip2930(tx) => Some(tx.chain_id),
+            Transaction::Eip1559(tx) => Some(tx.chain_id),
+        }
+    }
+
+    /// Hash of the transaction fields covered by the signature.
+    pub fn signing_hash(&self) -> H256 {
+        let mut stream = RlpStream::new();
+        let mut payload = Vec::new();
+        match self {
+            Transaction::Legacy(tx) => {
+                let chain_id = self.chain_id();
+                stream.begin_list(if chain_id.is_some() { 9 } else { 6 });
+                stream.append(&tx.nonce);
+                stream.append(&tx.gas_price);
+                stream.append(&tx.gas_limit);
+                tx.action.append(&mut stream);
+                stream.append(&tx.value);
+                stream.append(&tx.input);
+                if let Some(chain_id) = chain_id {
+                    stream.append(&chain_id);
+                    stream.append(&0u8);
+                    stream.append(&0u8);
+                }
+            }
+            Transaction::Eip2930(tx) => {
+                payload.push(EIP2930_TYPE);
+                stream.begin_list(8);
+                stream.append(&tx.chain_id);
+                stream.append(&tx.nonce);
+                stream.append(&tx.gas_price);
+                stream.append(&tx.gas_limit);
+                tx.action.append(&mut stream);
+                stream.append(&tx.value);
+                stream.append(&tx.input);
+                append_access_list(&mut stream, &tx.access_list);
+            }
+            Transaction::Eip1559(tx) => {
+                payload.push(EIP1559_TYPE);
+                stream.begin_list(9);
+                stream.append(&tx.chain_id);
+                stream.append(&tx.nonce);
+                stream.append(&tx.max_priority_fee_per_gas);
+                stream.append(&tx.max_fee_per_gas);
+                stream.append(&tx.gas_limit);
+                tx.action.append(&mut stream);
+                stream.append(&tx.value);
+                stream.append(&tx.input);
+                append_access_list(&mut stream, &tx.access_list);
+            }
+        }
+        payload.extend_from_slice(&stream.out());
+        H256::from(keccak_256(&payload))
+    }
+
+    /// Signature as `r || s || recovery id`.
+    fn signature(&self) -> Result<[u8; 65], TransactionError> {
+        let (r, s, recovery_id) = match self {
+            Transaction::Legacy(tx) => {
+                let recovery_id = match tx.v {
+                    27 | 28 => tx.v - 27,
+                    v if v >= 35 => (v - 35) % 2,
+                    _ => return Err(TransactionError::InvalidSignature),
+                };
+                (tx.r, tx.s, recovery_id as u8)
+            }
+            Transaction::Eip2930(tx) => (tx.r, tx.s, tx.odd_y_parity as u8),
+            Transaction::Eip1559(tx) => (tx.r, tx.s, tx.odd_y_parity as u8),
+        };
+        if r.is_zero() || s.is_zero() || s.as_bytes() > &SECP256K1_HALF_N[..] {
+            return Err(TransactionError::InvalidSignature);
+        }
+        let mut signature = [0u8; 65];
+        signature[0..32].copy_from_slice(r.as_bytes());
+        signature[32..64].copy_from_slice(s.as_bytes());
+        signature[64] = recovery_id;
+        Ok(signature)
+    }
+
+    /// Recover the sender from the signature.
+    pub fn recover_sender(&self) -> Result<H160, TransactionError> {
+        let signature = self.signature()?;
+        recover_signer(&signature, &self.signing_hash()).ok_or(TransactionError::InvalidSignature)
+    }
+}
+
+/// Recover the address that signed `hash` with a `r || s || recovery id`
+/// signature. The recovery runs natively through the secp256k1 host
+/// function rather than being interpreted in the runtime.
+pub fn recover_signer(signature: &[u8; 65], hash: &H256) -> Option<H160> {
+    let pubkey = sp_io::crypto::secp256k1_ecdsa_recover(signature, &hash.0).ok()?;
+    Some(H160::from_slice(&keccak_256(&pubkey[..])[12..]))
+}
+
+#[derive(Clone, Eq, PartialEq, Encode, Decode)]
+#[cfg_attr(feature = "std", derive(Debug))]
+/// Decoded transaction with its hash and recovered sender, so that the
+/// signature is only recovered once.
+pub struct CheckedTransaction {
+    pub transaction: Transaction,
+    /// Keccak hash of the raw transaction.
+    pub hash: H256,
+    pub sender: H160,
+}
+
+impl CheckedTransaction {
+    /// Decode a raw transaction and recover its sender.
+    pub fn from_raw(bytes: &[u8]) -> Result<Self, TransactionError> {
+        let transaction = Transaction::decode(bytes)?;
+        let sender = transaction.recover_sender()?;
+        Ok(CheckedTransaction {
+            transaction,
+            hash: H256::from(keccak_256(bytes)),
+            sender,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Signed transaction of the EIP-155 example, with chain id 1.
+    const EIP155_EXAMPLE: &str = "f86c098504a817c800825208943535353535353535353535353535353535353535880de0b6b3a76400008025a028ef61340bd939bc2195fe537567866003e1a15d3c71ff63e1590620aa636276a067cbe9d8997f761aecb703304b3800ccf555c9f3dc64214b297fb1966a3b6d83";
+
+    /// Order of the secp256k1 curve.
+    const SECP256K1_N: &str = "fffffffffffffffffffffffffffffffebaaedce6af48a03bbfd25e8cd0364141";
+
+    fn example() -> Vec<u8> {
+        hex::decode(EIP155_EXAMPLE).unwrap()
+    }
+
+    #[test]
+    fn decodes_known_vector() {
+        let raw = example();
+        let checked = CheckedTransaction::from_raw(&raw).unwrap();
+
+        assert_eq!(checked.transaction.nonce(), U256::from(9));
+        assert_eq!(
+            checked.transaction.max_gas_price(),
+            U256::from(20_000_000_000u64)
+        );
+        assert_eq!(checked.transaction.gas_limit(), U256::from(21_000));
+        assert_eq!(
+            checked.transaction.action(),
+            TransactionAction::Call(H160::repeat_byte(0x35))
+        );
+        assert_eq!(checked.transaction.value(), U256::exp10(18));
+        assert_eq!(checked.transaction.chain_id(), Some(1));
+        assert_eq!(
+            checked.transaction.signing_hash(),
+            H256::from_slice(
+                &hex::decode("daf5a779ae972f972197303d7b574746c7ef83eadac0f2791ad23db92e4c8e53")
+                    .unwrap()
+            )
+        );
+        assert_eq!(
+            checked.sender,
+            H160::from_slice(&hex::decode("9d8a62f656a8d1615c1294fd71e9cfb3e4855a4f").unwrap())
+        );
+        assert_eq!(checked.hash, H256::from(keccak_256(&raw)));
+    }
+
+    #[test]
+    fn rejects_truncated_transactions() {
+        let raw = example();
+        for len in 0..raw.len() {
+            assert!(Transaction::decode(&raw[..len]).is_err());
+        }
+    }
+
+    #[test]
+    fn rejects_trailing_bytes() {
+        let mut raw = example();
+        raw.push(0);
+        assert_eq!(Transaction::decode(&raw), Err(TransactionError::InvalidRlp));
+    }
+
+    #[test]
+    fn rejects_unsupported_types() {
+        let mut raw = example();
+        raw.insert(0, 3);
+        assert_eq!(
+            Transaction::decode(&raw),
+            Err(TransactionError::UnsupportedType)
+        );
+    }
+
+    #[test]
+    fn rejects_high_s() {
+        let mut transaction = Transaction::decode(&example()).unwrap();
+        if let Transaction::Legacy(ref mut tx) = transaction {
+            // `n - s` is the other valid, malleable, `s` of the signature.
+            let n = U256::from_big_endian(&hex::decode(SECP256K1_N).unwrap());
+            let s = n - U256::from_big_endian(tx.s.as_bytes());
+            s.to_big_endian(tx.s.as_bytes_mut());
+            tx.v ^= 1;
+        }
+        assert_eq!(
+            transaction.recover_sender(),
+            Err(TransactionError::InvalidSignature)
+        );
+    }
+}
@@ -0,0 +1,171 @@
+// Copyright (C) 2020 Second State.
+// This file is part of Pallet-SSVM.
+
+// Pallet-SSVM is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+
+// Pallet-SSVM is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+//! Request and response types shared by the runtime API and the RPC layer.
+//! They are SCALE encoded across the runtime API and serialized in the
+//! Ethereum JSON-RPC format: hashes and byte arrays as 0x-prefixed hex
+//! strings, and numbers as compact 0x-prefixed hex quantities.
+
+use codec::{Decode, Encode};
+#[cfg(feature = "std")]
+use serde::{Deserialize, Serialize};
+use sp_core::{H160, H256, U256};
+use sp_std::collections::btree_map::BTreeMap;
+use sp_std::vec::Vec;
+
+/// Serde helpers for byte arrays as 0x-prefixed hex strings.
+#[cfg(feature = "std")]
+pub mod hex_bytes {
+    use serde::{de::Error, Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(bytes: &[u8], serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&format!("0x{}", hex::encode(bytes)))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<u8>, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        if !s.starts_with("0x") {
+            return Err(D::Error::custom("hex bytes must be 0x-prefixed"));
+        }
+        hex::decode(&s[2..]).map_err(|e| D::Error::custom(format!("invalid hex bytes: {}", e)))
+    }
+}
+
+/// Serde helpers for optional byte arrays as 0x-prefixed hex strings.
+#[cfg(feature = "std")]
+pub mod hex_bytes_option {
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(
+        bytes: &Option<Vec<u8>>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        match bytes {
+            Some(bytes) => super::hex_bytes::serialize(bytes, serializer),
+            None => serializer.serialize_none(),
+        }
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Option<Vec<u8>>, D::Error> {
+        #[derive(Deserialize)]
+        struct Bytes(#[serde(with = "super::hex_bytes")] Vec<u8>);
+
+        Ok(Option::<Bytes>::deserialize(deserializer)?.map(|Bytes(bytes)| bytes))
+    }
+}
+
+/// Serde helpers for `u64` as compact 0x-prefixed hex quantities.
+#[cfg(feature = "std")]
+pub mod hex_quantity {
+    use serde::{de::Error, Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(value: &u64, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&format!("0x{:x}", value))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<u64, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        if !s.starts_with("0x") {
+            return Err(D::Error::custom("quantity must be 0x-prefixed"));
+        }
+        let digits = &s[2..];
+        if digits.is_empty() || (digits.len() > 1 && digits.starts_with('0')) {
+            return Err(D::Error::custom("quantity must be compact hex"));
+        }
+        u64::from_str_radix(digits, 16)
+            .map_err(|e| D::Error::custom(format!("invalid quantity: {}", e)))
+    }
+}
+
+#[derive(Clone, Eq, PartialEq, Encode, Decode, Default)]
+#[cfg_attr(feature = "std", derive(Debug, Serialize, Deserialize))]
+#[cfg_attr(feature = "std", serde(rename_all = "camelCase"))]
+/// Request of a read-only call or contract creation, as in `eth_call` and
+/// `eth_estimateGas`.
+pub struct CallRequest {
+    /// Sender, the zero address if not set.
+    pub from: Option<H160>,
+    /// Recipient, a contract creation if not set.
+    pub to: Option<H160>,
+    /// Gas limit.
+    pub gas: Option<U256>,
+    /// Gas price, the minimum gas price if not set.
+    pub gas_price: Option<U256>,
+    /// Transferred value.
+    pub value: Option<U256>,
+    /// Call data, or code for a contract creation.
+    #[cfg_attr(feature = "std", serde(default, with = "hex_bytes"))]
+    pub data: Vec<u8>,
+}
+
+#[derive(Clone, Eq, PartialEq, Encode, Decode, Default)]
+#[cfg_attr(feature = "std", derive(Debug, Serialize, Deserialize))]
+#[cfg_attr(feature = "std", serde(rename_all = "camelCase"))]
+/// Temporary changes to an account before a read-only call, as in the state
+/// override set of `eth_call`.
+pub struct StateOverride {
+    /// Balance replacing the account balance.
+    pub balance: Option<U256>,
+    /// Nonce replacing the account nonce.
+    pub nonce: Option<U256>,
+    /// Code replacing the account code.
+    #[cfg_attr(feature = "std", serde(default, with = "hex_bytes_option"))]
+    pub code: Option<Vec<u8>>,
+    /// Storage replacing the whole account storage.
+    pub state: Option<BTreeMap<H256, H256>>,
+    /// Storage slots replacing single slots of the account storage.
+    #[cfg_attr(feature = "std", serde(default))]
+    pub state_diff: BTreeMap<H256, H256>,
+}
+
+#[derive(Clone, Eq, PartialEq, Encode, Decode, Default)]
+#[cfg_attr(feature = "std", derive(Debug, Serialize, Deserialize))]
+#[cfg_attr(feature = "std", serde(rename_all = "camelCase"))]
+/// Request of a transaction, as in `eth_sendTransaction`.
+pub struct TransactionRequest {
+    /// Sender.
+    pub from: H160,
+    /// Recipient, a contract creation if not set.
+    pub to: Option<H160>,
+    /// Gas limit.
+    pub gas: Option<U256>,
+    /// Gas price, the minimum gas price if not set.
+    pub gas_price: Option<U256>,
+    /// Transferred value.
+    pub value: Option<U256>,
+    /// Call data, or code for a contract creation.
+    #[cfg_attr(feature = "std", serde(default, with = "hex_bytes"))]
+    pub data: Vec<u8>,
+    /// Nonce, the account nonce if not set.
+    pub nonce: Option<U256>,
+}
+
+#[derive(Clone, Eq, PartialEq, Encode, Decode, Default)]
+#[cfg_attr(feature = "std", derive(Debug, Serialize, Deserialize))]
+#[cfg_attr(feature = "std", serde(rename_all = "camelCase"))]
+/// State of an account.
+pub struct AccountInfo {
+    /// Account address.
+    pub address: H160,
+    /// Account nonce.
+    pub nonce: U256,
+    /// Account balance.
+    pub balance: U256,
+    /// Keccak hash of the account code.
+    pub code_hash: H256,
+}
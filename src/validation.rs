@@ -0,0 +1,246 @@
+// Copyright (C) 2020 Second State.
+// This file is part of Pallet-SSVM.
+
+// Pallet-SSVM is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+
+// Pallet-SSVM is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use crate::{abi, Call, EnsureAddressOrigin, Error, Module, Trait};
+use codec::{Decode, Encode};
+use frame_support::traits::{Get, IsSubType};
+use frame_support::weights::DispatchInfo;
+use frame_system::RawOrigin;
+use sp_core::{H160, U256};
+use sp_runtime::traits::SignedExtension;
+use sp_runtime::transaction_validity::{
+    InvalidTransaction, TransactionPriority, TransactionValidity, TransactionValidityError,
+    ValidTransaction,
+};
+use sp_std::{marker::PhantomData, vec, vec::Vec};
+
+/// Custom `InvalidTransaction` code for a gas price below the minimum.
+pub const INVALID_GAS_PRICE: u8 = 1;
+/// Custom `InvalidTransaction` code for an overflowing fee or payment.
+pub const INVALID_FEE: u8 = 2;
+/// Custom `InvalidTransaction` code for a transaction signed for another chain.
+pub const INVALID_CHAIN_ID: u8 = 3;
+/// Custom `InvalidTransaction` code for a transaction without replay protection.
+pub const UNPROTECTED_TRANSACTION: u8 = 4;
+/// Custom `InvalidTransaction` code for a transaction input above the size limit.
+pub const INPUT_TOO_LARGE: u8 = 5;
+/// Custom `InvalidTransaction` code for a call with input to an address without code.
+pub const NO_CODE: u8 = 6;
+/// Custom `InvalidTransaction` code for a sender over its rate limit.
+pub const RATE_LIMITED: u8 = 7;
+
+impl<T: Trait> From<Error<T>> for InvalidTransaction {
+    fn from(err: Error<T>) -> Self {
+        match err {
+            Error::<T>::BalanceLow => InvalidTransaction::Payment,
+            Error::<T>::GasPriceTooLow => InvalidTransaction::Custom(INVALID_GAS_PRICE),
+            Error::<T>::FeeOverflow | Error::<T>::PaymentOverflow => {
+                InvalidTransaction::Custom(INVALID_FEE)
+            }
+            Error::<T>::InvalidNonce => InvalidTransaction::Stale,
+            Error::<T>::InvalidChainId => InvalidTransaction::Custom(INVALID_CHAIN_ID),
+            Error::<T>::UnprotectedTransaction => {
+                InvalidTransaction::Custom(UNPROTECTED_TRANSACTION)
+            }
+            Error::<T>::InputTooLarge => InvalidTransaction::Custom(INPUT_TOO_LARGE),
+            Error::<T>::NoCode => InvalidTransaction::Custom(NO_CODE),
+            Error::<T>::InvalidPayerSignature => InvalidTransaction::BadProof,
+            Error::<T>::RateLimited => InvalidTransaction::Custom(RATE_LIMITED),
+            _ => InvalidTransaction::Call,
+        }
+    }
+}
+
+/// Transaction priority derived from the effective tip, i.e. the part of the
+/// gas price above the current minimum gas price. Transactions from the same sender
+/// with the same nonce are replaced in the pool by one with a higher tip.
+pub fn transaction_priority<T: Trait>(gas_price: U256) -> TransactionPriority {
+    let tip = gas_price.saturating_sub(Module::<T>::min_gas_price());
+    if tip > U256::from(TransactionPriority::max_value()) {
+        TransactionPriority::max_value()
+    } else {
+        tip.low_u64()
+    }
+}
+
+/// Check the `v` value of an Ethereum transaction signature against EIP-155.
+/// Protected transactions must be signed for `T::ChainId`, and unprotected
+/// ones, with `v` of 27 or 28, are only accepted if
+/// `T::AllowUnprotectedTransactions` is set.
+pub fn check_replay_protection<T: Trait>(v: u64) -> Result<(), Error<T>> {
+    match v {
+        27 | 28 if T::AllowUnprotectedTransactions::get() => Ok(()),
+        27 | 28 => Err(Error::<T>::UnprotectedTransaction),
+        v if v >= 35 && (v - 35) / 2 == T::ChainId::get() => Ok(()),
+        _ => Err(Error::<T>::InvalidChainId),
+    }
+}
+
+/// Validate a transaction carrying an explicit `nonce` for the transaction
+/// pool. A transaction provides the `(source, nonce)` tag and, if its nonce is
+/// ahead of the account nonce, requires the `(source, nonce - 1)` tag, so that
+/// out-of-order submissions wait in the pool until the gap is filled.
+pub fn validate_transaction<T: Trait>(
+    source: &H160,
+    nonce: U256,
+    value: U256,
+    gas_limit: u32,
+    gas_price: U256,
+) -> TransactionValidity {
+    let account_nonce = Module::<T>::nonce_of(source);
+    if nonce < account_nonce {
+        return Err(InvalidTransaction::Stale.into());
+    }
+
+    Module::<T>::check_transaction(source, None, value, gas_limit, gas_price)
+        .map_err(InvalidTransaction::from)?;
+    Module::<T>::check_rate_limit(source, gas_limit).map_err(InvalidTransaction::from)?;
+
+    let requires = if nonce > account_nonce {
+        vec![(source, nonce - U256::one()).encode()]
+    } else {
+        Vec::new()
+    };
+
+    Ok(ValidTransaction {
+        priority: transaction_priority::<T>(gas_price),
+        requires,
+        provides: vec![(source, nonce).encode()],
+        ..Default::default()
+    })
+}
+
+/// Reject SSVM `call` and `create` extrinsics at the transaction pool if they
+/// can not pay for themselves.
+#[derive(Encode, Decode, Clone, Eq, PartialEq)]
+pub struct CheckSsvmTransaction<T: Trait + Send + Sync>(PhantomData<T>);
+
+impl<T: Trait + Send + Sync> CheckSsvmTransaction<T> {
+    pub fn new() -> Self {
+        Self(PhantomData)
+    }
+}
+
+impl<T: Trait + Send + Sync> Default for CheckSsvmTransaction<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: Trait + Send + Sync> sp_std::fmt::Debug for CheckSsvmTransaction<T> {
+    fn fmt(&self, f: &mut sp_std::fmt::Formatter) -> sp_std::fmt::Result {
+        write!(f, "CheckSsvmTransaction")
+    }
+}
+
+impl<T: Trait + Send + Sync> SignedExtension for CheckSsvmTransaction<T>
+where
+    <T as frame_system::Trait>::Call: IsSubType<Module<T>, T>,
+{
+    const IDENTIFIER: &'static str = "CheckSsvmTransaction";
+    type AccountId = T::AccountId;
+    type Call = <T as frame_system::Trait>::Call;
+    type AdditionalSigned = ();
+    type DispatchInfo = DispatchInfo;
+    type Pre = ();
+
+    fn additional_signed(&self) -> Result<(), TransactionValidityError> {
+        Ok(())
+    }
+
+    fn validate(
+        &self,
+        who: &Self::AccountId,
+        call: &Self::Call,
+        _info: Self::DispatchInfo,
+        _len: usize,
+    ) -> TransactionValidity {
+        let method_input;
+        let (source, input, value, gas_limit, gas_price, fee_payer) = match call.is_sub_type() {
+            Some(Call::call(source, target, input, value, gas_limit, gas_price)) => {
+                Module::<T>::check_call_target(target, input).map_err(InvalidTransaction::from)?;
+                let fee_payer =
+                    Module::<T>::fee_payer(source, target, input, *gas_limit, *gas_price);
+                (source, input, value, gas_limit, gas_price, fee_payer)
+            }
+            Some(Call::call_method(
+                source,
+                target,
+                selector,
+                args,
+                value,
+                gas_limit,
+                gas_price,
+            )) => {
+                method_input = abi::encode_call(*selector, args).ok_or(InvalidTransaction::Call)?;
+                Module::<T>::check_call_target(target, &method_input)
+                    .map_err(InvalidTransaction::from)?;
+                let fee_payer =
+                    Module::<T>::fee_payer(source, target, &method_input, *gas_limit, *gas_price);
+                (
+                    source,
+                    &method_input,
+                    value,
+                    gas_limit,
+                    gas_price,
+                    fee_payer,
+                )
+            }
+            Some(Call::sponsored_call(
+                source,
+                target,
+                input,
+                value,
+                gas_limit,
+                gas_price,
+                fee_payer,
+                payer_signature,
+            )) => {
+                Module::<T>::check_call_target(target, input).map_err(InvalidTransaction::from)?;
+                Module::<T>::check_payer_signature(
+                    source,
+                    target,
+                    input,
+                    *value,
+                    *gas_limit,
+                    *gas_price,
+                    fee_payer,
+                    payer_signature,
+                )
+                .map_err(InvalidTransaction::from)?;
+                (source, input, value, gas_limit, gas_price, *fee_payer)
+            }
+            Some(Call::create(source, code, value, gas_limit, gas_price)) => {
+                (source, code, value, gas_limit, gas_price, *source)
+            }
+            _ => return Ok(ValidTransaction::default()),
+        };
+        let origin = RawOrigin::Signed(who.clone()).into();
+        T::EnsureAddressOrigin::ensure_address_origin(source, origin)
+            .map_err(|_| InvalidTransaction::BadProof)?;
+
+        Module::<T>::check_input_size(input).map_err(InvalidTransaction::from)?;
+        Module::<T>::check_sponsored_transaction(
+            source, &fee_payer, None, *value, *gas_limit, *gas_price,
+        )
+        .map_err(InvalidTransaction::from)?;
+        Module::<T>::check_rate_limit(source, *gas_limit).map_err(InvalidTransaction::from)?;
+        Ok(ValidTransaction {
+            priority: transaction_priority::<T>(*gas_price),
+            ..Default::default()
+        })
+    }
+}
@@ -0,0 +1,154 @@
+// Copyright (C) 2020 Second State.
+// This file is part of Pallet-SSVM.
+
+// Pallet-SSVM is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+
+// Pallet-SSVM is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+//! EVMC compatible engines executing contract code.
+//!
+//! Runtimes choose the engine of their chain with `Trait::Vm`. The pallet
+//! only provides `Ssvm`, which executes Ewasm. EVM bytecode needs an engine
+//! implementing `EvmcVm` outside the pallet, paired with `Ssvm` so the engine
+//! is picked by the format of the code. Contracts are tagged with the
+//! `CodeFormat` of their code when it is stored, and deployments in a format
+//! no engine accepts are rejected.
+
+#[cfg(feature = "std")]
+use crate::metrics;
+#[cfg(feature = "std")]
+use crate::Message;
+use codec::{Decode, Encode};
+#[cfg(feature = "std")]
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "std")]
+use sp_std::vec::Vec;
+#[cfg(feature = "std")]
+use ssvm::host::HostInterface;
+#[cfg(feature = "std")]
+use ssvm::types::{Revision, StatusCode};
+
+/// Magic number starting Wasm modules, and so Ewasm code.
+pub const WASM_MAGIC: [u8; 4] = [0x00, 0x61, 0x73, 0x6d];
+
+/// Whether `code` is Ewasm rather than EVM bytecode.
+pub fn is_ewasm(code: &[u8]) -> bool {
+    code.starts_with(&WASM_MAGIC)
+}
+
+#[derive(Clone, Copy, Eq, PartialEq, Encode, Decode)]
+#[cfg_attr(feature = "std", derive(Debug, Serialize, Deserialize))]
+/// Format of contract code, deciding the engine executing it.
+pub enum CodeFormat {
+    Ewasm,
+    /// EVM bytecode, e.g. compiled by solc.
+    Evm,
+}
+
+impl Default for CodeFormat {
+    fn default() -> Self {
+        CodeFormat::Ewasm
+    }
+}
+
+impl CodeFormat {
+    /// Format of `code`, Ewasm if it starts with the Wasm magic number.
+    pub fn detect(code: &[u8]) -> Self {
+        if is_ewasm(code) {
+            CodeFormat::Ewasm
+        } else {
+            CodeFormat::Evm
+        }
+    }
+}
+
+/// An EVMC compatible engine. Execution only exists natively.
+pub trait EvmcVm {
+    /// Whether the engine executes code of `format`.
+    fn accepts(&self, format: CodeFormat) -> bool;
+
+    /// Execute `code` of `format` for `message` under `revision`, calling
+    /// back into `host`. Returns the output, gas left and status code.
+    #[cfg(feature = "std")]
+    fn execute(
+        &self,
+        host: Box<dyn HostInterface>,
+        revision: Revision,
+        message: &Message,
+        code: &[u8],
+        format: CodeFormat,
+    ) -> (Vec<u8>, i64, StatusCode);
+}
+
+/// Second State's SSVM, executing Ewasm.
+#[derive(Default)]
+pub struct Ssvm;
+
+impl EvmcVm for Ssvm {
+    fn accepts(&self, format: CodeFormat) -> bool {
+        format == CodeFormat::Ewasm
+    }
+
+    #[cfg(feature = "std")]
+    fn execute(
+        &self,
+        host: Box<dyn HostInterface>,
+        revision: Revision,
+        message: &Message,
+        code: &[u8],
+        _format: CodeFormat,
+    ) -> (Vec<u8>, i64, StatusCode) {
+        let create2_salt = [0u8; 32];
+        let started = std::time::Instant::now();
+        let vm = ssvm::create();
+        metrics::record_instantiation(started.elapsed());
+        let (output, gas_left, status_code) = vm.execute(
+            host,
+            revision,
+            message.kind,
+            message.is_static,
+            message.depth,
+            message.gas.into(),
+            message.target.as_fixed_bytes(),
+            message.sender.as_fixed_bytes(),
+            &message.input[..],
+            &message.value.into(),
+            code,
+            &create2_salt,
+        );
+        (output.to_vec(), gas_left, status_code)
+    }
+}
+
+/// Two engines on one chain. Code is executed by `A` if it accepts its
+/// format, and by `B` otherwise.
+impl<A: EvmcVm, B: EvmcVm> EvmcVm for (A, B) {
+    fn accepts(&self, format: CodeFormat) -> bool {
+        self.0.accepts(format) || self.1.accepts(format)
+    }
+
+    #[cfg(feature = "std")]
+    fn execute(
+        &self,
+        host: Box<dyn HostInterface>,
+        revision: Revision,
+        message: &Message,
+        code: &[u8],
+        format: CodeFormat,
+    ) -> (Vec<u8>, i64, StatusCode) {
+        if self.0.accepts(format) {
+            self.0.execute(host, revision, message, code, format)
+        } else {
+            self.1.execute(host, revision, message, code, format)
+        }
+    }
+}